@@ -0,0 +1,34 @@
+// Box blur along one axis, used by two shaders chained into a two-stage blur.
+@group(0) @binding(0) var input: texture_storage_2d<rgba32float, read>;
+
+@group(0) @binding(1) var output: texture_storage_2d<rgba32float, write>;
+
+const RADIUS: i32 = 4;
+
+// Averages the pixels within `RADIUS` steps of `xy`, clamped to the texture.
+fn blur(xy: vec2<i32>, step: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(input));
+    var sum = vec4<f32>(0.0);
+    for (var i = -RADIUS; i <= RADIUS; i++) {
+        sum += textureLoad(input, clamp(xy + step * i, vec2<i32>(0), size - 1));
+    }
+    return sum / f32(2 * RADIUS + 1);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn horizontal(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let xy = vec2<i32>(global_id.xy);
+    if any(xy >= vec2<i32>(textureDimensions(output))) {
+        return;
+    }
+    textureStore(output, xy, blur(xy, vec2<i32>(1, 0)));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn vertical(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let xy = vec2<i32>(global_id.xy);
+    if any(xy >= vec2<i32>(textureDimensions(output))) {
+        return;
+    }
+    textureStore(output, xy, blur(xy, vec2<i32>(0, 1)));
+}
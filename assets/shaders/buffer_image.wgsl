@@ -0,0 +1,16 @@
+// Compute shader that writes a scalar field into a buffer, one f32 per cell.
+@group(0) @binding(0) var<storage, read_write> field: array<f32>;
+
+@group(0) @binding(1) var<uniform> time: f32;
+
+const SIZE: vec2<u32> = vec2<u32>(64u, 64u);
+
+// Writes rings moving out from the center, in [0, 1].
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= SIZE) {
+        return;
+    }
+    let distance = length(vec2<f32>(global_id.xy) - vec2<f32>(SIZE) * 0.5);
+    field[global_id.y * SIZE.x + global_id.x] = 0.5 + 0.5 * sin(distance * 0.5 - time * 4.0);
+}
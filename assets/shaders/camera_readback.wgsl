@@ -0,0 +1,9 @@
+// Compute shader that records which frame it was dispatched on.
+@group(0) @binding(0) var<storage, read_write> output: u32;
+
+@group(0) @binding(1) var<uniform> frame: u32;
+
+@compute @workgroup_size(1)
+fn main() {
+    output = frame;
+}
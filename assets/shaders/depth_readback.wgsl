@@ -0,0 +1,23 @@
+// Compute shader that finds the min and max depth of a view.
+// Non-negative floats compare the same as their bits, so the range is kept as atomic u32s.
+@group(0) @binding(0) var<storage, read_write> range: array<atomic<u32>, 2>;
+
+@group(1) @binding(0) var depth: texture_depth_2d;
+
+// Resets the range before folding in the pixels.
+@compute @workgroup_size(1)
+fn clear() {
+    atomicStore(&range[0], 0xffffffffu);
+    atomicStore(&range[1], 0u);
+}
+
+// Folds each pixel's depth into the range.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= textureDimensions(depth)) {
+        return;
+    }
+    let bits = bitcast<u32>(textureLoad(depth, vec2<i32>(global_id.xy), 0));
+    atomicMin(&range[0], bits);
+    atomicMax(&range[1], bits);
+}
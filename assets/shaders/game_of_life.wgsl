@@ -0,0 +1,74 @@
+// Conway's Game of Life, split into passes sharing one bind group.
+// `init` seeds the cells once per run, then `update` and `draw` run every frame.
+@group(0) @binding(0) var<storage, read_write> cells: array<u32>;
+
+@group(0) @binding(1) var<storage, read_write> next: array<u32>;
+
+@group(0) @binding(2) var texture: texture_storage_2d<rgba32float, write>;
+
+@group(0) @binding(3) var<uniform> seed: u32;
+
+// Integer hash used to seed the cells.
+fn hash(value: u32) -> u32 {
+    var state = value;
+    state = state ^ 2747636419u;
+    state = state * 2654435769u;
+    state = state ^ (state >> 16u);
+    state = state * 2654435769u;
+    state = state ^ (state >> 16u);
+    state = state * 2654435769u;
+    return state;
+}
+
+fn size() -> vec2<i32> {
+    return vec2<i32>(textureDimensions(texture));
+}
+
+// Index of a cell, wrapping around the edges.
+fn index(xy: vec2<i32>) -> u32 {
+    let s = size();
+    let wrapped = (xy + s) % s;
+    return u32(wrapped.y * s.x + wrapped.x);
+}
+
+// Randomly seeds about one in ten cells.
+@compute @workgroup_size(8, 8, 1)
+fn init(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let xy = vec2<i32>(global_id.xy);
+    if any(xy >= size()) {
+        return;
+    }
+    let random = hash(seed ^ ((global_id.y << 16u) | global_id.x));
+    cells[index(xy)] = u32(random % 10u == 0u);
+}
+
+// Computes the next generation from the live neighbor count.
+@compute @workgroup_size(8, 8, 1)
+fn update(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let xy = vec2<i32>(global_id.xy);
+    if any(xy >= size()) {
+        return;
+    }
+    var neighbors = 0u;
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            if dx != 0 || dy != 0 {
+                neighbors += cells[index(xy + vec2<i32>(dx, dy))];
+            }
+        }
+    }
+    let alive = cells[index(xy)] == 1u;
+    next[index(xy)] = u32(neighbors == 3u || (alive && neighbors == 2u));
+}
+
+// Copies the next generation back and draws it to the texture.
+@compute @workgroup_size(8, 8, 1)
+fn draw(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let xy = vec2<i32>(global_id.xy);
+    if any(xy >= size()) {
+        return;
+    }
+    let cell = next[index(xy)];
+    cells[index(xy)] = cell;
+    textureStore(texture, xy, vec4<f32>(vec3<f32>(f32(cell)), 1.0));
+}
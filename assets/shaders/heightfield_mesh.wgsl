@@ -0,0 +1,63 @@
+// Compute shader that triangulates an animated heightfield into a counted vertex buffer.
+
+// Matches `MeshVertex`.
+struct MeshVertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    uv: vec2<f32>,
+}
+
+// Same layout as `EventHeader` from `bevy_compute_readback::events`, followed by the vertices.
+struct Vertices {
+    count: atomic<u32>,
+    capacity: u32,
+    _padding: vec2<u32>,
+    vertices: array<MeshVertex>,
+}
+
+@group(0) @binding(0) var<storage, read_write> mesh: Vertices;
+
+@group(0) @binding(1) var<uniform> time: f32;
+
+const GRID: u32 = 32u;
+// Width of a cell in world units.
+const CELL: f32 = 0.25;
+
+fn height(cell: vec2<f32>) -> f32 {
+    return 0.5 * sin(cell.x * 0.4 + time) * cos(cell.y * 0.4 + time * 0.7);
+}
+
+// Vertex at a corner of the grid, with the normal from the height's gradient.
+fn vertex(cell: vec2<f32>) -> MeshVertex {
+    let dx = (height(cell + vec2(0.5, 0.0)) - height(cell - vec2(0.5, 0.0))) / CELL;
+    let dz = (height(cell + vec2(0.0, 0.5)) - height(cell - vec2(0.0, 0.5))) / CELL;
+    let xz = (cell - f32(GRID) * 0.5) * CELL;
+    return MeshVertex(
+        vec3(xz.x, height(cell), xz.y),
+        normalize(vec3(-dx, 1.0, -dz)),
+        cell / f32(GRID),
+    );
+}
+
+// Appends the two triangles of a cell, facing up.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= vec2(GRID)) {
+        return;
+    }
+    let start = atomicAdd(&mesh.count, 6u);
+    if start + 6u > mesh.capacity {
+        return;
+    }
+    let cell = vec2<f32>(global_id.xy);
+    let a = vertex(cell);
+    let b = vertex(cell + vec2(1.0, 0.0));
+    let c = vertex(cell + vec2(0.0, 1.0));
+    let d = vertex(cell + vec2(1.0, 1.0));
+    mesh.vertices[start] = a;
+    mesh.vertices[start + 1u] = c;
+    mesh.vertices[start + 2u] = b;
+    mesh.vertices[start + 3u] = b;
+    mesh.vertices[start + 4u] = c;
+    mesh.vertices[start + 5u] = d;
+}
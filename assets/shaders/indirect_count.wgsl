@@ -0,0 +1,12 @@
+// Compute shader that decides how many workgroups the fill shader dispatches.
+@group(0) @binding(0) var<storage, read_write> args: array<u32, 3>;
+
+@group(0) @binding(1) var<uniform> count: u32;
+
+// Writes `DispatchIndirectArgs` covering `count` invocations of 64.
+@compute @workgroup_size(1)
+fn main() {
+    args[0] = (count + 63u) / 64u;
+    args[1] = 1u;
+    args[2] = 1u;
+}
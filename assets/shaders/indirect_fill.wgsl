@@ -0,0 +1,18 @@
+// Compute shader dispatched indirectly, marking each invocation in the output.
+@group(0) @binding(0) var<storage, read_write> output: array<u32>;
+
+// Clears the whole output.
+@compute @workgroup_size(64)
+fn clear(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x < arrayLength(&output) {
+        output[global_id.x] = 0u;
+    }
+}
+
+// Marks each dispatched invocation.
+@compute @workgroup_size(64)
+fn fill(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x < arrayLength(&output) {
+        output[global_id.x] = 1u;
+    }
+}
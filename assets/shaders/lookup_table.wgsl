@@ -0,0 +1,12 @@
+// Compute shader that colors a texture using a lookup table owned by another plugin.
+@group(0) @binding(0) var texture: texture_storage_2d<rgba32float, write>;
+
+// Shared lookup table, bound in a second bind group.
+@group(1) @binding(0) var<storage, read> palette: array<vec4<f32>>;
+
+// Writes a palette color to each pixel, in vertical bands.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let band = (global_id.x / 8u) % arrayLength(&palette);
+    textureStore(texture, vec2<i32>(global_id.xy), palette[band]);
+}
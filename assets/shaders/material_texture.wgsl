@@ -0,0 +1,16 @@
+// Compute shader that draws a moving pattern into a texture sampled by a material.
+@group(0) @binding(0) var texture: texture_storage_2d<rgba8unorm, write>;
+
+@group(0) @binding(1) var<uniform> time: f32;
+
+// Writes rings moving outwards from the center.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(texture);
+    if any(global_id.xy >= size) {
+        return;
+    }
+    let uv = vec2<f32>(global_id.xy) / vec2<f32>(size);
+    let rings = sin(length(uv - 0.5) * 40.0 - time * 4.0) * 0.5 + 0.5;
+    textureStore(texture, vec2<i32>(global_id.xy), vec4<f32>(rings, uv, 1.0));
+}
@@ -0,0 +1,33 @@
+// Draws the paint events sent from the CPU into the canvas.
+#import bevy_compute_readback::events::InputEventHeader
+
+struct PaintEvent {
+    position: vec2<f32>,
+    radius: f32,
+    color: vec4<f32>,
+}
+
+struct PaintEvents {
+    header: InputEventHeader,
+    events: array<PaintEvent>,
+}
+
+@group(0) @binding(0) var texture: texture_storage_2d<rgba8unorm, write>;
+
+@group(0) @binding(1) var<storage, read> paints: PaintEvents;
+
+// Each texel takes the color of the last event covering it, and is left as is otherwise.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let xy = vec2<i32>(global_id.xy);
+    if any(xy >= vec2<i32>(textureDimensions(texture))) {
+        return;
+    }
+    let center = vec2<f32>(xy) + 0.5;
+    for (var index = 0u; index < paints.header.count; index++) {
+        let paint = paints.events[index];
+        if distance(center, paint.position) <= paint.radius {
+            textureStore(texture, xy, paint.color);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+// Compute shader for a long bake, adding to each value on every dispatch.
+@group(0) @binding(0) var<storage, read_write> values: array<f32>;
+
+@compute @workgroup_size(64, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= arrayLength(&values) {
+        return;
+    }
+    values[global_id.x] += 1.0;
+}
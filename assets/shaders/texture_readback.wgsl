@@ -1,5 +1,5 @@
 // Compute shader that populates a texture.
-@group(0) @binding(0) var texture: texture_storage_2d<rgba32float, write>;
+@group(0) @binding(0) var texture: texture_storage_2d<rgba8unorm, write>;
 
 @group(0) @binding(1) var<uniform> color: vec4<f32>;
 
@@ -12,6 +12,6 @@ fn f(xy: vec2<f32>) -> vec4<f32> {
 @compute @workgroup_size(1)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let texture_xy = vec2<i32>(global_id.xy);
-    let g = vec3<f32>(global_id) / 64.0;
+    let g = vec3<f32>(global_id) / 100.0;
     textureStore(texture, texture_xy, color * f(g.xy));
 }
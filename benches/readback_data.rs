@@ -0,0 +1,43 @@
+//! Benchmark delivering a large texture readback to every consumer, copying the bytes for each
+//! one as before [`ReadbackData`], or sharing one aligned copy.
+//! `cargo bench --bench readback_data`
+use std::hint::black_box;
+
+use bevy_compute_readback::ReadbackData;
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+
+/// A 4096x4096 `Rgba32Float` texture.
+const SIZE: usize = 4096 * 4096 * 16;
+/// The history, queue, channel, event and `LatestReadback`.
+const CONSUMERS: usize = 5;
+
+fn deliver(c: &mut Criterion) {
+    let readback = vec![1u8; SIZE];
+    let mut group = c.benchmark_group("deliver_256mb_readback");
+    group.sample_size(10);
+    group.bench_function("copied", |b| {
+        b.iter_batched(
+            || readback.clone(),
+            |data| {
+                let deliveries = (0..CONSUMERS).map(|_| data.clone()).collect::<Vec<_>>();
+                black_box((deliveries, data))
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("shared", |b| {
+        b.iter_batched(
+            || readback.clone(),
+            |data| {
+                let data = ReadbackData::new(&data);
+                let deliveries = (0..CONSUMERS).map(|_| data.clone()).collect::<Vec<_>>();
+                black_box((deliveries, data))
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, deliver);
+criterion_main!(benches);
@@ -0,0 +1,185 @@
+//! Example to demonstrate chaining compute shaders: a two-stage blur.
+//! The horizontal pass writes an intermediate texture that the vertical pass reads,
+//! so the vertical shader `depends_on` the horizontal one.
+//! Press Space to change the pattern, which resets both shaders.
+//! `cargo run --example blur`
+use std::borrow::Cow;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{AsBindGroup, Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{ComputeShader, ComputeShaderPlugin, ReadbackLimit, workgroup_count};
+
+/// Size of the textures.
+const SIZE: UVec3 = UVec3::new(64, 64, 1);
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<HorizontalBlur>::new().with_limit(ReadbackLimit::Finite(1)),
+            ComputeShaderPlugin::<VerticalBlur>::new()
+                .with_limit(ReadbackLimit::Finite(1))
+                .depends_on::<HorizontalBlur>(),
+        ))
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, setup)
+        .add_systems(Update, change_pattern_on_space)
+        .run();
+}
+
+/// Show the source and blurred textures side by side.
+fn setup(mut commands: Commands, textures: Res<BlurTextures>) {
+    commands.spawn(Camera2d);
+    for (image, x) in [(&textures.source, -200.0), (&textures.output, 200.0)] {
+        commands.spawn((
+            Sprite::from_image(image.clone()),
+            Transform {
+                translation: Vec3::new(x, 0.0, 0.0),
+                scale: Vec3::splat(5.0),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Redraw the source on Space. Only the first shader's input changes,
+/// and the second shader resets along with it.
+fn change_pattern_on_space(
+    inputs: Res<ButtonInput<KeyCode>>,
+    mut shader: ResMut<HorizontalBlur>,
+    mut images: ResMut<Assets<Image>>,
+    mut tile: Local<u32>,
+) {
+    if inputs.just_pressed(KeyCode::Space) {
+        *tile = (*tile % 4) + 1;
+        shader.input = images.add(checkerboard(2 << *tile));
+    }
+}
+
+/// Textures shared by both shaders.
+#[derive(Resource)]
+struct BlurTextures {
+    source: Handle<Image>,
+    intermediate: Handle<Image>,
+    output: Handle<Image>,
+}
+impl FromWorld for BlurTextures {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            source: world.add_asset(checkerboard(8)),
+            intermediate: world.add_asset(storage_image()),
+            output: world.add_asset(storage_image()),
+        }
+    }
+}
+
+/// Empty storage texture.
+fn storage_image() -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: SIZE.x,
+            height: SIZE.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &0f32.to_le_bytes().repeat(4),
+        TextureFormat::Rgba32Float,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    );
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC | TextureUsages::STORAGE_BINDING;
+    image
+}
+
+/// Storage texture with a black and white checkerboard of `tile` pixel squares.
+fn checkerboard(tile: u32) -> Image {
+    let mut image = storage_image();
+    image.data = Some(
+        (0..SIZE.x * SIZE.y)
+            .flat_map(|i| {
+                let (x, y) = (i % SIZE.x, i / SIZE.x);
+                let value = ((x / tile + y / tile) % 2) as f32;
+                [value, value, value, 1.0]
+            })
+            .flat_map(f32::to_le_bytes)
+            .collect(),
+    );
+    image
+}
+
+// First stage, blurring the source into the intermediate texture.
+#[derive(AsBindGroup, Resource, Clone, ExtractResource)]
+pub struct HorizontalBlur {
+    #[storage_texture(0, image_format=Rgba32Float, access=ReadOnly)]
+    input: Handle<Image>,
+
+    #[storage_texture(1, image_format=Rgba32Float, access=WriteOnly)]
+    output: Handle<Image>,
+}
+impl ComputeShader for HorizontalBlur {
+    fn compute_shader() -> ShaderRef {
+        "shaders/blur.wgsl".into()
+    }
+    fn entry_point() -> Cow<'static, str> {
+        "horizontal".into()
+    }
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+}
+impl FromWorld for HorizontalBlur {
+    fn from_world(world: &mut World) -> Self {
+        let textures = world.get_resource_or_init::<BlurTextures>();
+        Self {
+            input: textures.source.clone(),
+            output: textures.intermediate.clone(),
+        }
+    }
+}
+
+// Second stage, blurring the intermediate texture into the output.
+#[derive(AsBindGroup, Resource, Clone, ExtractResource)]
+pub struct VerticalBlur {
+    #[storage_texture(0, image_format=Rgba32Float, access=ReadOnly)]
+    input: Handle<Image>,
+
+    #[storage_texture(1, image_format=Rgba32Float, access=WriteOnly)]
+    output: Handle<Image>,
+}
+impl ComputeShader for VerticalBlur {
+    fn compute_shader() -> ShaderRef {
+        "shaders/blur.wgsl".into()
+    }
+    fn entry_point() -> Cow<'static, str> {
+        "vertical".into()
+    }
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+    /// Read back the fully blurred texture.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::texture(self.output.clone()))
+    }
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        info!("Blurred {} bytes", trigger.event().data.len());
+    }
+}
+impl FromWorld for VerticalBlur {
+    fn from_world(world: &mut World) -> Self {
+        let textures = world.get_resource_or_init::<BlurTextures>();
+        Self {
+            input: textures.intermediate.clone(),
+            output: textures.output.clone(),
+        }
+    }
+}
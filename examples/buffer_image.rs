@@ -0,0 +1,120 @@
+//! Example to demonstrate displaying a buffer readback as an image.
+//! The shader writes a scalar field into a storage buffer, which is converted to a gray image.
+//! `cargo run --example buffer_image`
+use bevy::{
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{AsBindGroup, BufferUsages, Extent3d, TextureFormat},
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{
+    BufferPacking, ComputeShader, ComputeShaderPlugin, ReadbackLimit, buffer_to_image,
+    workgroup_count,
+};
+
+/// Size of the scalar field.
+const SIZE: UVec3 = UVec3::new(64, 64, 1);
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<FieldComputeShader>::new().with_limit(ReadbackLimit::Infinite),
+        ))
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, setup)
+        .add_systems(Update, advance_time)
+        .run();
+}
+
+/// Image the readbacks are written to.
+#[derive(Resource)]
+struct FieldImage(Handle<Image>);
+
+/// Visualize the field as a sprite. The image is created by the first readback.
+fn setup(mut commands: Commands, images: Res<Assets<Image>>) {
+    let image = images.reserve_handle();
+    commands.spawn(Camera2d);
+    commands.spawn((
+        Sprite::from_image(image.clone()),
+        Transform {
+            scale: Vec3::splat(5.0),
+            ..default()
+        },
+    ));
+    commands.insert_resource(FieldImage(image));
+}
+
+/// Animate the field.
+fn advance_time(time: Res<Time>, mut shader: ResMut<FieldComputeShader>) {
+    shader.time = time.elapsed_secs();
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+// Time as plain data, so advancing it doesn't rebuild the bind group.
+#[data(1, f32)]
+pub struct FieldComputeShader {
+    // One f32 per cell of the field.
+    #[storage(0, visibility(compute))]
+    field: Handle<ShaderBuffer>,
+
+    // Seconds since startup, bound at 1.
+    pub time: f32,
+}
+impl From<&FieldComputeShader> for f32 {
+    fn from(shader: &FieldComputeShader) -> Self {
+        shader.time
+    }
+}
+impl ComputeShader for FieldComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/buffer_image.wgsl".into()
+    }
+    /// Number of workgroups needed to cover the field.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+    /// Read back the field.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::buffer(self.field.clone()))
+    }
+    /// Show the field in gray.
+    fn on_readback(trigger: On<ReadbackComplete>, mut world: DeferredWorld) {
+        let handle = world.resource::<FieldImage>().0.clone();
+        let extent = Extent3d {
+            width: SIZE.x,
+            height: SIZE.y,
+            depth_or_array_layers: SIZE.z,
+        };
+        if let Err(error) = buffer_to_image(
+            &trigger.event().data,
+            extent,
+            TextureFormat::Rgba32Float,
+            BufferPacking::F32,
+            &mut world.resource_mut::<Assets<Image>>(),
+            &handle,
+        ) {
+            warn!("Failed to show the field: {error}");
+        }
+    }
+}
+impl FromWorld for FieldComputeShader {
+    /// Initialize the shader with an empty field.
+    fn from_world(world: &mut World) -> Self {
+        let mut field = ShaderBuffer::from(vec![0f32; (SIZE.x * SIZE.y) as usize]);
+        field.buffer_description.usage |= BufferUsages::COPY_SRC;
+        Self {
+            field: world.add_asset(field),
+            time: 0.0,
+        }
+    }
+}
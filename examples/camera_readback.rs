@@ -0,0 +1,158 @@
+//! Example to demonstrate reading back what a camera rendered alongside a compute shader.
+//! Each frame the camera clears to a red level encoding the frame number, and the shader writes
+//! the same frame number to a buffer. Both readbacks of a frame report the same frame.
+//! `cargo run --example camera_readback`
+use bevy::{
+    asset::RenderAssetUsages,
+    camera::RenderTarget,
+    diagnostic::FrameCount,
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{
+            AsBindGroup, BufferUsages, Extent3d, TextureDimension, TextureFormat, TextureUsages,
+        },
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{
+    ComputeReadbackCamera, ComputeShader, ComputeShaderPlugin, ComputeShaderReadback,
+};
+
+/// Size of the camera's render target.
+const SIZE: UVec2 = UVec2::new(64, 64);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<FrameComputeShader>::new(),
+        ))
+        .init_resource::<ReadbackFrames>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, encode_frame)
+        .run();
+}
+
+/// Frames reported by the readbacks of the same frame, whichever arrives first.
+#[derive(Resource, Default)]
+struct ReadbackFrames {
+    dispatched: Option<u32>,
+    rendered: Option<u8>,
+}
+
+/// Spawn a camera rendering to an image that's read back, and show the image in the window.
+fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: SIZE.x,
+            height: SIZE.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    );
+    image.texture_descriptor.usage |=
+        TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING;
+    let image = images.add(image);
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: -1,
+            ..default()
+        },
+        RenderTarget::Image(image.clone().into()),
+        ComputeReadbackCamera::<FrameComputeShader>::default(),
+    ));
+    commands.spawn(Camera2d);
+    commands.spawn((
+        Sprite::from_image(image),
+        Transform {
+            scale: Vec3::splat(5.0),
+            ..default()
+        },
+    ));
+}
+
+/// Write the frame number to the shader input and the camera's clear color.
+fn encode_frame(
+    frame: Res<FrameCount>,
+    mut shader: ResMut<FrameComputeShader>,
+    mut cameras: Query<&mut Camera, With<ComputeReadbackCamera<FrameComputeShader>>>,
+) {
+    shader.frame = frame.0;
+    for mut camera in cameras.iter_mut() {
+        let red = (frame.0 % 256) as f32 / 255.0;
+        camera.clear_color = ClearColorConfig::Custom(LinearRgba::new(red, 0.0, 0.0, 1.0).into());
+    }
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+// Written in place every frame instead of rebuilding the bind group.
+#[data(1, u32)]
+pub struct FrameComputeShader {
+    // Buffer the shader writes the frame number to.
+    #[storage(0, visibility(compute))]
+    output: Handle<ShaderBuffer>,
+
+    // Frame number of this dispatch, bound at 1.
+    pub frame: u32,
+}
+impl From<&FrameComputeShader> for u32 {
+    fn from(shader: &FrameComputeShader) -> Self {
+        shader.frame
+    }
+}
+impl ComputeShader for FrameComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/camera_readback.wgsl".into()
+    }
+    /// A single invocation writes the frame number.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        UVec3::ONE
+    }
+    /// Read back the frame number. The camera's readback follows at index 1.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::buffer(self.output.clone()))
+    }
+    /// Compare the frame the camera rendered with the frame the shader dispatched.
+    fn on_readback(trigger: On<ReadbackComplete>, mut world: DeferredWorld) {
+        let event = trigger.event();
+        let Some(readback) = world.get::<ComputeShaderReadback<Self>>(event.entity) else {
+            return;
+        };
+        let index = readback.index;
+        let mut frames = world.resource_mut::<ReadbackFrames>();
+        if index == 0 {
+            frames.dispatched = Some(u32::from_le_bytes(event.data[..4].try_into().unwrap()));
+        } else {
+            // The red channel of the first pixel holds the frame number modulo 256.
+            frames.rendered = Some(event.data[0]);
+        }
+        if let (Some(dispatched), Some(rendered)) = (frames.dispatched, frames.rendered) {
+            info!(
+                "Dispatched frame {dispatched}, camera rendered frame {rendered} (expected {})",
+                dispatched % 256
+            );
+            *frames = ReadbackFrames::default();
+        }
+    }
+}
+impl FromWorld for FrameComputeShader {
+    /// Initialize the shader with an empty output buffer.
+    fn from_world(world: &mut World) -> Self {
+        let mut output = ShaderBuffer::from(0u32);
+        output.buffer_description.usage |= BufferUsages::COPY_SRC;
+        Self {
+            output: world.add_asset(output),
+            frame: 0,
+        }
+    }
+}
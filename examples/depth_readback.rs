@@ -0,0 +1,147 @@
+//! Example to demonstrate binding a camera's depth texture into a compute shader.
+//! The shader finds the min and max depth of each frame after the main pass, which is read back.
+//! `cargo run --example depth_readback`
+use bevy::{
+    core_pipeline::{Core3d, Core3dSystems},
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{AsBindGroup, BufferUsages, TextureUsages},
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+    window::PrimaryWindow,
+};
+use bevy_compute_readback::{
+    ComputePassSpec, ComputeShader, ComputeShaderPlugin, ComputeViewCamera, ComputeViewTexture,
+    ReadbackLimit, workgroup_count,
+};
+
+/// Matches `@workgroup_size(8, 8, 1)` of `main` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            // Read the depth once the main pass has written it.
+            ComputeShaderPlugin::<DepthRangeComputeShader>::new()
+                .with_limit(ReadbackLimit::Infinite)
+                .with_readback_interval(60)
+                .with_render_schedule(Core3d)
+                .after_set(Core3dSystems::MainPass),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (resize_to_window, rotate))
+        .run();
+}
+
+/// Spawn a few cubes at different depths and a camera whose depth texture can be bound.
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Cuboid::default());
+    let material = materials.add(Color::srgb(0.3, 0.5, 0.9));
+    for z in 0..4 {
+        commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(z as f32 * 1.5 - 2.0, 0.0, -(z as f32) * 3.0),
+        ));
+    }
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    commands.spawn((
+        Camera3d {
+            depth_texture_usages: (TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING)
+                .into(),
+            ..default()
+        },
+        // The depth texture is bound as `texture_depth_2d`, which can't be multisampled.
+        Msaa::Off,
+        Transform::from_xyz(0.0, 2.0, 6.0).looking_at(Vec3::new(0.0, 0.0, -3.0), Vec3::Y),
+        ComputeViewCamera::<DepthRangeComputeShader>::default(),
+    ));
+}
+
+/// Dispatch over the whole window.
+fn resize_to_window(
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut shader: ResMut<DepthRangeComputeShader>,
+) {
+    let size = window.physical_size();
+    if shader.size != size {
+        shader.size = size;
+    }
+}
+
+/// Rotate the cubes so the depth range changes.
+fn rotate(time: Res<Time>, mut transforms: Query<&mut Transform, With<Mesh3d>>) {
+    for mut transform in transforms.iter_mut() {
+        transform.rotate_y(time.delta_secs());
+    }
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct DepthRangeComputeShader {
+    // Min and max depth, as the bits of the floats.
+    #[storage(0, visibility(compute))]
+    range: Handle<ShaderBuffer>,
+
+    // Size of the view to cover.
+    size: UVec2,
+}
+impl ComputeShader for DepthRangeComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/depth_readback.wgsl".into()
+    }
+    /// Reset the range, then fold every pixel into it.
+    fn passes() -> Vec<ComputePassSpec> {
+        vec![
+            ComputePassSpec::new("clear").with_workgroups(UVec3::ONE),
+            ComputePassSpec::new("main"),
+        ]
+    }
+    /// Dispatch enough workgroups to cover the view.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(self.size.extend(1), LOCAL_SIZE)
+    }
+    /// The depth texture is bound at `@group(1) @binding(0)`.
+    fn view_textures() -> Vec<(u32, ComputeViewTexture)> {
+        vec![(0, ComputeViewTexture::Depth)]
+    }
+    /// Read back the range.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::buffer(self.range.clone()))
+    }
+    /// Log the depth range. Depth is reversed, so 1 is the near plane and 0 is infinitely far.
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        let depth = |bytes: &[u8]| f32::from_bits(u32::from_le_bytes(bytes.try_into().unwrap()));
+        let data = &trigger.event().data;
+        info!(
+            "Depth ranges from {} to {}",
+            depth(&data[0..4]),
+            depth(&data[4..8])
+        );
+    }
+}
+impl FromWorld for DepthRangeComputeShader {
+    /// Initialize the shader with an empty range.
+    fn from_world(world: &mut World) -> Self {
+        let mut range = ShaderBuffer::from(vec![0u32; 2]);
+        range.buffer_description.usage |= BufferUsages::COPY_SRC;
+        Self {
+            range: world.add_asset(range),
+            size: UVec2::ONE,
+        }
+    }
+}
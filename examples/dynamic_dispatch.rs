@@ -0,0 +1,138 @@
+//! Example to demonstrate a dispatch size that follows a texture resized at runtime.
+//! Press Up/Down to grow or shrink the texture; the compute dispatch follows its size.
+//! `cargo run --example dynamic_dispatch`
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{AsBindGroup, Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{ComputeShader, ComputeShaderPlugin, ReadbackLimit, workgroup_count};
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<ResizableComputeShader>::new()
+                .with_limit(ReadbackLimit::Finite(1)),
+        ))
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (resize_on_arrows, update_sprite))
+        .run();
+}
+
+/// Visualize the compute shader output as a sprite.
+fn setup(mut commands: Commands, shader: Res<ResizableComputeShader>) {
+    commands.spawn(Camera2d);
+    commands.spawn((
+        Sprite::from_image(shader.texture.clone()),
+        Transform {
+            scale: Vec3::splat(5.0),
+            ..default()
+        },
+    ));
+}
+
+/// Resize the texture on Up/Down. The dispatch size is read from the resource each dispatch.
+fn resize_on_arrows(
+    inputs: Res<ButtonInput<KeyCode>>,
+    mut shader: ResMut<ResizableComputeShader>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let size = if inputs.just_pressed(KeyCode::ArrowUp) {
+        (shader.size * 2).min(UVec2::splat(256))
+    } else if inputs.just_pressed(KeyCode::ArrowDown) {
+        (shader.size / 2).max(UVec2::splat(8))
+    } else {
+        return;
+    };
+    info!("Resizing to {size}");
+    shader.size = size;
+    shader.texture = images.add(ResizableComputeShader::image(size));
+}
+
+/// Keep the sprite pointed at the current texture.
+fn update_sprite(shader: Res<ResizableComputeShader>, mut sprites: Query<&mut Sprite>) {
+    if shader.is_changed() {
+        for mut sprite in sprites.iter_mut() {
+            sprite.image = shader.texture.clone();
+        }
+    }
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct ResizableComputeShader {
+    // Texture for the GPU to write to.
+    #[storage_texture(0, image_format=Rgba32Float, access=WriteOnly)]
+    texture: Handle<Image>,
+
+    // Input color
+    #[uniform(1)]
+    pub color: LinearRgba,
+
+    // Current size of the texture.
+    size: UVec2,
+}
+impl ResizableComputeShader {
+    /// Create an empty storage texture of the given size.
+    fn image(size: UVec2) -> Image {
+        let pixel = 0f32.to_le_bytes().repeat(4);
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &pixel,
+            TextureFormat::Rgba32Float,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::COPY_SRC | TextureUsages::STORAGE_BINDING;
+        image
+    }
+}
+impl ComputeShader for ResizableComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/texture_readback.wgsl".into()
+    }
+    /// Dispatch enough workgroups to cover the current texture.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(self.size.extend(1), UVec3::ONE)
+    }
+    /// Matches `@workgroup_size(1)` in the shader.
+    fn local_workgroup_size() -> Option<UVec3> {
+        Some(UVec3::ONE)
+    }
+    /// The dispatch should cover the whole texture.
+    fn output_extent(&self) -> Option<UVec3> {
+        Some(self.size.extend(1))
+    }
+    /// Indicate which buffer/texture should be read back to CPU.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::texture(self.texture.clone()))
+    }
+    /// Handle readback events.
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        info!("Readback {} bytes", trigger.event().data.len());
+    }
+}
+impl FromWorld for ResizableComputeShader {
+    /// Initialize the shader with an empty texture.
+    fn from_world(world: &mut World) -> Self {
+        let size = UVec2::splat(64);
+        Self {
+            texture: world.add_asset(Self::image(size)),
+            color: LinearRgba::new(1.0, 1.0, 1.0, 1.0),
+            size,
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Example to demonstrate a multi-pass shader: Conway's Game of Life.
+//! The cells are seeded by an `init` pass once, then `update` and `draw` run every frame.
+//! Press R to reseed, which resets the shader and runs `init` again.
+//! `cargo run --example game_of_life`
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{
+            AsBindGroup, BufferUsages, Extent3d, TextureDimension, TextureFormat, TextureUsages,
+        },
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{
+    ComputePassSpec, ComputeShader, ComputeShaderPlugin, ReadbackLimit, workgroup_count,
+};
+
+/// Size of the grid of cells.
+const SIZE: UVec3 = UVec3::new(128, 128, 1);
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<GameOfLifeComputeShader>::new()
+                .with_limit(ReadbackLimit::Infinite)
+                .with_readback_interval(60),
+        ))
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, setup)
+        .add_systems(Update, reseed_on_r)
+        .run();
+}
+
+/// Visualize the cells as a sprite.
+fn setup(mut commands: Commands, shader: Res<GameOfLifeComputeShader>) {
+    commands.spawn(Camera2d);
+    commands.spawn((
+        Sprite::from_image(shader.texture.clone()),
+        Transform {
+            scale: Vec3::splat(5.0),
+            ..default()
+        },
+    ));
+}
+
+/// Change the seed on R, which resets the shader and re-runs the `init` pass.
+fn reseed_on_r(inputs: Res<ButtonInput<KeyCode>>, mut shader: ResMut<GameOfLifeComputeShader>) {
+    if inputs.just_pressed(KeyCode::KeyR) {
+        shader.seed = shader.seed.wrapping_add(1);
+        info!("Reseeding with {}", shader.seed);
+    }
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct GameOfLifeComputeShader {
+    // Current generation, one u32 per cell.
+    #[storage(0, visibility(compute))]
+    cells: Handle<ShaderBuffer>,
+
+    // Next generation, one u32 per cell.
+    #[storage(1, visibility(compute))]
+    next: Handle<ShaderBuffer>,
+
+    // Texture for the GPU to draw the cells to.
+    #[storage_texture(2, image_format=Rgba32Float, access=WriteOnly)]
+    texture: Handle<Image>,
+
+    // Seed for the initial cells.
+    #[uniform(3)]
+    pub seed: u32,
+}
+impl ComputeShader for GameOfLifeComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/game_of_life.wgsl".into()
+    }
+    /// Seed once, then step and draw every frame.
+    fn passes() -> Vec<ComputePassSpec> {
+        vec![
+            ComputePassSpec::once("init"),
+            ComputePassSpec::new("update"),
+            ComputePassSpec::new("draw"),
+        ]
+    }
+    /// Dispatch enough workgroups to cover the grid.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+    fn local_workgroup_size() -> Option<UVec3> {
+        Some(LOCAL_SIZE)
+    }
+    fn output_extent(&self) -> Option<UVec3> {
+        Some(SIZE)
+    }
+    /// Read back the cells to count the live ones.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::buffer(self.cells.clone()))
+    }
+    /// Log the population.
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        let alive: u32 = trigger
+            .event()
+            .data
+            .chunks_exact(4)
+            .map(|cell| u32::from_le_bytes(cell.try_into().unwrap()))
+            .sum();
+        info!("{alive} cells alive");
+    }
+}
+impl FromWorld for GameOfLifeComputeShader {
+    /// Initialize the shader with empty cells and texture.
+    fn from_world(world: &mut World) -> Self {
+        let cell_count = (SIZE.x * SIZE.y) as usize;
+        let mut cells = ShaderBuffer::from(vec![0u32; cell_count]);
+        cells.buffer_description.usage |= BufferUsages::COPY_SRC;
+        let next = ShaderBuffer::from(vec![0u32; cell_count]);
+
+        let pixel = 0f32.to_le_bytes().repeat(4);
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: SIZE.x,
+                height: SIZE.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &pixel,
+            TextureFormat::Rgba32Float,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+        Self {
+            cells: world.add_asset(cells),
+            next: world.add_asset(next),
+            texture: world.add_asset(image),
+            seed: 0,
+        }
+    }
+}
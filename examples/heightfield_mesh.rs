@@ -0,0 +1,130 @@
+//! Example to demonstrate building a mesh from geometry generated on the GPU.
+//! The shader triangulates an animated heightfield into a counted vertex buffer, which is read
+//! back into a `Mesh` shown in the scene.
+//! `cargo run --example heightfield_mesh`
+use bevy::{
+    camera::visibility::NoFrustumCulling,
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::AsBindGroup,
+        renderer::RenderContext,
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{
+    ComputeShader, ComputeShaderPlugin, CountedBuffer, MeshVertex, ReadbackLimit, readback_to_mesh,
+    workgroup_count,
+};
+
+/// Number of cells along each side of the heightfield.
+const GRID: u32 = 32;
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<HeightfieldComputeShader>::new()
+                .with_limit(ReadbackLimit::Infinite),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, advance_time)
+        .run();
+}
+
+/// Mesh the readbacks are written to.
+#[derive(Resource)]
+struct HeightfieldMesh(Handle<Mesh>);
+
+/// Show the mesh, which is created by the first readback.
+fn setup(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.reserve_handle();
+    commands.spawn((
+        Mesh3d(mesh.clone()),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.7, 0.4))),
+        // The geometry changes every readback, so its first bounds don't hold.
+        NoFrustumCulling,
+    ));
+    commands.insert_resource(HeightfieldMesh(mesh));
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 6.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+}
+
+/// Animate the heightfield.
+fn advance_time(time: Res<Time>, mut shader: ResMut<HeightfieldComputeShader>) {
+    shader.time = time.elapsed_secs();
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+// Time as plain data, written into the existing uniform buffer.
+#[data(1, f32)]
+pub struct HeightfieldComputeShader {
+    // Vertices of the triangles, after a count of vertices appended.
+    #[storage(0, visibility(compute))]
+    buffer: Handle<ShaderBuffer>,
+
+    // Seconds since startup, bound at 1.
+    pub time: f32,
+
+    // The same buffer, to clear its count.
+    vertices: CountedBuffer<MeshVertex>,
+}
+impl From<&HeightfieldComputeShader> for f32 {
+    fn from(shader: &HeightfieldComputeShader) -> Self {
+        shader.time
+    }
+}
+impl ComputeShader for HeightfieldComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/heightfield_mesh.wgsl".into()
+    }
+    /// One invocation per cell.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(UVec3::new(GRID, GRID, 1), LOCAL_SIZE)
+    }
+    /// Start appending from the beginning each dispatch.
+    fn encode_pre_dispatch(&self, ctx: &mut RenderContext, world: &World) {
+        self.vertices.clear(ctx, world);
+    }
+    /// Read back the vertices.
+    fn readback(&self) -> Option<Readback> {
+        Some(self.vertices.readback())
+    }
+    /// Write the vertices into the mesh.
+    fn on_readback(trigger: On<ReadbackComplete>, mut world: DeferredWorld) {
+        let handle = world.resource::<HeightfieldMesh>().0.clone();
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        if let Err(error) = readback_to_mesh(&trigger.event().data, &mut meshes, &handle) {
+            warn!("Failed to build the mesh: {error}");
+        }
+    }
+}
+impl FromWorld for HeightfieldComputeShader {
+    /// Initialize the shader with room for two triangles per cell.
+    fn from_world(world: &mut World) -> Self {
+        let mut buffers = world.resource_mut::<Assets<ShaderBuffer>>();
+        let vertices = CountedBuffer::new(&mut buffers, GRID * GRID * 6);
+        Self {
+            buffer: vertices.buffer.clone(),
+            time: 0.0,
+            vertices,
+        }
+    }
+}
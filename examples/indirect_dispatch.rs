@@ -0,0 +1,135 @@
+//! Example to demonstrate dispatching from GPU-written indirect arguments.
+//! The first shader writes the workgroup count, and the second is dispatched from it.
+//! Press Up/Down to change the amount of work; the CPU never reads the arguments.
+//! Ordering between shaders isn't guaranteed, so the second may see last frame's arguments.
+//! `cargo run --example indirect_dispatch`
+use bevy::{
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{AsBindGroup, BufferUsages},
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{ComputePassSpec, ComputeShader, ComputeShaderPlugin, ReadbackLimit};
+
+/// Length of the output buffer.
+const MAX_COUNT: u32 = 1024;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<CountComputeShader>::new(),
+            ComputeShaderPlugin::<FillComputeShader>::new()
+                .with_limit(ReadbackLimit::Infinite)
+                .with_readback_interval(60),
+        ))
+        .add_systems(Update, count_on_arrows)
+        .run();
+}
+
+/// Change the amount of work on Up/Down.
+fn count_on_arrows(inputs: Res<ButtonInput<KeyCode>>, mut shader: ResMut<CountComputeShader>) {
+    let count = if inputs.just_pressed(KeyCode::ArrowUp) {
+        (shader.count + 100).min(MAX_COUNT)
+    } else if inputs.just_pressed(KeyCode::ArrowDown) {
+        shader.count.saturating_sub(100)
+    } else {
+        return;
+    };
+    info!("Requesting {count} invocations");
+    shader.count = count;
+}
+
+/// Indirect dispatch arguments shared by both shaders.
+#[derive(Resource)]
+struct DispatchArgs(Handle<ShaderBuffer>);
+impl FromWorld for DispatchArgs {
+    fn from_world(world: &mut World) -> Self {
+        let mut args = ShaderBuffer::from([0u32; 3]);
+        args.buffer_description.usage |= BufferUsages::INDIRECT;
+        Self(world.add_asset(args))
+    }
+}
+
+// Compute shader writing the indirect arguments.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct CountComputeShader {
+    // Indirect dispatch arguments for the fill shader.
+    #[storage(0, visibility(compute))]
+    args: Handle<ShaderBuffer>,
+
+    // Number of invocations to dispatch.
+    #[uniform(1)]
+    pub count: u32,
+}
+impl ComputeShader for CountComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/indirect_count.wgsl".into()
+    }
+}
+impl FromWorld for CountComputeShader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            args: world.get_resource_or_init::<DispatchArgs>().0.clone(),
+            count: 100,
+        }
+    }
+}
+
+// Compute shader dispatched from the indirect arguments.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct FillComputeShader {
+    // One flag per invocation.
+    #[storage(0, visibility(compute))]
+    output: Handle<ShaderBuffer>,
+
+    // Not bound, since a buffer can't be bound and used for indirect arguments at once.
+    args: Handle<ShaderBuffer>,
+}
+impl ComputeShader for FillComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/indirect_fill.wgsl".into()
+    }
+    /// Clear the whole output, then fill as many invocations as the GPU asked for.
+    fn passes() -> Vec<ComputePassSpec> {
+        vec![
+            ComputePassSpec::new("clear").with_workgroups(UVec3::new(MAX_COUNT / 64, 1, 1)),
+            ComputePassSpec::new("fill"),
+        ]
+    }
+    /// Dispatch passes without fixed workgroups from the arguments buffer.
+    fn indirect_dispatch(&self) -> Option<Handle<ShaderBuffer>> {
+        Some(self.args.clone())
+    }
+    /// Read back the output to count the invocations.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::buffer(self.output.clone()))
+    }
+    /// Log the number of invocations.
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        let invocations: u32 = trigger
+            .event()
+            .data
+            .chunks_exact(4)
+            .map(|flag| u32::from_le_bytes(flag.try_into().unwrap()))
+            .sum();
+        info!("{invocations} invocations dispatched indirectly");
+    }
+}
+impl FromWorld for FillComputeShader {
+    fn from_world(world: &mut World) -> Self {
+        let mut output = ShaderBuffer::from(vec![0u32; MAX_COUNT as usize]);
+        output.buffer_description.usage |= BufferUsages::COPY_SRC;
+        Self {
+            output: world.add_asset(output),
+            args: world.get_resource_or_init::<DispatchArgs>().0.clone(),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Example to demonstrate binding a second bind group owned by another plugin.
+//! The lookup table lives in the render world and is shared through `@group(1)`.
+//! `cargo run --example lookup_table`
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        RenderApp,
+        extract_resource::ExtractResource,
+        render_resource::{
+            AsBindGroup, BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntries, Buffer, BufferInitDescriptor, BufferUsages, Extent3d,
+            ShaderStages, TextureDimension, TextureFormat, TextureUsages,
+            binding_types::storage_buffer_read_only,
+        },
+        renderer::RenderDevice,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{ComputeShader, ComputeShaderPlugin, ReadbackLimit, workgroup_count};
+
+/// Size of the output texture.
+const SIZE: UVec3 = UVec3::new(64, 64, 1);
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            LookupTablePlugin,
+            ComputeShaderPlugin::<PaletteComputeShader>::new().with_limit(ReadbackLimit::Finite(1)),
+        ))
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+/// Visualize the compute shader output as a sprite.
+fn setup(mut commands: Commands, shader: Res<PaletteComputeShader>) {
+    commands.spawn(Camera2d);
+    commands.spawn((
+        Sprite::from_image(shader.texture.clone()),
+        Transform {
+            scale: Vec3::splat(5.0),
+            ..default()
+        },
+    ));
+}
+
+/// Stand-in for another plugin that owns a render world lookup table.
+struct LookupTablePlugin;
+impl Plugin for LookupTablePlugin {
+    fn build(&self, _app: &mut App) {}
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp).init_resource::<LookupTable>();
+    }
+}
+
+/// Lookup table stored in the render world.
+#[derive(Resource)]
+struct LookupTable {
+    buffer: Buffer,
+}
+impl LookupTable {
+    /// Layout of the bind group containing the lookup table.
+    fn layout() -> BindGroupLayoutDescriptor {
+        BindGroupLayoutDescriptor::new(
+            "lookup_table",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::COMPUTE,
+                storage_buffer_read_only::<Vec<Vec4>>(false),
+            ),
+        )
+    }
+}
+impl FromWorld for LookupTable {
+    fn from_world(world: &mut World) -> Self {
+        let palette = [
+            Vec4::new(0.9, 0.3, 0.3, 1.0),
+            Vec4::new(0.9, 0.7, 0.3, 1.0),
+            Vec4::new(0.3, 0.9, 0.5, 1.0),
+            Vec4::new(0.3, 0.5, 0.9, 1.0),
+        ];
+        let contents = palette
+            .iter()
+            .flat_map(|color| color.to_array())
+            .flat_map(f32::to_le_bytes)
+            .collect::<Vec<u8>>();
+        let buffer =
+            world
+                .resource::<RenderDevice>()
+                .create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("lookup_table"),
+                    contents: &contents,
+                    usage: BufferUsages::STORAGE,
+                });
+        Self { buffer }
+    }
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct PaletteComputeShader {
+    // Texture for the GPU to write to.
+    #[storage_texture(0, image_format=Rgba32Float, access=WriteOnly)]
+    texture: Handle<Image>,
+}
+impl ComputeShader for PaletteComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/lookup_table.wgsl".into()
+    }
+    /// Number of workgroups needed to cover the texture.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+    /// The lookup table is bound at `@group(1)`.
+    fn extra_bind_group_layouts(_render_device: &RenderDevice) -> Vec<BindGroupLayoutDescriptor> {
+        vec![LookupTable::layout()]
+    }
+    /// Bind the lookup table owned by the other plugin.
+    fn extra_bind_groups(world: &World, layouts: &[BindGroupLayout]) -> Option<Vec<BindGroup>> {
+        let table = world.get_resource::<LookupTable>()?;
+        let bind_group = world.resource::<RenderDevice>().create_bind_group(
+            "lookup_table",
+            &layouts[0],
+            &BindGroupEntries::single(table.buffer.as_entire_binding()),
+        );
+        Some(vec![bind_group])
+    }
+}
+impl FromWorld for PaletteComputeShader {
+    /// Initialize the shader with an empty texture.
+    fn from_world(world: &mut World) -> Self {
+        let size = Extent3d {
+            width: SIZE.x,
+            height: SIZE.y,
+            depth_or_array_layers: SIZE.z,
+        };
+        let pixel = 0f32.to_le_bytes().repeat(4);
+        let mut image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &pixel,
+            TextureFormat::Rgba32Float,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::COPY_SRC | TextureUsages::STORAGE_BINDING;
+        Self {
+            texture: world.add_asset(image),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Example to demonstrate dispatching inside the `Core3d` schedule, so a texture written by the
+//! compute shader is sampled by a material in the main pass of the same frame.
+//! `cargo run --example material_texture`
+use bevy::{
+    asset::RenderAssetUsages,
+    core_pipeline::{Core3d, Core3dSystems},
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        render_resource::{AsBindGroup, Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{ComputeShader, ComputeShaderPlugin, ReadbackLimit, workgroup_count};
+
+/// Size of the texture.
+const SIZE: UVec3 = UVec3::new(256, 256, 1);
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            // Write the texture before the main pass samples it.
+            ComputeShaderPlugin::<PatternComputeShader>::new()
+                .with_limit(ReadbackLimit::Infinite)
+                .with_render_schedule(Core3d)
+                .before_set(Core3dSystems::MainPass),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (animate, rotate))
+        .run();
+}
+
+/// Spawn a cube whose material samples the compute shader's texture.
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    shader: Res<PatternComputeShader>,
+) {
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::default())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color_texture: Some(shader.texture.clone()),
+            unlit: true,
+            ..default()
+        })),
+    ));
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 1.5, 2.5).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+}
+
+/// Move the pattern.
+fn animate(time: Res<Time>, mut shader: ResMut<PatternComputeShader>) {
+    shader.time = time.elapsed_secs();
+}
+
+/// Rotate the cube.
+fn rotate(time: Res<Time>, mut transforms: Query<&mut Transform, With<Mesh3d>>) {
+    for mut transform in transforms.iter_mut() {
+        transform.rotate_y(time.delta_secs() * 0.5);
+    }
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct PatternComputeShader {
+    // Texture written every frame and sampled by the material.
+    #[storage_texture(0, image_format=Rgba8Unorm, access=WriteOnly)]
+    texture: Handle<Image>,
+
+    // Seconds since startup.
+    #[uniform(1)]
+    time: f32,
+}
+impl ComputeShader for PatternComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/material_texture.wgsl".into()
+    }
+    /// Dispatch enough workgroups to cover the texture.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+    /// The time changes every frame without restarting the run.
+    fn should_reset(&self, _previous: &Self) -> bool {
+        false
+    }
+}
+impl FromWorld for PatternComputeShader {
+    /// Initialize the shader with an empty texture.
+    fn from_world(world: &mut World) -> Self {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: SIZE.x,
+                height: SIZE.y,
+                depth_or_array_layers: SIZE.z,
+            },
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+        Self {
+            texture: world.add_asset(image),
+            time: 0.0,
+        }
+    }
+}
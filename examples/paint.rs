@@ -0,0 +1,140 @@
+//! Example to demonstrate sending events from the CPU to a compute shader.
+//! Clicking or dragging with the left mouse button sends paint events, which the shader
+//! draws into a texture on its next dispatch.
+//! `cargo run --example paint`
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::message::Message,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        render_resource::{
+            AsBindGroup, Extent3d, ShaderType, TextureDimension, TextureFormat, TextureUsages,
+        },
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{
+    ComputeShader, ComputeShaderAppExt, ComputeShaderPlugin, GpuEventInput, ReadbackLimit,
+    workgroup_count,
+};
+
+/// Size of the canvas.
+const SIZE: UVec3 = UVec3::new(256, 256, 1);
+/// Matches `@workgroup_size(8, 8, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(8, 8, 1);
+/// Scale of the canvas sprite.
+const SCALE: f32 = 3.0;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        // Added before the shader, so `from_world` can bind the event buffer.
+        .add_compute_event_input::<PaintComputeShader, PaintEvent>(64)
+        .add_plugins(
+            ComputeShaderPlugin::<PaintComputeShader>::new().with_limit(ReadbackLimit::Infinite),
+        )
+        .add_systems(Startup, setup)
+        .add_systems(Update, paint_on_click)
+        .run();
+}
+
+/// Show the canvas as a sprite.
+fn setup(mut commands: Commands, shader: Res<PaintComputeShader>) {
+    commands.spawn(Camera2d);
+    commands.spawn((
+        Sprite::from_image(shader.texture.clone()),
+        Transform::from_scale(Vec3::splat(SCALE)),
+    ));
+}
+
+/// Send a paint event under the cursor while the left mouse button is held.
+fn paint_on_click(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    time: Res<Time>,
+    mut paints: MessageWriter<PaintEvent>,
+) {
+    if !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+    let (Ok(window), Ok((camera, transform))) = (windows.single(), cameras.single()) else {
+        return;
+    };
+    let Some(position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(transform, cursor).ok())
+    else {
+        return;
+    };
+    // World position to texel, with y pointing down in the texture.
+    let texel = Vec2::new(position.x, -position.y) / SCALE + SIZE.truncate().as_vec2() / 2.0;
+    let hue = (time.elapsed_secs() * 90.0) % 360.0;
+    paints.write(PaintEvent {
+        position: texel,
+        radius: 4.0,
+        color: LinearRgba::from(Color::hsl(hue, 0.8, 0.6)).to_vec4(),
+    });
+}
+
+/// Paint stroke sent from the CPU, matching `PaintEvent` in the shader.
+#[derive(Message, ShaderType, Clone, Debug)]
+struct PaintEvent {
+    position: Vec2,
+    radius: f32,
+    color: Vec4,
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct PaintComputeShader {
+    // Canvas the paint events are drawn into.
+    #[storage_texture(0, image_format=Rgba8Unorm, access=WriteOnly)]
+    texture: Handle<Image>,
+
+    // Paint events sent since the last dispatch.
+    #[storage(1, read_only, visibility(compute))]
+    paints: Handle<ShaderBuffer>,
+}
+impl ComputeShader for PaintComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/paint.wgsl".into()
+    }
+    /// Dispatch enough workgroups to cover the canvas.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, LOCAL_SIZE)
+    }
+    fn local_workgroup_size() -> Option<UVec3> {
+        Some(LOCAL_SIZE)
+    }
+    fn output_extent(&self) -> Option<UVec3> {
+        Some(SIZE)
+    }
+}
+impl FromWorld for PaintComputeShader {
+    /// Initialize the shader with a blank canvas and the event buffer.
+    fn from_world(world: &mut World) -> Self {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: SIZE.x,
+                height: SIZE.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[255, 255, 255, 255],
+            TextureFormat::Rgba8Unorm,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+        Self {
+            texture: world.add_asset(image),
+            paints: world
+                .resource::<GpuEventInput<Self, PaintEvent>>()
+                .buffer
+                .clone(),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Example to demonstrate showing the progress of a long finite run in a UI progress bar.
+//! The bake dispatches 500 times, reading back every 50 dispatches.
+//! `cargo run --example progress_bar`
+use bevy::{
+    ecs::world::DeferredWorld,
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{AsBindGroup, BufferUsages},
+        storage::ShaderBuffer,
+    },
+    shader::ShaderRef,
+};
+use bevy_compute_readback::{
+    ComputeNodeState, ComputeProgress, ComputeShader, ComputeShaderPlugin, ReadbackLimit,
+    decode_readback, workgroup_count,
+};
+
+/// Number of values baked.
+const SIZE: u32 = 4096;
+/// Matches `@workgroup_size(64, 1, 1)` in the shader.
+const LOCAL_SIZE: UVec3 = UVec3::new(64, 1, 1);
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            ComputeShaderPlugin::<BakeComputeShader>::new()
+                .with_limit(ReadbackLimit::Finite(500))
+                .with_readback_interval(50),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, update_progress)
+        .run();
+}
+
+/// Fill of the progress bar.
+#[derive(Component)]
+struct ProgressFill;
+
+/// Label under the progress bar.
+#[derive(Component)]
+struct ProgressLabel;
+
+/// Spawn an empty progress bar with a label.
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2d);
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(400.0),
+                        height: Val::Px(24.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.2)),
+                ))
+                .with_child((
+                    ProgressFill,
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.3, 0.8, 0.4)),
+                ));
+            parent.spawn((ProgressLabel, Text::new("Loading")));
+        });
+}
+
+/// Size the fill by the fraction of dispatches done.
+fn update_progress(
+    progress: Res<ComputeProgress<BakeComputeShader>>,
+    state: Res<State<ComputeNodeState<BakeComputeShader>>>,
+    mut fill: Single<&mut Node, With<ProgressFill>>,
+    mut label: Single<&mut Text, With<ProgressLabel>>,
+) {
+    fill.width = Val::Percent(progress.fraction().unwrap_or(0.0) * 100.0);
+    label.0 = format!(
+        "{:?}: {} / {} dispatches, {} readbacks",
+        state.get().status(),
+        progress.dispatched,
+        progress.limit.unwrap_or(0),
+        progress.delivered,
+    );
+}
+
+// Custom compute shader input.
+#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+pub struct BakeComputeShader {
+    // Values accumulated over the bake.
+    #[storage(0, visibility(compute))]
+    values: Handle<ShaderBuffer>,
+}
+impl ComputeShader for BakeComputeShader {
+    /// Path to your compute shader WGSL file.
+    fn compute_shader() -> ShaderRef {
+        "shaders/progress_bar.wgsl".into()
+    }
+    /// Number of workgroups needed to cover the values.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(UVec3::new(SIZE, 1, 1), LOCAL_SIZE)
+    }
+    /// Read back the values.
+    fn readback(&self) -> Option<Readback> {
+        Some(Readback::buffer(self.values.clone()))
+    }
+    /// Log the number of dispatches accumulated so far.
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        match decode_readback::<f32>(&trigger.event().data) {
+            Ok(values) => info!("Baked {} dispatches", values[0]),
+            Err(error) => warn!("Failed to decode the bake: {error}"),
+        }
+    }
+}
+impl FromWorld for BakeComputeShader {
+    /// Initialize the shader with zeroed values.
+    fn from_world(world: &mut World) -> Self {
+        let mut values = ShaderBuffer::from(vec![0f32; SIZE as usize]);
+        values.buffer_description.usage |= BufferUsages::COPY_SRC;
+        Self {
+            values: world.add_asset(values),
+        }
+    }
+}
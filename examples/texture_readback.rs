@@ -1,6 +1,6 @@
 //! Example to demonstrate reading texture data back to CPU from a compute shader.
 //! Press Space to cycle through different inputs to the shader to demonstrate reactivity.
-//! `cargo run --example texture_readback`
+//! `cargo run --example texture_readback --features io`
 use bevy::{
     asset::RenderAssetUsages,
     ecs::world::DeferredWorld,
@@ -12,20 +12,26 @@ use bevy::{
     },
     shader::ShaderRef,
 };
-use bevy_compute_readback::{ComputeShader, ComputeShaderPlugin, ReadbackLimit};
-use image::DynamicImage;
+use bevy_compute_readback::{
+    ComputeShader, ComputeShaderAppExt, ComputeShaderPlugin, ReadbackLimit,
+    io::save_readback_as_png, workgroup_count,
+};
+
+/// Size of the output texture.
+/// Rows of 400 bytes aren't aligned to 256, so readbacks come back padded.
+const SIZE: UVec3 = UVec3::new(100, 100, 1);
+/// Format of the output texture, matching the shader.
+const FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
 
 fn main() {
     App::new()
-        .add_plugins((
-            DefaultPlugins,
-            // Initialize compute shader pipeline.
-            ComputeShaderPlugin::<CustomComputeShader> {
-                limit: ReadbackLimit::Finite(1),
-                remove_on_complete: false,
-                ..default()
-            },
-        ))
+        .add_plugins(DefaultPlugins)
+        // Initialize compute shader pipeline.
+        .add_compute_shader_with(
+            ComputeShaderPlugin::<CustomComputeShader>::new()
+                .with_limit(ReadbackLimit::Finite(1))
+                .with_remove_on_complete(false),
+        )
         .insert_resource(ClearColor(Color::BLACK))
         .add_systems(Startup, setup)
         .add_systems(Update, reload_on_space)
@@ -63,65 +69,79 @@ fn reload_on_space(
     }
 }
 
-// Custom compute shader input.
-#[derive(AsBindGroup, Resource, Clone, Debug, ExtractResource)]
+// Custom compute shader input. `Debug` isn't required.
+#[derive(AsBindGroup, Resource, Clone, ExtractResource)]
+// The color is plain data, written into the existing bind group when it's the only change.
+#[data(1, LinearRgba)]
 pub struct CustomComputeShader {
     // Texture for the GPU to write to.
-    #[storage_texture(0, image_format=Rgba32Float, access=WriteOnly)]
+    #[storage_texture(0, image_format=Rgba8Unorm, access=WriteOnly)]
     texture: Handle<Image>,
 
-    // Input color
-    #[uniform(1)]
+    // Input color, bound at 1.
     pub color: LinearRgba,
 
     // Texture where data will be read back to from GPU.
     // We only need this because we want to render the read back texture.
     readback_texture: Handle<Image>,
 }
+impl From<&CustomComputeShader> for LinearRgba {
+    fn from(shader: &CustomComputeShader) -> Self {
+        shader.color
+    }
+}
 impl ComputeShader for CustomComputeShader {
     /// Path to your compute shader WGSL file.
     fn compute_shader() -> ShaderRef {
         "shaders/texture_readback.wgsl".into()
     }
-    /// Workgroup size for the compute shader.
-    fn workgroup_size() -> UVec3 {
-        UVec3::new(64, 64, 1)
+    /// Number of workgroups needed to cover the texture.
+    fn dispatch_workgroups(&self) -> UVec3 {
+        workgroup_count(SIZE, UVec3::ONE)
+    }
+    /// Matches `@workgroup_size(1)` in the shader.
+    fn local_workgroup_size() -> Option<UVec3> {
+        Some(UVec3::ONE)
+    }
+    /// The dispatch should cover the whole texture.
+    fn output_extent(&self) -> Option<UVec3> {
+        Some(SIZE)
     }
     /// Indicate which buffer/texture should be read back to CPU.
     fn readback(&self) -> Option<Readback> {
         Some(Readback::texture(self.texture.clone()))
     }
-    /// Handle readback events.
-    fn on_readback(trigger: On<ReadbackComplete>, mut world: DeferredWorld) {
-        // Copy readback buffer to the render texture so we can see it.
-        // Then save it as a PNG.
-        let image_handle = world.resource::<Self>().readback_texture.clone();
-        if let Some(mut image) = world.resource_mut::<Assets<Image>>().get_mut(&image_handle) {
-            image.data = Some(trigger.event().data.clone());
-            info!("Readback");
-            if let Ok(DynamicImage::ImageRgba32F(rgba)) = image.clone().try_into_dynamic() {
-                let _ = rgba.save("target/readback_output.png");
-            }
-        } else {
-            warn!("Handle not ready: {:?}", image_handle);
+    /// Copy readbacks to the render texture so we can see it.
+    fn readback_image_target(&self) -> Option<Handle<Image>> {
+        Some(self.readback_texture.clone())
+    }
+    /// Save the readback as a PNG.
+    fn on_readback(trigger: On<ReadbackComplete>, _world: DeferredWorld) {
+        let size = Extent3d {
+            width: SIZE.x,
+            height: SIZE.y,
+            depth_or_array_layers: SIZE.z,
+        };
+        let path = "target/readback_output.png";
+        if let Err(error) = save_readback_as_png(&trigger.event().data, size, FORMAT, path) {
+            warn!("Failed to save {path}: {error}");
         }
     }
 }
 impl FromWorld for CustomComputeShader {
     /// Initialize the shader with empty textures.
     fn from_world(world: &mut World) -> Self {
-        let workgroup_size = Self::workgroup_size();
         let size = Extent3d {
-            width: workgroup_size.x,
-            height: workgroup_size.y,
-            depth_or_array_layers: workgroup_size.z,
+            width: SIZE.x,
+            height: SIZE.y,
+            depth_or_array_layers: SIZE.z,
         };
-        let pixel = 0f32.to_le_bytes().repeat(4);
+        let pixel = [0; 4];
         let mut image = Image::new_fill(
             size,
             TextureDimension::D2,
             &pixel,
-            TextureFormat::Rgba32Float,
+            FORMAT,
             RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
         );
         image.texture_descriptor.usage |= TextureUsages::COPY_SRC | TextureUsages::STORAGE_BINDING;
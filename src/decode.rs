@@ -0,0 +1,695 @@
+//! Decoding readback bytes into values and images.
+
+use std::fmt::Debug;
+
+use bevy::{
+    asset::{Assets, Handle, RenderAssetUsages},
+    ecs::world::World,
+    image::Image,
+    log::error_once,
+    math::{UVec2, Vec2, Vec4},
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            Extent3d, Origin3d, ShaderSize, ShaderType, TexelCopyBufferInfo, TexelCopyBufferLayout,
+            TexelCopyTextureInfo, TextureAspect, TextureDimension, TextureFormat,
+            encase::{StorageBuffer, internal::CreateFrom},
+        },
+        renderer::{RenderContext, RenderDevice},
+        storage::{GpuShaderBuffer, ShaderBuffer},
+        texture::GpuImage,
+    },
+};
+use bytemuck::{Pod, PodCastError};
+use thiserror::Error;
+
+use crate::ComputeShader;
+
+/// Decode readback bytes as an array of `T`, using the std430 layout.
+pub fn decode_readback<T: ShaderType + ShaderSize + CreateFrom>(
+    data: &[u8],
+) -> Result<Vec<T>, ReadbackDecodeError> {
+    // The minimum size of a runtime-sized array is one element, including padding.
+    let stride = <Vec<T> as ShaderType>::min_size().get();
+    if !(data.len() as u64).is_multiple_of(stride) {
+        return Err(ReadbackDecodeError::Length {
+            len: data.len(),
+            stride,
+            size: T::min_size().get(),
+        });
+    }
+    StorageBuffer::new(data)
+        .create::<Vec<T>>()
+        .map_err(|error| ReadbackDecodeError::Decode(error.to_string()))
+}
+
+/// Zero-copy views of readback bytes as [`Pod`] elements, for types without std430 padding.
+/// [`ReadbackData`] is aligned for them, but views of other misaligned data fail with
+/// [`ReadbackDecodeError::Misaligned`], see [`ReadbackBytes::to_pod_vec`].
+///
+/// [`ReadbackData`]: crate::ReadbackData
+pub trait ReadbackBytes {
+    /// View the bytes as a slice of `T`.
+    fn as_pod_slice<T: Pod>(&self) -> Result<&[T], ReadbackDecodeError>;
+    /// View the bytes as a mutable slice of `T`, e.g. to process them in place.
+    fn as_pod_slice_mut<T: Pod>(&mut self) -> Result<&mut [T], ReadbackDecodeError>;
+    /// Copy the bytes into a `Vec<T>`, whatever their alignment.
+    fn to_pod_vec<T: Pod>(&self) -> Result<Vec<T>, ReadbackDecodeError>;
+}
+impl ReadbackBytes for [u8] {
+    fn as_pod_slice<T: Pod>(&self) -> Result<&[T], ReadbackDecodeError> {
+        bytemuck::try_cast_slice(self).map_err(|error| pod_error::<T>(self.len(), error))
+    }
+    fn as_pod_slice_mut<T: Pod>(&mut self) -> Result<&mut [T], ReadbackDecodeError> {
+        let len = self.len();
+        bytemuck::try_cast_slice_mut(self).map_err(|error| pod_error::<T>(len, error))
+    }
+    fn to_pod_vec<T: Pod>(&self) -> Result<Vec<T>, ReadbackDecodeError> {
+        let size = size_of::<T>();
+        if size == 0 || !self.len().is_multiple_of(size) {
+            return Err(ReadbackDecodeError::PodLength {
+                len: self.len(),
+                size,
+            });
+        }
+        Ok(bytemuck::pod_collect_to_vec(self))
+    }
+}
+fn pod_error<T>(len: usize, error: PodCastError) -> ReadbackDecodeError {
+    match error {
+        PodCastError::TargetAlignmentGreaterAndInputNotAligned
+        | PodCastError::AlignmentMismatch => ReadbackDecodeError::Misaligned {
+            align: align_of::<T>(),
+        },
+        PodCastError::OutputSliceWouldHaveSlop | PodCastError::SizeMismatch => {
+            ReadbackDecodeError::PodLength {
+                len,
+                size: size_of::<T>(),
+            }
+        }
+    }
+}
+
+/// Decode readback bytes as a single `T`, e.g. from a buffer holding one struct, using the
+/// std430 layout. Trailing bytes past `T` are ignored.
+pub fn decode_single<T: ShaderType + ShaderSize + CreateFrom>(
+    data: &[u8],
+) -> Result<T, ReadbackDecodeError> {
+    let size = T::min_size().get();
+    if (data.len() as u64) < size {
+        return Err(ReadbackDecodeError::Short {
+            len: data.len(),
+            size,
+        });
+    }
+    StorageBuffer::new(data)
+        .create::<T>()
+        .map_err(|error| ReadbackDecodeError::Decode(error.to_string()))
+}
+
+/// Remove the row padding of a texture readback, returning tightly packed texels as in
+/// `Image::data`. Applied automatically for [`ComputeShader::readback_image_target`].
+pub fn unpad_texture_data(
+    data: &[u8],
+    size: Extent3d,
+    format: TextureFormat,
+) -> Result<Vec<u8>, ReadbackDecodeError> {
+    let layout = TextureRowLayout::new(size, format)?;
+    layout.check(data)?;
+    if layout.padded_row == layout.row {
+        return Ok(data[..layout.row * layout.rows].to_vec());
+    }
+    Ok(layout.rows(data).flatten().copied().collect())
+}
+
+/// How texels are packed in a buffer readback, for [`buffer_to_image`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BufferPacking {
+    /// Tightly packed texels in the image's format.
+    #[default]
+    Texels,
+    /// One `f32` per texel, e.g. a scalar field. Kept as is for `R32Float`, and shown in gray
+    /// for `Rgba32Float` and, clamped to `[0, 1]`, for `Rgba8Unorm` and `Rgba8UnormSrgb`.
+    F32,
+}
+
+/// Write a buffer readback into the image at `handle` for display, replacing it when missing
+/// or of another size or format. [`ComputeShader::buffer_images`] copies on the GPU instead.
+pub fn buffer_to_image(
+    data: &[u8],
+    extent: Extent3d,
+    format: TextureFormat,
+    packing: BufferPacking,
+    images: &mut Assets<Image>,
+    handle: &Handle<Image>,
+) -> Result<(), ReadbackDecodeError> {
+    if format.block_dimensions() != (1, 1) {
+        return Err(ReadbackDecodeError::TextureFormat(format));
+    }
+    let texel_size = match packing {
+        BufferPacking::Texels => format
+            .block_copy_size(None)
+            .ok_or(ReadbackDecodeError::TextureFormat(format))?
+            as usize,
+        BufferPacking::F32 => size_of::<f32>(),
+    };
+    let expected = [extent.height, extent.depth_or_array_layers]
+        .into_iter()
+        .try_fold(extent.width as usize * texel_size, |bytes, factor| {
+            bytes.checked_mul(factor as usize)
+        })
+        .ok_or(ReadbackDecodeError::TextureSize(extent))?;
+    if data.len() < expected {
+        return Err(ReadbackDecodeError::TextureLength {
+            len: data.len(),
+            expected,
+        });
+    }
+    let floats = data[..expected]
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()));
+    let data = match (packing, format) {
+        (BufferPacking::Texels, _) | (BufferPacking::F32, TextureFormat::R32Float) => {
+            data[..expected].to_vec()
+        }
+        (BufferPacking::F32, TextureFormat::Rgba32Float) => floats
+            .flat_map(|value| [value, value, value, 1.0])
+            .flat_map(f32::to_le_bytes)
+            .collect(),
+        (BufferPacking::F32, TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb) => floats
+            .flat_map(|value| {
+                let value = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                [value, value, value, u8::MAX]
+            })
+            .collect(),
+        (BufferPacking::F32, format) => return Err(ReadbackDecodeError::F32Format(format)),
+    };
+    if let Some(mut image) = images.get_mut(handle)
+        && image.texture_descriptor.size == extent
+        && image.texture_descriptor.format == format
+    {
+        image.data = Some(data);
+        return Ok(());
+    }
+    let image = Image::new(
+        extent,
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::default(),
+    );
+    let _ = images.insert(handle.id(), image);
+    Ok(())
+}
+
+/// Copy a storage buffer into an image after the dispatch, for [`ComputeShader::buffer_images`].
+pub(crate) fn copy_buffer_to_image<S: ComputeShader>(
+    ctx: &mut RenderContext,
+    world: &World,
+    buffer: &Handle<ShaderBuffer>,
+    image: &Handle<Image>,
+) {
+    let (Some(image), Some(buffer)) = (
+        world.resource::<RenderAssets<GpuImage>>().get(image),
+        world
+            .resource::<RenderAssets<GpuShaderBuffer>>()
+            .get(buffer),
+    ) else {
+        return;
+    };
+    let Ok(layout) = TextureRowLayout::new(image.texture.size(), image.texture.format()) else {
+        error_once!(
+            "{}: {}",
+            S::shader_label(),
+            ReadbackDecodeError::TextureFormat(image.texture.format())
+        );
+        return;
+    };
+    if (buffer.buffer.size() as usize) < layout.len() {
+        error_once!(
+            "{}: buffer of {} bytes can't fill an image of {} bytes with padded rows",
+            S::shader_label(),
+            buffer.buffer.size(),
+            layout.len(),
+        );
+        return;
+    }
+    ctx.command_encoder().copy_buffer_to_texture(
+        TexelCopyBufferInfo {
+            buffer: &buffer.buffer,
+            layout: TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: (layout.rows > 1).then_some(layout.padded_row as u32),
+                rows_per_image: (image.texture.size().depth_or_array_layers > 1)
+                    .then_some(layout.rows as u32 / image.texture.size().depth_or_array_layers),
+            },
+        },
+        TexelCopyTextureInfo {
+            texture: &image.texture,
+            mip_level: 0,
+            origin: Origin3d::ZERO,
+            aspect: TextureAspect::All,
+        },
+        image.texture.size(),
+    );
+}
+
+/// Rows of a texture readback of the given size and format, without their padding and
+/// without copying. See [`unpad_texture_data`]. Rows of all depth layers follow each other.
+pub fn texture_rows(
+    data: &[u8],
+    size: Extent3d,
+    format: TextureFormat,
+) -> Result<impl ExactSizeIterator<Item = &[u8]>, ReadbackDecodeError> {
+    let layout = TextureRowLayout::new(size, format)?;
+    layout.check(data)?;
+    Ok(layout.rows(data))
+}
+
+/// View of a texture readback for reading texels by position, handling the row padding.
+/// Layers follow each other, so `y` goes up to `height * depth_or_array_layers`.
+///
+/// ```ignore
+/// let view = ReadbackImageView::new(&data, size, TextureFormat::Rgba8Unorm)?;
+/// let color = view.get::<Vec4>(x, y);
+/// ```
+#[derive(Clone, Copy)]
+pub struct ReadbackImageView<'a> {
+    data: &'a [u8],
+    size: Extent3d,
+    format: TextureFormat,
+    texel_size: usize,
+    padded_row: usize,
+    rows: usize,
+}
+impl<'a> ReadbackImageView<'a> {
+    /// View a texture readback of the given size and format.
+    pub fn new(
+        data: &'a [u8],
+        size: Extent3d,
+        format: TextureFormat,
+    ) -> Result<Self, ReadbackDecodeError> {
+        if format.block_dimensions() != (1, 1) {
+            return Err(ReadbackDecodeError::TextureFormat(format));
+        }
+        let layout = TextureRowLayout::new(size, format)?;
+        layout.check(data)?;
+        Ok(Self {
+            data,
+            size,
+            format,
+            texel_size: layout.row / size.width.max(1) as usize,
+            padded_row: layout.padded_row,
+            rows: layout.rows,
+        })
+    }
+    pub fn size(&self) -> Extent3d {
+        self.size
+    }
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+    /// Texels of row `y`, without padding.
+    pub fn row(&self, y: u32) -> Option<&'a [u8]> {
+        let y = y as usize;
+        (y < self.rows).then(|| {
+            &self.data[y * self.padded_row..][..self.size.width as usize * self.texel_size]
+        })
+    }
+    /// Bytes of the texel at `(x, y)`.
+    pub fn texel(&self, x: u32, y: u32) -> Option<&'a [u8]> {
+        if x >= self.size.width {
+            return None;
+        }
+        let row = self.row(y)?;
+        Some(&row[x as usize * self.texel_size..][..self.texel_size])
+    }
+    /// Texel at `(x, y)` as `T`, or `None` outside the texture or if `T` can't be read from
+    /// the format. See [`ReadbackTexel`].
+    pub fn get<T: ReadbackTexel>(&self, x: u32, y: u32) -> Option<T> {
+        T::from_texel(self.texel(x, y)?, self.format)
+    }
+    /// Positions and values of every texel, row by row. Fails if `T` can't be read from the
+    /// format.
+    pub fn iter_pixels<T: ReadbackTexel>(
+        &self,
+    ) -> Result<impl Iterator<Item = (UVec2, T)> + use<'a, T>, ReadbackDecodeError> {
+        if !T::supports(self.format) {
+            return Err(ReadbackDecodeError::TextureFormat(self.format));
+        }
+        let view = *self;
+        Ok((0..self.rows as u32).flat_map(move |y| {
+            (0..view.size.width).filter_map(move |x| Some((UVec2::new(x, y), view.get(x, y)?)))
+        }))
+    }
+    /// Texels without padding, as in `Image::data`.
+    pub fn to_vec(&self) -> Vec<u8> {
+        (0..self.rows as u32)
+            .filter_map(|y| self.row(y))
+            .flatten()
+            .copied()
+            .collect()
+    }
+    /// Copy the texels into an [`Image`] of the same size and format, e.g. for display.
+    /// Depth slices become array layers.
+    pub fn to_image(&self) -> Image {
+        Image::new(
+            self.size,
+            TextureDimension::D2,
+            self.to_vec(),
+            self.format,
+            RenderAssetUsages::default(),
+        )
+    }
+}
+
+/// Texel values [`ReadbackImageView::get`] can read: `Vec4` and `f32` from 8 bit unorm and
+/// 32 bit formats, `Vec2` from `Rg32Float`, `u32` from `R32Uint` and 8 bit `Uint` formats,
+/// and `[u8; 4]` from RGBA8 and BGRA8 formats, in RGBA order.
+pub trait ReadbackTexel: Sized {
+    /// Read a texel of `format`, or `None` if unsupported.
+    fn from_texel(texel: &[u8], format: TextureFormat) -> Option<Self>;
+    /// Whether texels of `format` can be read.
+    fn supports(format: TextureFormat) -> bool;
+}
+fn texel_f32(texel: &[u8], index: usize) -> f32 {
+    f32::from_le_bytes(texel[index * 4..][..4].try_into().unwrap())
+}
+fn texel_u32(texel: &[u8]) -> u32 {
+    u32::from_le_bytes(texel[..4].try_into().unwrap())
+}
+impl ReadbackTexel for Vec4 {
+    fn from_texel(texel: &[u8], format: TextureFormat) -> Option<Self> {
+        let unorm = |value: u8| value as f32 / 255.0;
+        Some(match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+                Vec4::from_array(std::array::from_fn(|index| unorm(texel[index])))
+            }
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Vec4::new(
+                unorm(texel[2]),
+                unorm(texel[1]),
+                unorm(texel[0]),
+                unorm(texel[3]),
+            ),
+            TextureFormat::R8Unorm => Vec4::new(unorm(texel[0]), 0.0, 0.0, 1.0),
+            TextureFormat::Rgba32Float => {
+                Vec4::from_array(std::array::from_fn(|index| texel_f32(texel, index)))
+            }
+            TextureFormat::Rg32Float => {
+                Vec4::new(texel_f32(texel, 0), texel_f32(texel, 1), 0.0, 1.0)
+            }
+            TextureFormat::R32Float => Vec4::new(texel_f32(texel, 0), 0.0, 0.0, 1.0),
+            TextureFormat::R32Uint => Vec4::new(texel_u32(texel) as f32, 0.0, 0.0, 1.0),
+            _ => return None,
+        })
+    }
+    fn supports(format: TextureFormat) -> bool {
+        matches!(
+            format,
+            TextureFormat::Rgba8Unorm
+                | TextureFormat::Rgba8UnormSrgb
+                | TextureFormat::Bgra8Unorm
+                | TextureFormat::Bgra8UnormSrgb
+                | TextureFormat::R8Unorm
+                | TextureFormat::Rgba32Float
+                | TextureFormat::Rg32Float
+                | TextureFormat::R32Float
+                | TextureFormat::R32Uint
+        )
+    }
+}
+impl ReadbackTexel for f32 {
+    fn from_texel(texel: &[u8], format: TextureFormat) -> Option<Self> {
+        Vec4::from_texel(texel, format).map(|value| value.x)
+    }
+    fn supports(format: TextureFormat) -> bool {
+        Vec4::supports(format)
+    }
+}
+impl ReadbackTexel for Vec2 {
+    fn from_texel(texel: &[u8], format: TextureFormat) -> Option<Self> {
+        Self::supports(format).then(|| Vec2::new(texel_f32(texel, 0), texel_f32(texel, 1)))
+    }
+    fn supports(format: TextureFormat) -> bool {
+        format == TextureFormat::Rg32Float
+    }
+}
+impl ReadbackTexel for u32 {
+    fn from_texel(texel: &[u8], format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::R32Uint => Some(texel_u32(texel)),
+            TextureFormat::R8Uint | TextureFormat::Rg8Uint | TextureFormat::Rgba8Uint => {
+                Some(texel[0] as u32)
+            }
+            _ => None,
+        }
+    }
+    fn supports(format: TextureFormat) -> bool {
+        matches!(
+            format,
+            TextureFormat::R32Uint
+                | TextureFormat::R8Uint
+                | TextureFormat::Rg8Uint
+                | TextureFormat::Rgba8Uint
+        )
+    }
+}
+impl ReadbackTexel for [u8; 4] {
+    fn from_texel(texel: &[u8], format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Rgba8Uint => texel[..4].try_into().ok(),
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+                Some([texel[2], texel[1], texel[0], texel[3]])
+            }
+            _ => None,
+        }
+    }
+    fn supports(format: TextureFormat) -> bool {
+        matches!(
+            format,
+            TextureFormat::Rgba8Unorm
+                | TextureFormat::Rgba8UnormSrgb
+                | TextureFormat::Rgba8Uint
+                | TextureFormat::Bgra8Unorm
+                | TextureFormat::Bgra8UnormSrgb
+        )
+    }
+}
+
+/// Layout of a texture copied into a buffer.
+pub(crate) struct TextureRowLayout {
+    /// Bytes of texels in each row.
+    row: usize,
+    /// Bytes between the starts of two rows.
+    pub(crate) padded_row: usize,
+    /// Number of rows, of texel blocks for compressed formats.
+    pub(crate) rows: usize,
+}
+impl TextureRowLayout {
+    pub(crate) fn new(size: Extent3d, format: TextureFormat) -> Result<Self, ReadbackDecodeError> {
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format
+            .block_copy_size(None)
+            .ok_or(ReadbackDecodeError::TextureFormat(format))?;
+        let row = size.width.div_ceil(block_width) as usize * block_size as usize;
+        let rows =
+            size.height.div_ceil(block_height) as usize * size.depth_or_array_layers as usize;
+        let padded_row = if rows > 1 {
+            RenderDevice::align_copy_bytes_per_row(row)
+        } else {
+            row
+        };
+        Ok(Self {
+            row,
+            padded_row,
+            rows,
+        })
+    }
+    /// Bytes needed to hold every row.
+    pub(crate) fn len(&self) -> usize {
+        (self.padded_row * self.rows).saturating_sub(self.padded_row - self.row)
+    }
+    /// Bytes of the readback buffer, which pads every row.
+    pub(crate) fn padded_len(&self) -> usize {
+        RenderDevice::align_copy_bytes_per_row(self.row) * self.rows
+    }
+    /// Check that the data covers every row.
+    pub(crate) fn check(&self, data: &[u8]) -> Result<(), ReadbackDecodeError> {
+        let expected = self.len();
+        if data.len() < expected {
+            return Err(ReadbackDecodeError::TextureLength {
+                len: data.len(),
+                expected,
+            });
+        }
+        Ok(())
+    }
+    pub(crate) fn rows<'a>(
+        &self,
+        data: &'a [u8],
+    ) -> impl ExactSizeIterator<Item = &'a [u8]> + use<'a> {
+        let (row, padded_row) = (self.row, self.padded_row);
+        (0..self.rows).map(move |index| &data[index * padded_row..][..row])
+    }
+}
+
+/// Errors decoding readback data.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReadbackDecodeError {
+    #[error(
+        "readback of {len} bytes is not a whole number of {stride} byte elements \
+        ({size} bytes of data plus padding)"
+    )]
+    Length { len: usize, stride: u64, size: u64 },
+    #[error("readback of {len} bytes is shorter than the {size} bytes of its type")]
+    Short { len: usize, size: u64 },
+    #[error("failed to decode readback: {0}")]
+    Decode(String),
+    #[error("texture readback of {len} bytes is shorter than the {expected} bytes of its texture")]
+    TextureLength { len: usize, expected: usize },
+    #[error("texture readbacks of format {0:?} can't be unpadded, copy a single aspect instead")]
+    TextureFormat(TextureFormat),
+    #[error("texture of size {0:?} is too large to decode")]
+    TextureSize(Extent3d),
+    #[error("f32 readbacks can't be converted to {0:?}")]
+    F32Format(TextureFormat),
+    #[error("readback of {len} bytes is not a whole number of {size} byte elements")]
+    PodLength { len: usize, size: usize },
+    #[error(
+        "counted readback of {len} bytes is shorter than the {expected} bytes of its {count} \
+        items, read back the whole buffer"
+    )]
+    CountedLength {
+        len: usize,
+        count: u32,
+        expected: usize,
+    },
+    #[error(
+        "readback data is not aligned to the {align} bytes of its element type, \
+        copy it with `to_pod_vec` instead"
+    )]
+    Misaligned { align: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpad_texture_data_strips_row_padding() {
+        for (format, texel_size) in [
+            (TextureFormat::R8Unorm, 1),
+            (TextureFormat::Rgba8Unorm, 4),
+            (TextureFormat::Rgba32Float, 16),
+        ] {
+            for width in [1, 3, 65] {
+                let size = Extent3d {
+                    width,
+                    height: 2,
+                    depth_or_array_layers: 1,
+                };
+                let row = width as usize * texel_size;
+                let padded_row = row.div_ceil(256) * 256;
+                let layout = TextureRowLayout::new(size, format).unwrap();
+                assert_eq!(
+                    (layout.row, layout.padded_row, layout.rows),
+                    (row, padded_row, 2)
+                );
+                assert_eq!(layout.padded_len(), padded_row * 2);
+                // The last row may come back without its padding.
+                assert_eq!(layout.len(), padded_row + row);
+
+                let texels: Vec<u8> = (0..row * 2).map(|index| index as u8).collect();
+                let mut padded = vec![0xff; layout.len()];
+                padded[..row].copy_from_slice(&texels[..row]);
+                padded[padded_row..].copy_from_slice(&texels[row..]);
+                assert_eq!(unpad_texture_data(&padded, size, format).unwrap(), texels);
+                assert_eq!(
+                    unpad_texture_data(&padded[..layout.len() - 1], size, format),
+                    Err(ReadbackDecodeError::TextureLength {
+                        len: layout.len() - 1,
+                        expected: layout.len(),
+                    })
+                );
+
+                // A single row isn't padded.
+                let size = Extent3d { height: 1, ..size };
+                let layout = TextureRowLayout::new(size, format).unwrap();
+                assert_eq!((layout.padded_row, layout.len()), (row, row));
+                assert_eq!(
+                    unpad_texture_data(&texels[..row], size, format).unwrap(),
+                    texels[..row]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_to_image_expands_f32() {
+        let mut images = Assets::<Image>::default();
+        let handle = Handle::default();
+        let data: Vec<u8> = [0.0f32, 1.0]
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let extent = Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        buffer_to_image(
+            &data,
+            extent,
+            TextureFormat::Rgba8Unorm,
+            BufferPacking::F32,
+            &mut images,
+            &handle,
+        )
+        .unwrap();
+        let image = images.get(&handle).unwrap();
+        assert_eq!(
+            image.data.as_deref(),
+            Some(&[0, 0, 0, 255, 255, 255, 255, 255][..])
+        );
+    }
+
+    #[test]
+    fn buffer_to_image_rejects_oversized_extents() {
+        let mut images = Assets::<Image>::default();
+        let handle = Handle::default();
+        let mut decode = |extent| {
+            buffer_to_image(
+                &[0; 16],
+                extent,
+                TextureFormat::Rgba32Float,
+                BufferPacking::Texels,
+                &mut images,
+                &handle,
+            )
+        };
+        // The texel count alone overflows a u32.
+        let extent = Extent3d {
+            width: 65536,
+            height: 65536,
+            depth_or_array_layers: 1,
+        };
+        assert_eq!(
+            decode(extent),
+            Err(ReadbackDecodeError::TextureLength {
+                len: 16,
+                expected: 65536 * 65536 * 16,
+            })
+        );
+        let extent = Extent3d {
+            width: u32::MAX,
+            height: u32::MAX,
+            depth_or_array_layers: u32::MAX,
+        };
+        assert_eq!(
+            decode(extent),
+            Err(ReadbackDecodeError::TextureSize(extent))
+        );
+    }
+}
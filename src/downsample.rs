@@ -0,0 +1,288 @@
+//! Downsampling textures on the GPU before reading them back.
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::{
+    app::{App, Plugin},
+    asset::{Assets, Handle, RenderAssetUsages, embedded_asset, load_embedded_asset},
+    ecs::{resource::Resource, world::World},
+    image::Image,
+    math::UVec2,
+    render::{
+        RenderApp,
+        extract_resource::ExtractResource,
+        render_asset::RenderAssets,
+        render_resource::{
+            BindGroupEntry, BindGroupLayoutDescriptor, BindingResource, CachedComputePipelineId,
+            ComputePassDescriptor, ComputePipelineDescriptor, Extent3d, PipelineCache,
+            ShaderStages, StorageTextureAccess, TextureDimension, TextureFormat, TextureSampleType,
+            TextureUsages,
+            binding_types::{texture_2d, texture_storage_2d},
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::GpuImage,
+    },
+    shader::Shader,
+    utils::default,
+};
+
+use crate::ComputeShader;
+
+/// Texture readbacks of `S` box-filtered to a smaller size on the GPU before they're read back.
+/// The full resolution textures need `TextureUsages::TEXTURE_BINDING`.
+/// See [`ComputeShaderPlugin::with_readback_downsample`].
+///
+/// [`ComputeShaderPlugin::with_readback_downsample`]: crate::ComputeShaderPlugin::with_readback_downsample
+#[derive(Resource, ExtractResource, Clone, Debug)]
+pub struct ReadbackDownsample<S: ComputeShader> {
+    /// Size of the textures read back.
+    pub size: UVec2,
+    /// Index of each downsampled readback, its full resolution texture and the smaller one.
+    pub targets: Vec<(usize, Handle<Image>, Handle<Image>)>,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ReadbackDownsample<S> {
+    /// Downsample to `size`.
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            targets: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+    /// Format of the texture read back for a texture of `format`: `Rgba8Unorm` for 8 bit
+    /// unorm formats, sRGB ones coming back linear and BGRA ones in RGBA order, and
+    /// `Rgba32Float` otherwise.
+    pub fn target_format(format: TextureFormat) -> TextureFormat {
+        match format {
+            TextureFormat::R8Unorm
+            | TextureFormat::Rg8Unorm
+            | TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Bgra8UnormSrgb => TextureFormat::Rgba8Unorm,
+            _ => TextureFormat::Rgba32Float,
+        }
+    }
+    /// Smaller texture to read back instead of `source`, added the first time. Sources not
+    /// loaded in the main world, e.g. only in the render world, are assumed to be float.
+    pub(crate) fn target(
+        &mut self,
+        index: usize,
+        source: Handle<Image>,
+        images: &mut Assets<Image>,
+    ) -> Handle<Image> {
+        if let Some((_, _, target)) =
+            self.targets
+                .iter()
+                .find(|(target_index, target_source, _)| {
+                    *target_index == index && *target_source == source
+                })
+        {
+            return target.clone();
+        }
+        let format = images
+            .get(&source)
+            .map_or(TextureFormat::Rgba32Float, |image| {
+                Self::target_format(image.texture_descriptor.format)
+            });
+        let texel = vec![0; format.block_copy_size(None).unwrap_or(16) as usize];
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: self.size.x.max(1),
+                height: self.size.y.max(1),
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &texel,
+            format,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC;
+        let target = images.add(image);
+        self.targets
+            .retain(|(target_index, ..)| *target_index != index);
+        self.targets.push((index, source, target.clone()));
+        target
+    }
+    /// Filter the full resolution textures into the smaller ones, after the dispatch.
+    pub(crate) fn encode(&self, ctx: &mut RenderContext, world: &World) {
+        let Some(pipeline) = world.get_resource::<DownsamplePipeline>() else {
+            return;
+        };
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let images = world.resource::<RenderAssets<GpuImage>>();
+        for (_, source, target) in &self.targets {
+            let (Some(source), Some(target)) = (images.get(source), images.get(target)) else {
+                continue;
+            };
+            let Some((layout, id)) = pipeline.get(target.texture.format()) else {
+                continue;
+            };
+            let Some(compute_pipeline) = pipeline_cache.get_compute_pipeline(id) else {
+                continue;
+            };
+            let bind_group = world.resource::<RenderDevice>().create_bind_group(
+                "readback_downsample",
+                &pipeline_cache.get_bind_group_layout(layout),
+                &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&source.texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&target.texture_view),
+                    },
+                ],
+            );
+            let mut pass = ctx
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("readback_downsample"),
+                    ..Default::default()
+                });
+            pass.set_pipeline(compute_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                target.texture.size().width.div_ceil(8),
+                target.texture.size().height.div_ceil(8),
+                1,
+            );
+        }
+    }
+}
+
+/// Loads the shader of [`ReadbackDownsample`] and queues its pipelines.
+pub(crate) struct DownsamplePlugin;
+impl Plugin for DownsamplePlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "shaders/downsample.wgsl");
+    }
+    fn finish(&self, app: &mut App) {
+        let shader = load_embedded_asset!(app, "shaders/downsample.wgsl");
+        let render_app = app.sub_app_mut(RenderApp);
+        let pipeline = DownsamplePipeline::new(render_app.world(), shader);
+        render_app.insert_resource(pipeline);
+    }
+}
+
+/// Pipelines of [`ReadbackDownsample`], one per format read back.
+#[derive(Resource)]
+struct DownsamplePipeline {
+    pipelines: Vec<(
+        TextureFormat,
+        BindGroupLayoutDescriptor,
+        CachedComputePipelineId,
+    )>,
+}
+impl DownsamplePipeline {
+    fn new(world: &World, shader: Handle<Shader>) -> Self {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipelines = [TextureFormat::Rgba8Unorm, TextureFormat::Rgba32Float]
+            .into_iter()
+            .map(|format| {
+                let layout = BindGroupLayoutDescriptor::new(
+                    "readback_downsample",
+                    &[
+                        texture_2d(TextureSampleType::Float { filterable: false })
+                            .build(0, ShaderStages::COMPUTE),
+                        texture_storage_2d(format, StorageTextureAccess::WriteOnly)
+                            .build(1, ShaderStages::COMPUTE),
+                    ],
+                );
+                let shader_defs = match format {
+                    TextureFormat::Rgba8Unorm => vec!["TARGET_RGBA8UNORM".into()],
+                    _ => Vec::new(),
+                };
+                let id = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                    label: Some("readback_downsample".into()),
+                    layout: vec![layout.clone()],
+                    shader: shader.clone(),
+                    shader_defs,
+                    entry_point: Some("main".into()),
+                    ..default()
+                });
+                (format, layout, id)
+            })
+            .collect();
+        Self { pipelines }
+    }
+    fn get(
+        &self,
+        format: TextureFormat,
+    ) -> Option<(&BindGroupLayoutDescriptor, CachedComputePipelineId)> {
+        self.pipelines
+            .iter()
+            .find(|(pipeline_format, ..)| *pipeline_format == format)
+            .map(|(_, layout, id)| (layout, *id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::tests::TestComputeShader;
+
+    type Downsample = ReadbackDownsample<TestComputeShader>;
+
+    #[test]
+    fn target_format_reads_8_bit_unorm_back_as_rgba8() {
+        for (format, target) in [
+            (TextureFormat::R8Unorm, TextureFormat::Rgba8Unorm),
+            (TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba8Unorm),
+            (TextureFormat::R8Snorm, TextureFormat::Rgba32Float),
+            (TextureFormat::Rgba16Float, TextureFormat::Rgba32Float),
+            (TextureFormat::R32Float, TextureFormat::Rgba32Float),
+        ] {
+            assert_eq!(Downsample::target_format(format), target);
+        }
+    }
+
+    #[test]
+    fn targets_are_sized_and_reused() {
+        let mut images = Assets::<Image>::default();
+        let source = images.add(Image::new_fill(
+            Extent3d {
+                width: 16,
+                height: 16,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        ));
+        let mut downsample = Downsample::new(UVec2::new(4, 0));
+        let target = downsample.target(0, source.clone(), &mut images);
+        let image = images.get(&target).unwrap();
+        // Sizes are at least a texel.
+        assert_eq!(
+            image.texture_descriptor.size,
+            Extent3d {
+                width: 4,
+                height: 1,
+                depth_or_array_layers: 1,
+            }
+        );
+        assert_eq!(image.texture_descriptor.format, TextureFormat::Rgba8Unorm);
+        assert!(
+            image
+                .texture_descriptor
+                .usage
+                .contains(TextureUsages::STORAGE_BINDING | TextureUsages::COPY_SRC)
+        );
+        assert_eq!(downsample.target(0, source.clone(), &mut images), target);
+
+        // Sources only in the render world are read back as float.
+        let render_only = downsample.target(0, Handle::default(), &mut images);
+        assert_ne!(render_only, target);
+        assert_eq!(
+            images.get(&render_only).unwrap().texture_descriptor.format,
+            TextureFormat::Rgba32Float
+        );
+        assert_eq!(downsample.targets.len(), 1);
+        downsample.target(1, source, &mut images);
+        assert_eq!(downsample.targets.len(), 2);
+    }
+}
@@ -0,0 +1,419 @@
+//! Messages passed between the main world and GPU buffers.
+
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    asset::{AssetId, Assets, Handle, RenderAssetUsages},
+    ecs::{
+        message::{Message, MessageReader, Messages},
+        resource::Resource,
+        system::Res,
+        world::{DeferredWorld, World},
+    },
+    log::{error_once, warn},
+    render::{
+        gpu_readback::Readback,
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferUsages, COPY_BUFFER_ALIGNMENT, ShaderSize, ShaderType,
+            encase::{
+                StorageBuffer,
+                internal::{CreateFrom, WriteInto},
+            },
+        },
+        renderer::{RenderContext, RenderQueue},
+        storage::{GpuShaderBuffer, ShaderBuffer},
+    },
+};
+
+use crate::{ComputeShader, ReadbackDecodeError, decode_readback};
+
+/// Buffer that `S` appends events of type `E` to, starting with the `EventHeader` from
+/// `#import bevy_compute_readback::events`. Decoded and written as messages after each readback.
+/// See [`ComputeShaderAppExt::add_compute_event_output`].
+///
+/// [`ComputeShaderAppExt::add_compute_event_output`]: crate::ComputeShaderAppExt::add_compute_event_output
+#[derive(Resource)]
+pub struct GpuEventBuffer<S: ComputeShader, E> {
+    /// Buffer to bind as `var<storage, read_write>` and return as `Readback::buffer`.
+    pub buffer: Handle<ShaderBuffer>,
+    capacity: u32,
+    /// Events dropped because the buffer was full.
+    pub overflowed: u64,
+    /// Number of events appended by the last dispatch read back, including dropped ones.
+    pub last_count: u32,
+    _marker: PhantomData<(S, E)>,
+}
+impl<S: ComputeShader, E: Message + ShaderType + ShaderSize + CreateFrom> GpuEventBuffer<S, E> {
+    pub(crate) fn new(buffers: &mut Assets<ShaderBuffer>, capacity: u32) -> Self {
+        Self {
+            buffer: buffers.add(CountedBuffer::<E>::storage_buffer(capacity)),
+            capacity,
+            overflowed: 0,
+            last_count: 0,
+            _marker: PhantomData,
+        }
+    }
+    /// Most events kept per dispatch.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+    /// Decode the events of a readback and write them as messages.
+    pub(crate) fn write(data: &[u8], world: &mut DeferredWorld) {
+        let events = match decode_counted::<E>(data) {
+            Ok(events) => events,
+            Err(error) => {
+                error_once!("{}: failed to decode events: {error}", S::shader_label());
+                return;
+            }
+        };
+        let Some(mut buffer) = world.get_resource_mut::<Self>() else {
+            return;
+        };
+        buffer.last_count = events.count;
+        if events.overflowed() > 0 {
+            buffer.overflowed += events.overflowed() as u64;
+            warn!(
+                "{}: {} events of {} emitted, dropped the ones past the capacity of {}",
+                S::shader_label(),
+                events.count,
+                std::any::type_name::<E>(),
+                events.capacity,
+            );
+        }
+        world
+            .resource_mut::<Messages<E>>()
+            .write_batch(events.items);
+    }
+}
+
+/// Buffer the shader appends up to `capacity` results of type `T` to, after an `EventHeader`
+/// with an atomic count. The count isn't reset automatically, see [`CountedBuffer::clear`].
+#[derive(Clone, Debug)]
+pub struct CountedBuffer<T> {
+    pub buffer: Handle<ShaderBuffer>,
+    pub _marker: PhantomData<fn() -> T>,
+}
+impl<T: ShaderType + ShaderSize + CreateFrom> CountedBuffer<T> {
+    /// Bytes of the header before the results.
+    pub const HEADER_SIZE: usize = 16;
+    /// Add a buffer for up to `capacity` results.
+    pub fn new(buffers: &mut Assets<ShaderBuffer>, capacity: u32) -> Self {
+        Self {
+            buffer: buffers.add(Self::storage_buffer(capacity)),
+            _marker: PhantomData,
+        }
+    }
+    /// Buffer for up to `capacity` results, with a count of 0 and the usages to clear and read
+    /// it back.
+    pub fn storage_buffer(capacity: u32) -> ShaderBuffer {
+        let stride = <Vec<T> as ShaderType>::min_size().get() as usize;
+        let mut data = vec![0; Self::HEADER_SIZE + stride * capacity as usize];
+        data[4..8].copy_from_slice(&capacity.to_le_bytes());
+        let mut buffer = ShaderBuffer::new(&data, RenderAssetUsages::RENDER_WORLD);
+        buffer.buffer_description.usage |= BufferUsages::COPY_DST | BufferUsages::COPY_SRC;
+        buffer
+    }
+    /// Readback of the whole buffer, for [`ComputeShader::readbacks`].
+    pub fn readback(&self) -> Readback {
+        Readback::buffer(self.buffer.clone())
+    }
+    /// Decode a readback of the buffer, see [`decode_counted`].
+    pub fn decode(data: &[u8]) -> Result<CountedItems<T>, ReadbackDecodeError> {
+        decode_counted(data)
+    }
+    /// Reset the count to 0, e.g. from [`ComputeShader::encode_pre_dispatch`].
+    pub fn clear(&self, ctx: &mut RenderContext, world: &World) {
+        let buffers = world.resource::<RenderAssets<GpuShaderBuffer>>();
+        if let Some(buffer) = buffers.get(&self.buffer) {
+            ctx.command_encoder()
+                .clear_buffer(&buffer.buffer, 0, Some(COPY_BUFFER_ALIGNMENT));
+        }
+    }
+}
+
+/// Results decoded from a [`CountedBuffer`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountedItems<T> {
+    /// The results appended, up to the capacity.
+    pub items: Vec<T>,
+    /// Number of results appended, including the ones past the capacity.
+    pub count: u32,
+    pub capacity: u32,
+}
+impl<T> CountedItems<T> {
+    /// Number of results dropped because the buffer was full.
+    pub fn overflowed(&self) -> u32 {
+        self.count.saturating_sub(self.capacity)
+    }
+}
+
+/// Decode a readback of a [`CountedBuffer`] or [`GpuEventBuffer`]: the results appended,
+/// reading no further than the count or the capacity.
+pub fn decode_counted<T: ShaderType + ShaderSize + CreateFrom>(
+    data: &[u8],
+) -> Result<CountedItems<T>, ReadbackDecodeError> {
+    let header = CountedBuffer::<T>::HEADER_SIZE;
+    if data.len() < header {
+        return Err(ReadbackDecodeError::Short {
+            len: data.len(),
+            size: header as u64,
+        });
+    }
+    let count = u32::from_le_bytes(data[..4].try_into().unwrap());
+    let capacity = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    let kept = count.min(capacity);
+    let stride = <Vec<T> as ShaderType>::min_size().get() as usize;
+    let expected = header + stride * kept as usize;
+    let Some(items) = data.get(header..expected) else {
+        return Err(ReadbackDecodeError::CountedLength {
+            len: data.len(),
+            count: kept,
+            expected,
+        });
+    };
+    // Encase can't read an empty runtime-sized array.
+    let items = if kept == 0 {
+        Vec::new()
+    } else {
+        decode_readback(items)?
+    };
+    Ok(CountedItems {
+        items,
+        count,
+        capacity,
+    })
+}
+
+/// Buffers added by [`ComputeShaderAppExt::add_compute_event_output`]: their decoders in the
+/// main world, and the buffers to clear before each dispatch in the render world.
+///
+/// [`ComputeShaderAppExt::add_compute_event_output`]: crate::ComputeShaderAppExt::add_compute_event_output
+#[derive(Resource)]
+pub(crate) struct GpuEventOutputs<S: ComputeShader> {
+    pub(crate) outputs: Vec<(AssetId<ShaderBuffer>, GpuEventWriter)>,
+    pub(crate) buffers: Vec<Handle<ShaderBuffer>>,
+    _marker: PhantomData<S>,
+}
+type GpuEventWriter = Arc<dyn Fn(&[u8], &mut DeferredWorld) + Send + Sync>;
+impl<S: ComputeShader> Default for GpuEventOutputs<S> {
+    fn default() -> Self {
+        Self {
+            outputs: Vec::new(),
+            buffers: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> GpuEventOutputs<S> {
+    /// Write the events of a readback if it's of an event buffer.
+    pub(crate) fn write(world: &mut DeferredWorld, index: usize, data: &[u8]) {
+        let Some(outputs) = world.get_resource::<Self>() else {
+            return;
+        };
+        let Some(Readback::Buffer { buffer, .. }) = world
+            .get_resource::<S>()
+            .and_then(|input| input.readbacks().into_iter().nth(index))
+        else {
+            return;
+        };
+        let writers = outputs
+            .outputs
+            .iter()
+            .filter(|(id, _)| *id == buffer.id())
+            .map(|(_, writer)| writer.clone())
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer(data, world);
+        }
+    }
+    /// Clear the event counts before the dispatch.
+    pub(crate) fn clear(&self, ctx: &mut RenderContext, buffers: &RenderAssets<GpuShaderBuffer>) {
+        for handle in &self.buffers {
+            if let Some(buffer) = buffers.get(handle) {
+                ctx.command_encoder()
+                    .clear_buffer(&buffer.buffer, 0, Some(COPY_BUFFER_ALIGNMENT));
+            }
+        }
+    }
+}
+
+/// Buffer of events of type `E` sent in the main world for `S` to read, uploaded before the
+/// next dispatch. See [`ComputeShaderAppExt::add_compute_event_input`].
+///
+/// [`ComputeShaderAppExt::add_compute_event_input`]: crate::ComputeShaderAppExt::add_compute_event_input
+#[derive(Resource)]
+pub struct GpuEventInput<S: ComputeShader, E> {
+    /// Buffer to bind as `var<storage, read>`.
+    pub buffer: Handle<ShaderBuffer>,
+    pub(crate) queue: GpuEventQueue,
+    _marker: PhantomData<(S, E)>,
+}
+impl<S: ComputeShader, E: Message + Clone + ShaderType + ShaderSize + WriteInto>
+    GpuEventInput<S, E>
+{
+    pub(crate) fn new(buffers: &mut Assets<ShaderBuffer>, capacity: u32) -> Self {
+        let stride = <Vec<E> as ShaderType>::min_size().get() as usize;
+        let size = GpuEventQueue::HEADER_SIZE + stride * capacity as usize;
+        let mut buffer = ShaderBuffer::with_size(size, RenderAssetUsages::RENDER_WORLD);
+        buffer.buffer_description.usage |= BufferUsages::COPY_DST;
+        let buffer = buffers.add(buffer);
+        Self {
+            buffer: buffer.clone(),
+            queue: GpuEventQueue {
+                buffer,
+                capacity,
+                stride,
+                event: std::any::type_name::<E>(),
+                pending: Arc::default(),
+            },
+            _marker: PhantomData,
+        }
+    }
+    /// Most events uploaded per dispatch.
+    pub fn capacity(&self) -> u32 {
+        self.queue.capacity
+    }
+    /// Encode the events sent this frame for the next dispatch.
+    pub(crate) fn collect(mut reader: MessageReader<E>, input: Res<Self>) {
+        let events = reader.read().cloned().collect::<Vec<_>>();
+        if events.is_empty() {
+            return;
+        }
+        let mut buffer = StorageBuffer::new(Vec::new());
+        buffer
+            .write(&events)
+            .expect("writing to a Vec can't run out of space");
+        let data = buffer.into_inner();
+        let queue = &input.queue;
+        let mut pending = queue.pending.lock().unwrap();
+        let kept = (queue.capacity as usize * queue.stride).saturating_sub(pending.data.len());
+        pending
+            .data
+            .extend_from_slice(&data[..kept.min(data.len())]);
+        pending.count += events.len();
+    }
+}
+
+/// Events waiting for the next dispatch, shared by both worlds.
+#[derive(Clone)]
+pub(crate) struct GpuEventQueue {
+    buffer: Handle<ShaderBuffer>,
+    capacity: u32,
+    stride: usize,
+    event: &'static str,
+    pending: Arc<Mutex<PendingEvents>>,
+}
+#[derive(Default)]
+struct PendingEvents {
+    /// Number of events sent, including the ones past the capacity.
+    count: usize,
+    data: Vec<u8>,
+}
+impl GpuEventQueue {
+    const HEADER_SIZE: usize = 16;
+    /// Upload the pending events, or a count of 0 if there are none.
+    pub(crate) fn upload(
+        &self,
+        label: &str,
+        queue: &RenderQueue,
+        buffers: &RenderAssets<GpuShaderBuffer>,
+    ) {
+        let Some(buffer) = buffers.get(&self.buffer) else {
+            return;
+        };
+        let mut pending = self.pending.lock().unwrap();
+        let kept = pending.data.len() / self.stride.max(1);
+        if pending.count > kept {
+            warn!(
+                "{label}: {} events of {} sent, dropped the ones past the capacity of {}",
+                pending.count, self.event, self.capacity
+            );
+        }
+        let mut data = Vec::with_capacity(Self::HEADER_SIZE + pending.data.len());
+        for value in [kept as u32, self.capacity, 0, 0] {
+            data.extend(value.to_le_bytes());
+        }
+        data.append(&mut pending.data);
+        pending.count = 0;
+        queue.write_buffer(&buffer.buffer, 0, &data);
+    }
+}
+
+/// Event queues added by [`ComputeShaderAppExt::add_compute_event_input`], uploaded before
+/// each dispatch in the render world.
+///
+/// [`ComputeShaderAppExt::add_compute_event_input`]: crate::ComputeShaderAppExt::add_compute_event_input
+#[derive(Resource)]
+pub(crate) struct GpuEventInputs<S: ComputeShader> {
+    pub(crate) inputs: Vec<GpuEventQueue>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for GpuEventInputs<S> {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A counted buffer readback holding `items`.
+    fn counted_data(count: u32, capacity: u32, items: &[u32]) -> Vec<u8> {
+        let mut data = vec![0; CountedBuffer::<u32>::HEADER_SIZE];
+        data[..4].copy_from_slice(&count.to_le_bytes());
+        data[4..8].copy_from_slice(&capacity.to_le_bytes());
+        data.extend(items.iter().flat_map(|item| item.to_le_bytes()));
+        data
+    }
+
+    #[test]
+    fn decode_counted_empty() {
+        let items = decode_counted::<u32>(&counted_data(0, 4, &[7; 4])).unwrap();
+        assert_eq!(
+            items,
+            CountedItems {
+                items: vec![],
+                count: 0,
+                capacity: 4,
+            }
+        );
+        assert_eq!(items.overflowed(), 0);
+    }
+
+    #[test]
+    fn decode_counted_clamps_to_capacity() {
+        let items = decode_counted::<u32>(&counted_data(10, 3, &[1, 2, 3])).unwrap();
+        assert_eq!(items.items, [1, 2, 3]);
+        assert_eq!((items.count, items.capacity), (10, 3));
+        assert_eq!(items.overflowed(), 7);
+    }
+
+    #[test]
+    fn decode_counted_truncated() {
+        let data = counted_data(3, 4, &[1, 2]);
+        assert_eq!(
+            decode_counted::<u32>(&data),
+            Err(ReadbackDecodeError::CountedLength {
+                len: data.len(),
+                count: 3,
+                expected: CountedBuffer::<u32>::HEADER_SIZE + 12,
+            })
+        );
+        assert_eq!(
+            decode_counted::<u32>(&data[..8]),
+            Err(ReadbackDecodeError::Short {
+                len: 8,
+                size: CountedBuffer::<u32>::HEADER_SIZE as u64,
+            })
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Save texture readbacks to disk. Requires the `io` feature.
+//! PNG and EXR convert the data for viewing, KTX2 keeps the exact texture format.
+
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    tasks::{IoTaskPool, Task},
+};
+use image::{
+    DynamicImage, ImageBuffer, ImageError, ImageFormat, Luma, Rgb32FImage, Rgba, Rgba32FImage,
+    RgbaImage,
+};
+use thiserror::Error;
+
+use crate::{ReadbackData, ReadbackDecodeError, unpad_texture_data};
+
+/// How float texels are converted to 8 bits for PNG.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PngConversion {
+    /// Clamp to `[0, 1]`.
+    #[default]
+    Clamp,
+    /// Map colors with `x / (1 + x)`, keeping detail above 1. Alpha is clamped.
+    Reinhard,
+}
+
+/// Errors saving a readback.
+#[derive(Error, Debug)]
+pub enum ReadbackSaveError {
+    #[error(transparent)]
+    Decode(#[from] ReadbackDecodeError),
+    #[error("texture format {format:?} can't be saved as {target}, read back {supported} instead")]
+    UnsupportedFormat {
+        format: TextureFormat,
+        target: &'static str,
+        supported: &'static str,
+    },
+    #[error("failed to write the image: {0}")]
+    Image(#[from] ImageError),
+    #[error("failed to write the file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+const PNG_FORMATS: &str = "Rgba8Unorm, Bgra8Unorm, R8Unorm, Rgba16Unorm, R32Float or Rgba32Float";
+const EXR_FORMATS: &str = "R32Float or Rgba32Float";
+
+/// Save a texture readback of the given size and format as a PNG, removing the row padding.
+/// Float formats are clamped to 8 bits, see [`save_readback_as_png_with`].
+/// Layers of 3D and array textures are stacked vertically.
+pub fn save_readback_as_png(
+    data: &[u8],
+    size: Extent3d,
+    format: TextureFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), ReadbackSaveError> {
+    save_readback_as_png_with(data, size, format, PngConversion::Clamp, path)
+}
+
+/// [`save_readback_as_png`], converting float formats with `conversion`.
+pub fn save_readback_as_png_with(
+    data: &[u8],
+    size: Extent3d,
+    format: TextureFormat,
+    conversion: PngConversion,
+    path: impl AsRef<Path>,
+) -> Result<(), ReadbackSaveError> {
+    let data = unpad_texture_data(data, size, format)?;
+    let (width, height) = (size.width, size.height * size.depth_or_array_layers);
+    let image = match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+            RgbaImage::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            let data = data
+                .chunks_exact(4)
+                .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+                .collect();
+            RgbaImage::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        TextureFormat::R8Unorm => {
+            ImageBuffer::<Luma<u8>, _>::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        TextureFormat::Rgba16Unorm => {
+            let data = data
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect();
+            ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, data)
+                .map(DynamicImage::ImageRgba16)
+        }
+        TextureFormat::R32Float | TextureFormat::Rgba32Float => {
+            let channels = if format == TextureFormat::R32Float {
+                1
+            } else {
+                4
+            };
+            let data = floats(&data)
+                .enumerate()
+                .map(|(index, value)| {
+                    let alpha = channels == 4 && index % 4 == 3;
+                    let value = match conversion {
+                        PngConversion::Reinhard if !alpha => value / (1.0 + value),
+                        _ => value,
+                    };
+                    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+                })
+                .collect();
+            if channels == 1 {
+                ImageBuffer::<Luma<u8>, _>::from_raw(width, height, data)
+                    .map(DynamicImage::ImageLuma8)
+            } else {
+                RgbaImage::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+            }
+        }
+        _ => None,
+    };
+    let image = image.ok_or(ReadbackSaveError::UnsupportedFormat {
+        format,
+        target: "PNG",
+        supported: PNG_FORMATS,
+    })?;
+    image.save_with_format(path, ImageFormat::Png)?;
+    Ok(())
+}
+
+/// Save a float texture readback of the given size and format as an OpenEXR image, keeping
+/// full precision. Layers of 3D and array textures are stacked vertically.
+pub fn save_readback_as_exr(
+    data: &[u8],
+    size: Extent3d,
+    format: TextureFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), ReadbackSaveError> {
+    let unsupported = ReadbackSaveError::UnsupportedFormat {
+        format,
+        target: "EXR",
+        supported: EXR_FORMATS,
+    };
+    let data = match format {
+        TextureFormat::R32Float | TextureFormat::Rgba32Float => {
+            unpad_texture_data(data, size, format)?
+        }
+        _ => return Err(unsupported),
+    };
+    let (width, height) = (size.width, size.height * size.depth_or_array_layers);
+    let image = if format == TextureFormat::R32Float {
+        let data = floats(&data).flat_map(|value| [value; 3]).collect();
+        Rgb32FImage::from_raw(width, height, data).map(DynamicImage::ImageRgb32F)
+    } else {
+        Rgba32FImage::from_raw(width, height, floats(&data).collect())
+            .map(DynamicImage::ImageRgba32F)
+    };
+    image
+        .ok_or(unsupported)?
+        .save_with_format(path, ImageFormat::OpenExr)?;
+    Ok(())
+}
+
+/// [`save_readback_as_png`] on the [`IoTaskPool`], so large images don't hitch the frame.
+/// Detach the task to save in the background, or poll it for the result.
+pub fn save_readback_as_png_async(
+    data: ReadbackData,
+    size: Extent3d,
+    format: TextureFormat,
+    path: impl Into<PathBuf>,
+) -> Task<Result<(), ReadbackSaveError>> {
+    let path = path.into();
+    IoTaskPool::get().spawn(async move { save_readback_as_png(&data, size, format, path) })
+}
+
+/// [`save_readback_as_exr`] on the [`IoTaskPool`], so large images don't hitch the frame.
+/// Detach the task to save in the background, or poll it for the result.
+pub fn save_readback_as_exr_async(
+    data: ReadbackData,
+    size: Extent3d,
+    format: TextureFormat,
+    path: impl Into<PathBuf>,
+) -> Task<Result<(), ReadbackSaveError>> {
+    let path = path.into();
+    IoTaskPool::get().spawn(async move { save_readback_as_exr(&data, size, format, path) })
+}
+
+/// Little-endian `f32`s of tightly packed texels.
+fn floats(data: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    data.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+const KTX2_FORMATS: &str = "an uncompressed 8, 16 or 32 bit per channel color format";
+
+/// Channel type of a format, for its KTX2 data format descriptor.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ChannelKind {
+    Unorm,
+    Snorm,
+    Uint,
+    Sint,
+    Float,
+}
+
+/// Vulkan format, channels, bytes per channel, channel kind, sRGB and BGR order of a format.
+fn ktx2_format(format: TextureFormat) -> Option<(u32, u32, u32, ChannelKind, bool, bool)> {
+    use ChannelKind::*;
+    use TextureFormat as F;
+    Some(match format {
+        F::R8Unorm => (9, 1, 1, Unorm, false, false),
+        F::R8Snorm => (10, 1, 1, Snorm, false, false),
+        F::R8Uint => (13, 1, 1, Uint, false, false),
+        F::R8Sint => (14, 1, 1, Sint, false, false),
+        F::Rg8Unorm => (16, 2, 1, Unorm, false, false),
+        F::Rg8Snorm => (17, 2, 1, Snorm, false, false),
+        F::Rg8Uint => (20, 2, 1, Uint, false, false),
+        F::Rg8Sint => (21, 2, 1, Sint, false, false),
+        F::Rgba8Unorm => (37, 4, 1, Unorm, false, false),
+        F::Rgba8Snorm => (38, 4, 1, Snorm, false, false),
+        F::Rgba8Uint => (41, 4, 1, Uint, false, false),
+        F::Rgba8Sint => (42, 4, 1, Sint, false, false),
+        F::Rgba8UnormSrgb => (43, 4, 1, Unorm, true, false),
+        F::Bgra8Unorm => (44, 4, 1, Unorm, false, true),
+        F::Bgra8UnormSrgb => (50, 4, 1, Unorm, true, true),
+        F::R16Unorm => (70, 1, 2, Unorm, false, false),
+        F::R16Snorm => (71, 1, 2, Snorm, false, false),
+        F::R16Uint => (74, 1, 2, Uint, false, false),
+        F::R16Sint => (75, 1, 2, Sint, false, false),
+        F::R16Float => (76, 1, 2, Float, false, false),
+        F::Rg16Unorm => (77, 2, 2, Unorm, false, false),
+        F::Rg16Snorm => (78, 2, 2, Snorm, false, false),
+        F::Rg16Uint => (81, 2, 2, Uint, false, false),
+        F::Rg16Sint => (82, 2, 2, Sint, false, false),
+        F::Rg16Float => (83, 2, 2, Float, false, false),
+        F::Rgba16Unorm => (91, 4, 2, Unorm, false, false),
+        F::Rgba16Snorm => (92, 4, 2, Snorm, false, false),
+        F::Rgba16Uint => (95, 4, 2, Uint, false, false),
+        F::Rgba16Sint => (96, 4, 2, Sint, false, false),
+        F::Rgba16Float => (97, 4, 2, Float, false, false),
+        F::R32Uint => (98, 1, 4, Uint, false, false),
+        F::R32Sint => (99, 1, 4, Sint, false, false),
+        F::R32Float => (100, 1, 4, Float, false, false),
+        F::Rg32Uint => (101, 2, 4, Uint, false, false),
+        F::Rg32Sint => (102, 2, 4, Sint, false, false),
+        F::Rg32Float => (103, 2, 4, Float, false, false),
+        F::Rgba32Uint => (107, 4, 4, Uint, false, false),
+        F::Rgba32Sint => (108, 4, 4, Sint, false, false),
+        F::Rgba32Float => (109, 4, 4, Float, false, false),
+        _ => return None,
+    })
+}
+
+/// Encode texture readbacks as a KTX2 file, keeping the exact texture format.
+/// `levels` holds one readback per mip level starting from level 0, with the row padding.
+pub fn encode_ktx2(
+    levels: &[&[u8]],
+    size: Extent3d,
+    dimension: TextureDimension,
+    format: TextureFormat,
+) -> Result<Vec<u8>, ReadbackSaveError> {
+    let Some((vk_format, channels, channel_size, kind, srgb, bgr)) = ktx2_format(format) else {
+        return Err(ReadbackSaveError::UnsupportedFormat {
+            format,
+            target: "KTX2",
+            supported: KTX2_FORMATS,
+        });
+    };
+    let texel_size = channels * channel_size;
+    let level_data = levels
+        .iter()
+        .enumerate()
+        .map(|(level, data)| {
+            unpad_texture_data(data, size.mip_level_size(level as u32, dimension), format)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Data format descriptor, a single basic block with one sample per channel.
+    let mut dfd = Vec::new();
+    let block_size = 24 + 16 * channels;
+    dfd.extend((4 + block_size).to_le_bytes());
+    dfd.extend(0u32.to_le_bytes());
+    dfd.extend(2u16.to_le_bytes());
+    dfd.extend((block_size as u16).to_le_bytes());
+    // RGBSDA color model, BT.709 primaries, linear or sRGB transfer, straight alpha.
+    dfd.extend([1, 1, if srgb { 2 } else { 1 }, 0]);
+    dfd.extend([0; 4]);
+    dfd.extend([texel_size as u8, 0, 0, 0, 0, 0, 0, 0]);
+    for channel in 0..channels {
+        let id = match channel {
+            3 => 15,
+            0 if bgr => 2,
+            2 if bgr => 0,
+            channel => channel as u8,
+        };
+        let bits = channel_size * 8;
+        let mut channel_type = id;
+        if matches!(
+            kind,
+            ChannelKind::Snorm | ChannelKind::Sint | ChannelKind::Float
+        ) {
+            channel_type |= 0x40;
+        }
+        if kind == ChannelKind::Float {
+            channel_type |= 0x80;
+        }
+        if srgb && id == 15 {
+            channel_type |= 0x10;
+        }
+        let (lower, upper): (u32, u32) = match kind {
+            ChannelKind::Unorm => (0, u32::MAX >> (32 - bits)),
+            ChannelKind::Snorm => ((-(1i32 << (bits - 1)) + 1) as u32, (1 << (bits - 1)) - 1),
+            ChannelKind::Uint => (0, 1),
+            ChannelKind::Sint => (-1i32 as u32, 1),
+            ChannelKind::Float => ((-1.0f32).to_bits(), 1.0f32.to_bits()),
+        };
+        dfd.extend(((channel * bits) as u16).to_le_bytes());
+        dfd.extend([(bits - 1) as u8, channel_type]);
+        dfd.extend([0; 4]);
+        dfd.extend(lower.to_le_bytes());
+        dfd.extend(upper.to_le_bytes());
+    }
+
+    // Header, index and level index, then the descriptor and the levels, smallest first.
+    let header_size = 80 + 24 * level_data.len();
+    let dfd_offset = header_size;
+    let alignment = texel_size.max(4) as usize;
+    let mut offset = dfd_offset + dfd.len();
+    let mut level_index = vec![(0, 0); level_data.len()];
+    for (level, data) in level_data.iter().enumerate().rev() {
+        offset = offset.next_multiple_of(alignment);
+        level_index[level] = (offset, data.len());
+        offset += data.len();
+    }
+
+    let mut file = Vec::with_capacity(offset);
+    file.extend([
+        0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+    ]);
+    let (height, depth, layers) = match dimension {
+        TextureDimension::D1 => (0, 0, size.depth_or_array_layers),
+        TextureDimension::D2 => (size.height, 0, size.depth_or_array_layers),
+        TextureDimension::D3 => (size.height, size.depth_or_array_layers, 1),
+    };
+    let layers = if layers > 1 { layers } else { 0 };
+    for value in [
+        vk_format,
+        channel_size,
+        size.width,
+        height,
+        depth,
+        layers,
+        1,
+        level_data.len() as u32,
+        0,
+        dfd_offset as u32,
+        dfd.len() as u32,
+        0,
+        0,
+    ] {
+        file.extend(value.to_le_bytes());
+    }
+    file.extend(0u64.to_le_bytes());
+    file.extend(0u64.to_le_bytes());
+    for (offset, len) in &level_index {
+        file.extend((*offset as u64).to_le_bytes());
+        file.extend((*len as u64).to_le_bytes());
+        file.extend((*len as u64).to_le_bytes());
+    }
+    file.extend(dfd);
+    for (level, data) in level_data.iter().enumerate().rev() {
+        file.resize(level_index[level].0, 0);
+        file.extend(data);
+    }
+    Ok(file)
+}
+
+/// Save texture readbacks as a KTX2 file, see [`encode_ktx2`].
+pub fn save_readback_as_ktx2(
+    levels: &[&[u8]],
+    size: Extent3d,
+    dimension: TextureDimension,
+    format: TextureFormat,
+    path: impl AsRef<Path>,
+) -> Result<(), ReadbackSaveError> {
+    std::fs::write(path, encode_ktx2(levels, size, dimension, format)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        image::{CompressedImageFormats, ktx2_buffer_to_image},
+        render::renderer::RenderDevice,
+    };
+
+    use super::*;
+
+    /// Texels of `size` and a readback of them with the row padding.
+    fn level(size: Extent3d, texel_size: usize, seed: usize) -> (Vec<u8>, Vec<u8>) {
+        let row = size.width as usize * texel_size;
+        let rows = (size.height * size.depth_or_array_layers) as usize;
+        let padded_row = if rows > 1 {
+            RenderDevice::align_copy_bytes_per_row(row)
+        } else {
+            row
+        };
+        let texels: Vec<u8> = (0..row * rows)
+            .map(|index| (index * 7 + seed) as u8)
+            .collect();
+        let mut padded = vec![0xff; padded_row * rows];
+        for (index, texels) in texels.chunks(row).enumerate() {
+            padded[index * padded_row..][..row].copy_from_slice(texels);
+        }
+        (texels, padded)
+    }
+
+    #[test]
+    fn encode_ktx2_round_trip() {
+        for format in [TextureFormat::Rg16Float, TextureFormat::R32Uint] {
+            for level_count in [1, 3] {
+                for (dimension, depth_or_array_layers) in
+                    [(TextureDimension::D2, 2), (TextureDimension::D3, 4)]
+                {
+                    let size = Extent3d {
+                        width: 5,
+                        height: 3,
+                        depth_or_array_layers,
+                    };
+                    let (texels, padded): (Vec<_>, Vec<_>) = (0..level_count)
+                        .map(|index| {
+                            level(size.mip_level_size(index, dimension), 4, index as usize)
+                        })
+                        .unzip();
+                    let levels: Vec<&[u8]> = padded.iter().map(Vec::as_slice).collect();
+                    let bytes = encode_ktx2(&levels, size, dimension, format).unwrap();
+
+                    let image =
+                        ktx2_buffer_to_image(&bytes, CompressedImageFormats::NONE, false).unwrap();
+                    let descriptor = &image.texture_descriptor;
+                    assert_eq!(descriptor.format, format);
+                    assert_eq!(descriptor.size, size);
+                    assert_eq!(descriptor.dimension, dimension);
+                    assert_eq!(descriptor.mip_level_count, level_count);
+                    assert_eq!(image.data, Some(texels.concat()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Building meshes from vertex readbacks.
+
+use std::fmt::Debug;
+
+use bevy::{
+    asset::{Assets, Handle, RenderAssetUsages},
+    math::{Vec2, Vec3},
+    mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues},
+    render::render_resource::ShaderType,
+};
+
+use crate::{ReadbackDecodeError, decode_counted};
+
+/// Vertex for [`vertices_to_mesh`], matching a WGSL struct of
+/// `position: vec3<f32>, normal: vec3<f32>, uv: vec2<f32>`.
+#[derive(ShaderType, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeshVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+}
+
+/// Write vertices generated on the GPU into the mesh at `handle`, as a triangle list, adding
+/// the mesh if missing. Without `indices`, every three vertices form a triangle.
+pub fn vertices_to_mesh(
+    vertices: &[MeshVertex],
+    indices: Option<&[u32]>,
+    meshes: &mut Assets<Mesh>,
+    handle: &Handle<Mesh>,
+) {
+    if let Some(mut mesh) = meshes.get_mut(handle) {
+        write_vertices(&mut mesh, vertices, indices);
+        return;
+    }
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+    write_vertices(&mut mesh, vertices, indices);
+    let _ = meshes.insert(handle.id(), mesh);
+}
+
+/// Decode a readback of a [`CountedBuffer`] of [`MeshVertex`] and write it with
+/// [`vertices_to_mesh`], returning the number of vertices.
+///
+/// [`CountedBuffer`]: crate::CountedBuffer
+pub fn readback_to_mesh(
+    data: &[u8],
+    meshes: &mut Assets<Mesh>,
+    handle: &Handle<Mesh>,
+) -> Result<usize, ReadbackDecodeError> {
+    let vertices = decode_counted::<MeshVertex>(data)?.items;
+    vertices_to_mesh(&vertices, None, meshes, handle);
+    Ok(vertices.len())
+}
+
+/// Replace the mesh's positions, normals, UVs and indices, reusing their allocations.
+fn write_vertices(mesh: &mut Mesh, vertices: &[MeshVertex], indices: Option<&[u32]>) {
+    let float32x3 = |values: Option<VertexAttributeValues>| match values {
+        Some(VertexAttributeValues::Float32x3(mut values)) => {
+            values.clear();
+            values
+        }
+        _ => Vec::new(),
+    };
+    let mut positions = float32x3(mesh.remove_attribute(Mesh::ATTRIBUTE_POSITION));
+    let mut normals = float32x3(mesh.remove_attribute(Mesh::ATTRIBUTE_NORMAL));
+    let mut uvs = match mesh.remove_attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(mut values)) => {
+            values.clear();
+            values
+        }
+        _ => Vec::new(),
+    };
+    positions.extend(vertices.iter().map(|vertex| vertex.position.to_array()));
+    normals.extend(vertices.iter().map(|vertex| vertex.normal.to_array()));
+    uvs.extend(vertices.iter().map(|vertex| vertex.uv.to_array()));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    match indices {
+        Some(indices) => {
+            let mut values = match mesh.remove_indices() {
+                Some(Indices::U32(mut values)) => {
+                    values.clear();
+                    values
+                }
+                _ => Vec::new(),
+            };
+            values.extend_from_slice(indices);
+            mesh.insert_indices(Indices::U32(values));
+        }
+        None => {
+            mesh.remove_indices();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_resource::encase::StorageBuffer;
+
+    use super::*;
+    use crate::CountedBuffer;
+
+    fn vertex(index: u32) -> MeshVertex {
+        MeshVertex {
+            position: Vec3::splat(index as f32),
+            normal: Vec3::Y,
+            uv: Vec2::splat(index as f32),
+        }
+    }
+
+    /// Positions, UVs and indices of the mesh at `handle`.
+    fn contents(
+        meshes: &Assets<Mesh>,
+        handle: &Handle<Mesh>,
+    ) -> (Vec<f32>, Vec<f32>, Option<Vec<usize>>) {
+        let mesh = meshes.get(handle).unwrap();
+        assert_eq!(mesh.primitive_topology(), PrimitiveTopology::TriangleList);
+        let positions = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(VertexAttributeValues::as_float3)
+            .unwrap()
+            .iter()
+            .map(|position| position[0])
+            .collect();
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            panic!("the mesh has no UVs");
+        };
+        let uvs = uvs.iter().map(|uv| uv[0]).collect();
+        let indices = mesh.indices().map(|indices| indices.iter().collect());
+        (positions, uvs, indices)
+    }
+
+    #[test]
+    fn vertices_to_mesh_replaces_the_mesh() {
+        let mut meshes = Assets::<Mesh>::default();
+        let handle = Handle::default();
+        let vertices: Vec<_> = (0..4).map(vertex).collect();
+        vertices_to_mesh(&vertices, Some(&[0, 1, 2, 2, 1, 3]), &mut meshes, &handle);
+        assert_eq!(
+            contents(&meshes, &handle),
+            (
+                vec![0.0, 1.0, 2.0, 3.0],
+                vec![0.0, 1.0, 2.0, 3.0],
+                Some(vec![0, 1, 2, 2, 1, 3])
+            )
+        );
+        // Without indices, every three vertices form a triangle.
+        vertices_to_mesh(&vertices[1..], None, &mut meshes, &handle);
+        assert_eq!(
+            contents(&meshes, &handle),
+            (vec![1.0, 2.0, 3.0], vec![1.0, 2.0, 3.0], None)
+        );
+        assert_eq!(meshes.len(), 1);
+    }
+
+    #[test]
+    fn readback_to_mesh_decodes_counted_vertices() {
+        let vertices: Vec<_> = (0..2).map(vertex).collect();
+        let mut data = vec![0; CountedBuffer::<MeshVertex>::HEADER_SIZE];
+        data[..4].copy_from_slice(&2u32.to_le_bytes());
+        data[4..8].copy_from_slice(&3u32.to_le_bytes());
+        let mut items = StorageBuffer::new(Vec::<u8>::new());
+        items.write(&vertices).unwrap();
+        data.extend(items.into_inner());
+
+        let mut meshes = Assets::<Mesh>::default();
+        let handle = Handle::default();
+        assert_eq!(readback_to_mesh(&data, &mut meshes, &handle), Ok(2));
+        assert_eq!(
+            contents(&meshes, &handle),
+            (vec![0.0, 1.0], vec![0.0, 1.0], None)
+        );
+        assert_eq!(
+            readback_to_mesh(&data[..8], &mut meshes, &handle),
+            Err(ReadbackDecodeError::Short { len: 8, size: 16 })
+        );
+    }
+}
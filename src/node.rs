@@ -0,0 +1,1362 @@
+//! The render world node dispatching each compute shader.
+
+use std::{any::TypeId, fmt::Debug, marker::PhantomData, time::Duration};
+
+use bevy::{
+    diagnostic::FrameCount,
+    ecs::{
+        entity::Entity,
+        query::With,
+        resource::Resource,
+        system::{Local, Query, Res, ResMut},
+        world::{Mut, World},
+    },
+    log::{error_once, warn},
+    platform::{collections::HashMap, time::Instant},
+    render::{
+        Extract,
+        extract_resource::ExtractResource,
+        gpu_readback::Readback,
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferUsages, CachedPipelineState, ComputePassDescriptor, PipelineCache,
+        },
+        renderer::{RenderContext, RenderQueue},
+        storage::GpuShaderBuffer,
+        sync_world::RenderEntity,
+        texture::GpuImage,
+    },
+    time::{Time, Virtual},
+    utils::default,
+};
+
+use crate::{
+    ComputeDependency, ComputeNodeState, ComputeNodeStatus, ComputePaused, ComputePipeline,
+    ComputeProgress, ComputeShader, ComputeShaderBindGroup, ComputeShaderError, ComputeShaderGate,
+    ComputeShaderReadback, ComputeViewBindGroup, GpuEventInputs, GpuEventOutputs, InputSnapshot,
+    ReadbackDownsample, ReadbackInfo, ReadbackInfos, ReadbackLimit, ReadbackLimitOverride,
+    ReadbackRange, ReadbackRegion, ReadbackTileInfo, ReadbackTiles, ReadbackTracker,
+    TextureRowLayout, TileCursor, copy_buffer_to_image, error_report,
+};
+
+/// Plugin settings kept in the main world after [`RemoveComputeShader`],
+/// so [`RestartComputeShader`] can recreate what it removed.
+///
+/// [`RemoveComputeShader`]: crate::RemoveComputeShader
+/// [`RestartComputeShader`]: crate::RestartComputeShader
+#[derive(Resource)]
+pub(crate) struct ComputeShaderConfig<S: ComputeShader> {
+    pub(crate) limit: ReadbackLimit,
+    pub(crate) readback_interval: usize,
+    pub(crate) reset_limit_on_change: bool,
+    pub(crate) warmup: usize,
+    pub(crate) dispatch_interval: usize,
+    pub(crate) dispatch_rate: Option<DispatchRate>,
+    pub(crate) synchronized: bool,
+    pub(crate) max_in_flight_readbacks: usize,
+    pub(crate) dependencies: Vec<ComputeDependency>,
+    pub(crate) remove_on_complete: bool,
+    /// Number of run conditions, see [`ComputeShaderGate`].
+    pub(crate) conditions: usize,
+    pub(crate) _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ComputeShaderConfig<S> {
+    pub(crate) fn tracker(&self) -> ReadbackTracker<S> {
+        ReadbackTracker::new(self.remove_on_complete)
+    }
+    pub(crate) fn node(&self) -> ComputeNode<S> {
+        ComputeNode {
+            limit: self.limit,
+            plugin_limit: self.limit,
+            readback_interval: self.readback_interval,
+            reset_limit_on_change: self.reset_limit_on_change,
+            warmup: self.warmup,
+            dispatch_interval: self.dispatch_interval,
+            dispatch_rate: self.dispatch_rate,
+            synchronized: self.synchronized,
+            in_flight: InFlightLimit::new(self.max_in_flight_readbacks),
+            dependencies: self.dependencies.clone(),
+            ..default()
+        }
+    }
+}
+
+/// Fixed dispatch rate, see [`ComputeShaderPlugin::dispatch_rate`].
+/// Each frame dispatches the ticks elapsed as iterations of the same pass, up to
+/// [`DispatchRate::max_ticks_per_frame`].
+///
+/// [`ComputeShaderPlugin::dispatch_rate`]: crate::ComputeShaderPlugin::dispatch_rate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DispatchRate {
+    /// Ticks per second.
+    pub hz: f64,
+    /// Most ticks dispatched in one frame. Time beyond that is dropped instead of caught up.
+    pub max_ticks_per_frame: u32,
+}
+impl DispatchRate {
+    /// Dispatch `hz` times per second, catching up at most 4 ticks per frame.
+    pub fn hz(hz: f64) -> Self {
+        Self {
+            hz,
+            max_ticks_per_frame: 4,
+        }
+    }
+    /// Set [`DispatchRate::max_ticks_per_frame`].
+    pub fn with_max_ticks_per_frame(mut self, max_ticks_per_frame: u32) -> Self {
+        self.max_ticks_per_frame = max_ticks_per_frame;
+        self
+    }
+    /// Duration of a tick. Panics unless `hz` is finite and positive.
+    pub fn period(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.hz)
+    }
+    /// Whether `hz` is finite and positive.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.hz.is_finite() && self.hz > 0.0
+    }
+}
+
+/// Render world entities receiving this shader's readbacks, extracted each frame.
+#[derive(Resource)]
+pub(crate) struct ReadbackEntities<S: ComputeShader> {
+    entities: Vec<Entity>,
+    /// Main world entities of the `entities`, in the same order.
+    main_entities: Vec<Entity>,
+    /// Requested buffer ranges of the `entities`, in the same order.
+    ranges: Vec<Option<ReadbackRange>>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ReadbackEntities<S> {
+    fn default() -> Self {
+        Self {
+            entities: Vec::new(),
+            main_entities: Vec::new(),
+            ranges: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ReadbackEntities<S> {
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn extract(
+        mut readback_entities: ResMut<Self>,
+        readbacks: Extract<
+            Query<
+                (Entity, RenderEntity, Option<&ReadbackRange>),
+                (With<ComputeShaderReadback<S>>, With<Readback>),
+            >,
+        >,
+    ) {
+        let readback_entities = &mut *readback_entities;
+        readback_entities.entities.clear();
+        readback_entities.main_entities.clear();
+        readback_entities.ranges.clear();
+        for (entity, render_entity, range) in &readbacks {
+            readback_entities.main_entities.push(entity);
+            readback_entities.entities.push(render_entity);
+            readback_entities.ranges.push(range.copied());
+        }
+    }
+    /// Take this frame's readbacks, dropping buffer readbacks whose range doesn't fit.
+    fn take_checked(world: &mut World) -> (Vec<Entity>, Vec<Entity>, Vec<Option<ReadbackRange>>) {
+        let mut readback_entities = world.resource_mut::<Self>();
+        let mut entities = std::mem::take(&mut readback_entities.entities);
+        let mut main_entities = std::mem::take(&mut readback_entities.main_entities);
+        let mut ranges = std::mem::take(&mut readback_entities.ranges);
+        let mut index = 0;
+        while index < entities.len() {
+            if clamp_buffer_readback::<S>(world, entities[index], ranges[index]) {
+                index += 1;
+            } else {
+                entities.remove(index);
+                main_entities.remove(index);
+                ranges.remove(index);
+            }
+        }
+        (entities, main_entities, ranges)
+    }
+}
+
+/// Crate-wide readback settings, shared by all compute shaders. Extracted to the render world.
+#[derive(Resource, ExtractResource, Clone, Debug)]
+pub struct ComputeReadbackSettings {
+    /// Most bytes read back per frame across all shaders. Shaders over budget drop their
+    /// readbacks that frame and go first the next one.
+    pub max_bytes_per_frame: usize,
+}
+impl Default for ComputeReadbackSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_frame: usize::MAX,
+        }
+    }
+}
+
+/// Readback bytes left this frame. See [`ComputeReadbackSettings::max_bytes_per_frame`].
+#[derive(Resource, Default)]
+pub(crate) struct ReadbackBudget {
+    frame: Option<u32>,
+    available: usize,
+    /// Shaders denied last frame, whose readbacks are reserved this frame.
+    reserved: HashMap<TypeId, usize>,
+    /// Shaders denied this frame, reserved next frame.
+    denied: HashMap<TypeId, usize>,
+}
+impl ReadbackBudget {
+    /// Take `bytes` from this frame's budget, returning whether the readback may go ahead.
+    fn request(&mut self, frame: u32, max_bytes: usize, shader: TypeId, bytes: usize) -> bool {
+        if self.frame != Some(frame) {
+            self.frame = Some(frame);
+            self.reserved = std::mem::take(&mut self.denied);
+            self.available = max_bytes.saturating_sub(self.reserved.values().sum());
+        }
+        if self.reserved.remove(&shader).is_some() {
+            return true;
+        }
+        if bytes <= self.available {
+            self.available -= bytes;
+            return true;
+        }
+        self.denied.insert(shader, bytes);
+        false
+    }
+    /// Take the bytes of the readbacks of `entities` from this frame's budget, returning
+    /// whether they may go ahead.
+    fn grant<S: ComputeShader>(world: &mut World, entities: &[Entity]) -> bool {
+        let max_bytes = world
+            .get_resource::<ComputeReadbackSettings>()
+            .map_or(usize::MAX, |settings| settings.max_bytes_per_frame);
+        if entities.is_empty() || max_bytes == usize::MAX {
+            return true;
+        }
+        let bytes = entities
+            .iter()
+            .filter_map(|entity| world.get::<Readback>(*entity))
+            .map(|readback| readback_size(world, readback))
+            .sum();
+        let frame = world.resource::<FrameCount>().0;
+        world
+            .resource_mut::<Self>()
+            .request(frame, max_bytes, TypeId::of::<S>(), bytes)
+    }
+}
+
+/// Size in bytes of a readback, or 0 while its asset isn't on the GPU.
+fn readback_size(world: &World, readback: &Readback) -> usize {
+    match readback {
+        Readback::Texture(image) => world
+            .get_resource::<RenderAssets<GpuImage>>()
+            .and_then(|images| images.get(image))
+            .and_then(|image| {
+                TextureRowLayout::new(image.texture.size(), image.texture.format()).ok()
+            })
+            .map_or(0, |layout| layout.padded_len()),
+        Readback::Buffer {
+            start_offset_and_size: Some((_, size)),
+            ..
+        } => usize::try_from(*size).unwrap_or(usize::MAX),
+        Readback::Buffer { buffer, .. } => world
+            .get_resource::<RenderAssets<GpuShaderBuffer>>()
+            .and_then(|buffers| buffers.get(buffer))
+            .map_or(0, |buffer| {
+                usize::try_from(buffer.buffer.size()).unwrap_or(usize::MAX)
+            }),
+    }
+}
+
+/// Clamp the aligned end of a buffer readback to its buffer, as Bevy panics reading past it.
+/// Removes the readback and returns `false` when the requested range doesn't fit.
+fn clamp_buffer_readback<S: ComputeShader>(
+    world: &mut World,
+    entity: Entity,
+    range: Option<ReadbackRange>,
+) -> bool {
+    let Some(Readback::Buffer {
+        buffer,
+        start_offset_and_size: Some((start, size)),
+    }) = world.get::<Readback>(entity)
+    else {
+        return true;
+    };
+    let (buffer, start, size) = (buffer.clone(), *start, *size);
+    let Some(buffer_size) = world
+        .resource::<RenderAssets<GpuShaderBuffer>>()
+        .get(&buffer)
+        .map(|gpu_buffer| gpu_buffer.buffer.size())
+    else {
+        return true;
+    };
+    if start + size <= buffer_size {
+        return true;
+    }
+    let end = range.map_or(start + size, |range| range.offset + range.size);
+    if end > buffer_size || start >= buffer_size {
+        error_once!(
+            "{}",
+            ComputeShaderError::ReadbackRange {
+                shader: S::shader_label(),
+                end,
+                size: buffer_size,
+            }
+        );
+        world.entity_mut(entity).remove::<Readback>();
+        return false;
+    }
+    world.entity_mut(entity).insert(Readback::Buffer {
+        buffer,
+        start_offset_and_size: Some((start, buffer_size - start)),
+    });
+    true
+}
+
+/// Frames to wait for a readback before assuming it was dropped.
+const DROPPED_READBACK_FRAMES: usize = 60;
+
+/// Cap on readbacks in flight, see [`ComputeShaderPlugin::max_in_flight_readbacks`].
+///
+/// [`ComputeShaderPlugin::max_in_flight_readbacks`]: crate::ComputeShaderPlugin::max_in_flight_readbacks
+pub(crate) struct InFlightLimit {
+    pub(crate) max: usize,
+    /// Readbacks assumed dropped, no longer counted as in flight.
+    pub(crate) lost: usize,
+    /// Frames spent at `max`.
+    pub(crate) stalled: usize,
+}
+impl InFlightLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            lost: 0,
+            stalled: 0,
+        }
+    }
+    /// Whether `readbacks` more may be requested, given the readbacks `requested` and
+    /// `delivered` so far. A frame of readbacks always goes through once nothing is in flight.
+    fn allow(&mut self, requested: usize, delivered: usize, readbacks: usize) -> bool {
+        let in_flight = requested.saturating_sub(delivered + self.lost);
+        if in_flight == 0 {
+            self.stalled = 0;
+            return true;
+        }
+        if in_flight + readbacks <= self.max {
+            return true;
+        }
+        self.stalled += 1;
+        if self.stalled > DROPPED_READBACK_FRAMES {
+            self.lost += in_flight;
+            self.stalled = 0;
+        }
+        false
+    }
+}
+
+/// The node that will execute the compute shader.
+/// Updates `ComputeNodeState<S>` in the `RenderWorld`.
+#[derive(Resource)]
+pub(crate) struct ComputeNode<S: ComputeShader> {
+    pub(crate) status: ComputeNodeStatus,
+    pub(crate) limit: ReadbackLimit,
+    /// [`ComputeShaderPlugin::limit`], used when there is no [`ReadbackLimitOverride`].
+    ///
+    /// [`ComputeShaderPlugin::limit`]: crate::ComputeShaderPlugin::limit
+    pub(crate) plugin_limit: ReadbackLimit,
+    /// Number of dispatches in the current run.
+    pub(crate) count: usize,
+    readback_interval: usize,
+    /// Whether changes to the input restart the run.
+    reset_limit_on_change: bool,
+    pub(crate) warmup: usize,
+    dispatch_interval: usize,
+    /// Frames since the current run became ready, for `dispatch_interval`.
+    frames: usize,
+    dispatch_rate: Option<DispatchRate>,
+    /// Time not yet dispatched at the `dispatch_rate`.
+    accumulated: Duration,
+    /// When time was last accumulated, unset while not dispatching.
+    last_tick: Option<Instant>,
+    /// Number of `dispatch_rate` ticks dispatched this frame.
+    ticks: u32,
+    /// Relative speed of `Time<Virtual>`, when following it.
+    time_scale: f64,
+    /// Whether to wait for readbacks to be delivered before dispatching again.
+    synchronized: bool,
+    /// Readbacks in flight.
+    in_flight: InFlightLimit,
+    /// Total readbacks requested, reported to the main world.
+    pub(crate) requested: usize,
+    /// Total readbacks delivered to the main world, as of the last extraction.
+    pub(crate) delivered: usize,
+    /// Total readbacks requested when the current run started.
+    requested_base: usize,
+    /// Readbacks of the current run assumed dropped.
+    dropped: usize,
+    /// Number of readback targets, as of the last readback.
+    pub(crate) readback_targets: usize,
+    /// Frames spent waiting on readbacks in flight.
+    waiting: usize,
+    /// Whether no readbacks were requested this frame.
+    pub(crate) readbacks_idle: bool,
+    /// Whether this frame doesn't dispatch, e.g. for [`ComputeShader::should_dispatch`].
+    skipped: bool,
+    /// Incremented on every reset, so [`ComputePassSpec::once`] passes can tell runs apart.
+    ///
+    /// [`ComputePassSpec::once`]: crate::ComputePassSpec::once
+    pub(crate) generation: usize,
+    dependencies: Vec<ComputeDependency>,
+    /// Generations of the dependencies when last checked, to reset along with them.
+    dependency_generations: Vec<Option<usize>>,
+    /// Whether all dependencies dispatched in their current run.
+    dependencies_ready: bool,
+    /// When the current run first dispatched, for [`ReadbackLimit::Duration`].
+    started: Option<Instant>,
+    /// Whether the current run exceeded its [`ReadbackLimit::Duration`], checked once per frame.
+    expired: bool,
+    /// Number of [`CompleteComputeShader`] requests seen from the main world.
+    ///
+    /// [`CompleteComputeShader`]: crate::CompleteComputeShader
+    pub(crate) completion_requests: usize,
+    /// Whether the main world asked to complete the current run.
+    pub(crate) complete_requested: bool,
+    /// Number of [`DispatchComputeShader`] requests seen from the main world.
+    ///
+    /// [`DispatchComputeShader`]: crate::DispatchComputeShader
+    pub(crate) dispatch_requests: usize,
+    /// Whether a [`ReadbackLimit::Manual`] dispatch was requested and not yet dispatched.
+    pub(crate) dispatch_pending: bool,
+    /// Number of [`RetryComputeShader`] requests seen from the main world.
+    ///
+    /// [`RetryComputeShader`]: crate::RetryComputeShader
+    pub(crate) retry_requests: usize,
+    /// Whether the pipelines should be queued again for a [`RetryComputeShader`].
+    ///
+    /// [`RetryComputeShader`]: crate::RetryComputeShader
+    pub(crate) retry_pending: bool,
+    /// Whether this frame's final readback was deferred by the [`ComputeReadbackSettings`]
+    /// budget or `max_in_flight_readbacks`.
+    readback_deferred: bool,
+    /// Whether [`ComputePaused`] holds the current status this frame.
+    paused: bool,
+    /// Whether the plugin's run conditions suspend dispatching this frame.
+    gated: bool,
+    /// Number of [`ComputePaused::step`] requests taken.
+    steps: usize,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeNode<S> {
+    fn default() -> Self {
+        Self {
+            status: ComputeNodeStatus::default(),
+            limit: ReadbackLimit::Infinite,
+            plugin_limit: ReadbackLimit::Infinite,
+            count: 0,
+            readback_interval: 1,
+            reset_limit_on_change: true,
+            warmup: 0,
+            dispatch_interval: 1,
+            frames: 0,
+            dispatch_rate: None,
+            accumulated: Duration::ZERO,
+            last_tick: None,
+            ticks: 1,
+            time_scale: 1.0,
+            synchronized: false,
+            in_flight: InFlightLimit::new(usize::MAX),
+            requested: 0,
+            delivered: 0,
+            requested_base: 0,
+            dropped: 0,
+            readback_targets: 1,
+            waiting: 0,
+            readbacks_idle: true,
+            skipped: false,
+            generation: 0,
+            dependencies: Vec::new(),
+            dependency_generations: Vec::new(),
+            dependencies_ready: true,
+            started: None,
+            expired: false,
+            completion_requests: 0,
+            complete_requested: false,
+            dispatch_requests: 0,
+            dispatch_pending: false,
+            retry_requests: 0,
+            retry_pending: false,
+            readback_deferred: false,
+            paused: false,
+            gated: false,
+            steps: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ComputeNode<S> {
+    /// Readbacks requested and delivered in the current run, for
+    /// [`ReadbackLimit::FiniteReadbacks`]. Readbacks are delivered in order, so the ones
+    /// from before the last reset are counted first.
+    pub(crate) fn run_readbacks(&self) -> (usize, usize) {
+        let requested = self.requested - self.requested_base - self.dropped;
+        let delivered = self.delivered.saturating_sub(self.requested_base);
+        (requested, delivered)
+    }
+    /// Whether the next dispatch must be read back: the last one of the run, or a manual one.
+    fn is_final_dispatch(&self) -> bool {
+        self.expired
+            || self.complete_requested
+            || match self.limit {
+                ReadbackLimit::Finite(limit) => self.count + 1 >= self.warmup + limit,
+                ReadbackLimit::Manual => true,
+                _ => false,
+            }
+    }
+    /// Skip this frame's dispatch, and hold the status, until its readback can go ahead.
+    fn hold_for_readback(&mut self) {
+        self.skipped = true;
+        self.readback_deferred = true;
+        if matches!(self.limit, ReadbackLimit::Manual) {
+            self.dispatch_pending = true;
+        }
+    }
+    /// Whether the node is ready to dispatch, unless paused.
+    fn is_running(&self) -> bool {
+        matches!(
+            self.status,
+            ComputeNodeStatus::Ready | ComputeNodeStatus::Paused
+        )
+    }
+    /// Whether this frame's readbacks should be kept. Readbacks are skipped for dispatches
+    /// between intervals, except for the final dispatch of a finite run.
+    fn wants_readback(&self) -> bool {
+        if self.expired || self.complete_requested {
+            return true;
+        }
+        let next = self.count + 1;
+        if next <= self.warmup && self.status != ComputeNodeStatus::Completed {
+            return false;
+        }
+        if !self.is_running() || matches!(self.limit, ReadbackLimit::Manual) {
+            return true;
+        }
+        (next - self.warmup).is_multiple_of(self.readback_interval)
+            || matches!(self.limit, ReadbackLimit::Finite(limit) if next >= self.warmup + limit)
+    }
+    /// Decide whether this frame dispatches, and remove this frame's extracted readbacks
+    /// if they shouldn't be read back. Runs before readback buffers are prepared.
+    pub(crate) fn gate_readbacks(world: &mut World) {
+        let (entities, main_entities, ranges) = ReadbackEntities::<S>::take_checked(world);
+        let skipped = Self::gate_dispatch(world, entities.len());
+        let mut wants_readback = !skipped && world.resource::<Self>().wants_readback();
+        let mut node = world.resource_mut::<Self>();
+        let (requested, delivered) = (node.requested, node.delivered);
+        if wants_readback && !node.in_flight.allow(requested, delivered, entities.len()) {
+            wants_readback = false;
+            if node.is_final_dispatch() {
+                node.hold_for_readback();
+            }
+        }
+        if wants_readback && !ReadbackBudget::grant::<S>(world, &entities) {
+            wants_readback = false;
+            // Dispatches whose readback matters wait for their turn instead.
+            let mut node = world.resource_mut::<Self>();
+            if node.is_final_dispatch() {
+                node.hold_for_readback();
+            }
+        }
+        if !wants_readback {
+            for entity in &entities {
+                if let Ok(mut entity) = world.get_entity_mut(*entity) {
+                    entity.remove::<Readback>();
+                }
+            }
+        }
+        let mut node = world.resource_mut::<Self>();
+        node.readbacks_idle = !wants_readback || entities.is_empty();
+        let (idle, run) = (node.readbacks_idle, node.generation);
+        let tile = TileCursor::<S>::advance(world, !idle, run);
+        if !idle {
+            Self::record_readbacks(world, main_entities, ranges, tile);
+        }
+    }
+    /// Decide whether this frame dispatches, given the number of readback `targets`.
+    fn gate_dispatch(world: &mut World, targets: usize) -> bool {
+        let enabled = world
+            .get_resource::<ComputeShaderGate<S>>()
+            .is_none_or(|gate| gate.enabled);
+        let (paused, steps) = world
+            .get_resource::<ComputePaused<S>>()
+            .map_or((false, 0), |paused| (paused.paused, paused.steps));
+        let skipped = !enabled
+            || world
+                .get_resource::<S>()
+                .is_some_and(|input| !input.should_dispatch(world))
+            || !Self::can_dispatch(world);
+        let mut node = world.resource_mut::<Self>();
+        node.gated = !enabled;
+        node.step_pause(paused, steps);
+        let in_flight = node.synchronized && node.delivered < node.requested;
+        let mut off_cadence = false;
+        if node.is_running() {
+            off_cadence = !node.frames.is_multiple_of(node.dispatch_interval);
+            node.frames += 1;
+        }
+        let mut skipped = skipped || node.paused || in_flight || off_cadence;
+        skipped |= node.wait_for_readbacks(targets);
+        skipped = node.tick(skipped);
+        if matches!(node.limit, ReadbackLimit::Manual) {
+            skipped = skipped || !node.dispatch_pending || !node.is_running();
+            if !skipped {
+                node.dispatch_pending = false;
+            }
+        }
+        node.skipped = skipped;
+        node.expired = match (node.limit, node.started) {
+            (ReadbackLimit::Duration(duration), Some(started)) => started.elapsed() >= duration,
+            _ => false,
+        };
+        node.readback_deferred = false;
+        skipped
+    }
+    /// Whether `run` has what it needs to dispatch this frame. Checked before readbacks are
+    /// prepared, so frames that can't dispatch don't count or read back stale results.
+    fn can_dispatch(world: &World) -> bool {
+        let (Some(pipeline), Some(input)) = (
+            world.get_resource::<ComputePipeline<S>>(),
+            world.get_resource::<S>(),
+        ) else {
+            return true;
+        };
+        if pipeline.view_layout.is_some() && !ComputeViewBindGroup::<S>::has_textures(world) {
+            return false;
+        }
+        if let Err(error) = pipeline.validate_dynamic_offsets(&input.dynamic_offsets()) {
+            error_once!("{error}");
+            return false;
+        }
+        let Some(handle) = input.indirect_dispatch() else {
+            return true;
+        };
+        // Wait for the buffer to be uploaded.
+        let Some(buffer) = world
+            .get_resource::<RenderAssets<GpuShaderBuffer>>()
+            .and_then(|buffers| buffers.get(&handle))
+        else {
+            return false;
+        };
+        if !buffer.buffer.usage().contains(BufferUsages::INDIRECT) {
+            error_once!("{}", ComputeShaderError::IndirectUsage(S::shader_label()));
+            return false;
+        }
+        true
+    }
+    /// Hold the status for [`ComputePaused`], taking one step per frame while paused.
+    fn step_pause(&mut self, paused: bool, steps: usize) {
+        self.paused = paused && self.steps >= steps;
+        if !paused {
+            self.steps = steps;
+        } else if !self.paused {
+            self.steps += 1;
+        }
+    }
+    /// Whether to wait for the readbacks in flight instead of requesting more than the
+    /// [`ReadbackLimit::FiniteReadbacks`] limit.
+    fn wait_for_readbacks(&mut self, targets: usize) -> bool {
+        let ReadbackLimit::FiniteReadbacks(limit) = self.limit else {
+            return false;
+        };
+        if !self.is_running() {
+            return false;
+        }
+        if targets > 0 {
+            self.readback_targets = targets;
+        }
+        let wanted = limit * self.readback_targets;
+        let (requested, delivered) = self.run_readbacks();
+        if requested < wanted || delivered >= wanted {
+            self.waiting = 0;
+            return false;
+        }
+        self.waiting += 1;
+        if self.waiting > DROPPED_READBACK_FRAMES {
+            self.dropped += requested - delivered;
+            self.waiting = 0;
+        }
+        true
+    }
+    /// Count the `dispatch_rate` ticks elapsed, returning whether to skip this frame.
+    fn tick(&mut self, skipped: bool) -> bool {
+        let Some(rate) = self.dispatch_rate else {
+            return skipped;
+        };
+        if skipped || !self.is_running() {
+            // Don't catch up on time spent not dispatching.
+            self.last_tick = None;
+            return skipped;
+        }
+        let now = Instant::now();
+        let period = rate.period();
+        // The first frame dispatches right away.
+        let elapsed = self.last_tick.map_or(period, |last| now - last);
+        self.accumulated += elapsed.mul_f64(self.time_scale);
+        self.last_tick = Some(now);
+        let ticks = (self.accumulated.as_secs_f64() / period.as_secs_f64()) as u32;
+        self.accumulated = self.accumulated.saturating_sub(period * ticks);
+        self.ticks = ticks.min(rate.max_ticks_per_frame);
+        self.ticks == 0
+    }
+    /// Count this frame's readbacks and record their [`ReadbackInfo`]s.
+    fn record_readbacks(
+        world: &mut World,
+        main_entities: Vec<Entity>,
+        ranges: Vec<Option<ReadbackRange>>,
+        tile: Option<ReadbackTileInfo>,
+    ) {
+        let mut node = world.resource_mut::<Self>();
+        node.requested += main_entities.len();
+        let (dispatch, run) = (node.count, node.generation);
+        let input = world
+            .get_resource::<InputSnapshot<S>>()
+            .map(|snapshot| snapshot.input.clone());
+        let info = ReadbackInfo {
+            dispatch,
+            frame: world.resource::<FrameCount>().0.into(),
+            run,
+            range: None,
+            tile,
+            latency: None,
+        };
+        if let Some(infos) = world.get_resource::<ReadbackInfos<S>>() {
+            for (entity, range) in main_entities.into_iter().zip(ranges) {
+                let range = range.map(|range| (range.offset, range.size));
+                infos.push(entity, ReadbackInfo { range, ..info }, input.clone());
+            }
+        }
+    }
+    /// When the input shader is changed, reset.
+    /// Changes that [`ComputeShader::should_reset`] ignores keep the current run going.
+    pub(crate) fn reset_on_change(
+        input: Res<S>,
+        mut previous: Local<Option<S>>,
+        mut state: ResMut<ComputeNodeState<S>>,
+        mut node: ResMut<Self>,
+    ) {
+        let reset = previous
+            .as_ref()
+            .is_none_or(|previous| node.reset_limit_on_change && input.should_reset(previous));
+        *previous = Some(input.clone());
+        if reset {
+            node.reset(&mut state);
+        }
+    }
+    /// Restart the run from the beginning.
+    fn reset(&mut self, state: &mut ComputeNodeState<S>) {
+        self.count = 0;
+        self.frames = 0;
+        self.requested_base = self.requested;
+        self.dropped = 0;
+        self.waiting = 0;
+        self.accumulated = Duration::ZERO;
+        self.last_tick = None;
+        self.started = None;
+        self.expired = false;
+        self.complete_requested = false;
+        self.generation += 1;
+        self.status = ComputeNodeStatus::Loading;
+        state.set(ComputeNodeStatus::Loading, None);
+    }
+    /// Wait for dependencies to dispatch in their current run, and reset when they reset.
+    pub(crate) fn check_dependencies(world: &mut World) {
+        let node = world.resource::<Self>();
+        if node.dependencies.is_empty() {
+            return;
+        }
+        let progress = node
+            .dependencies
+            .iter()
+            .map(|dependency| (dependency.progress)(world))
+            .collect::<Vec<_>>();
+        let generations = progress
+            .iter()
+            .map(|progress| progress.map(|(generation, _)| generation))
+            .collect::<Vec<_>>();
+        world.resource_scope(|world, mut state: Mut<ComputeNodeState<S>>| {
+            let mut node = world.resource_mut::<Self>();
+            node.dependencies_ready = progress
+                .iter()
+                .all(|progress| progress.is_some_and(|(_, count)| count > 0));
+            if node.dependency_generations != generations {
+                node.dependency_generations = generations;
+                node.reset(&mut state);
+            }
+        });
+    }
+    /// Copy the relative speed of `Time<Virtual>`, to scale the `dispatch_rate`.
+    pub(crate) fn extract_time_scale(
+        time: Extract<Option<Res<Time<Virtual>>>>,
+        mut node: ResMut<Self>,
+    ) {
+        node.time_scale = time.as_ref().map_or(1.0, |time| time.relative_speed_f64());
+    }
+    /// Apply the [`ReadbackLimitOverride`], or restore the plugin's limit.
+    pub(crate) fn apply_limit_override(
+        limit: Res<ReadbackLimitOverride<S>>,
+        mut node: ResMut<Self>,
+    ) {
+        node.limit = limit.limit.unwrap_or(node.plugin_limit);
+    }
+    /// Warn when the dispatch does not cover the declared output extent.
+    pub(crate) fn validate_dispatch(input: Res<S>) {
+        if input.indirect_dispatch().is_some() {
+            return;
+        }
+        if let (Some(local_size), Some(extent)) = (S::local_workgroup_size(), input.output_extent())
+        {
+            let covered = input.dispatch_workgroups() * local_size;
+            if covered.cmplt(extent).any() {
+                warn!(
+                    "{}: dispatch of {} workgroups of size {local_size} covers {covered}, \
+                    which does not cover the output extent {extent}",
+                    S::shader_label(),
+                    input.dispatch_workgroups(),
+                );
+            }
+        }
+    }
+    /// Run condition so the node updates and dispatches once per frame,
+    /// even in schedules that run once per view.
+    pub(crate) fn once_per_frame(
+        frame: Res<FrameCount>,
+        mut last_frame: Local<Option<u32>>,
+    ) -> bool {
+        let first = *last_frame != Some(frame.0);
+        *last_frame = Some(frame.0);
+        first
+    }
+    /// Update node status.
+    pub(crate) fn update(
+        pipeline: Res<ComputePipeline<S>>,
+        pipeline_cache: Res<PipelineCache>,
+        bind_group: Option<Res<ComputeShaderBindGroup<S>>>,
+        view_bind_group: Option<Res<ComputeViewBindGroup<S>>>,
+        mut node: ResMut<Self>,
+        mut state: ResMut<ComputeNodeState<S>>,
+        progress: Option<ResMut<ComputeProgress<S>>>,
+    ) {
+        // The view textures were checked against the previous frame: don't count a dispatch
+        // if this frame's couldn't be bound after all.
+        if pipeline.view_layout.is_some()
+            && view_bind_group.is_none_or(|view| view.bind_group.is_none())
+        {
+            node.skipped = true;
+        }
+        let mut error = pipeline.error.as_ref().map(ToString::to_string);
+        let pipeline_state = pipeline.state(&pipeline_cache);
+        // The cache queues the pipelines again once the shader is fixed and reloaded, e.g. by
+        // hot reloading: start the run over.
+        if node.status == ComputeNodeStatus::Error
+            && error.is_none()
+            && !matches!(pipeline_state, CachedPipelineState::Err(_))
+        {
+            node.reset(&mut state);
+        }
+        let next_status = match pipeline_state {
+            _ if error.is_some() => ComputeNodeStatus::Error,
+            // Don't count towards the limit until there is a bind group to dispatch with.
+            CachedPipelineState::Ok(_) if bind_group.is_none() && pipeline.has_bind_group() => {
+                ComputeNodeStatus::Loading
+            }
+            CachedPipelineState::Ok(_) if !node.dependencies_ready => ComputeNodeStatus::Loading,
+            CachedPipelineState::Ok(_) => match (node.status, node.limit) {
+                (ComputeNodeStatus::Ready | ComputeNodeStatus::Paused, _)
+                    if node.paused || node.gated =>
+                {
+                    ComputeNodeStatus::Paused
+                }
+                // Pausing while loading or completed holds the status instead.
+                _ if node.paused || node.gated => node.status,
+                _ if node.readback_deferred && node.status != ComputeNodeStatus::Paused => {
+                    node.status
+                }
+                (ComputeNodeStatus::Completed, _) => ComputeNodeStatus::Completed,
+                (_, ReadbackLimit::Finite(limit)) if node.count >= node.warmup + limit => {
+                    ComputeNodeStatus::Completed
+                }
+                (_, ReadbackLimit::Duration(_)) if node.expired => ComputeNodeStatus::Completed,
+                (_, ReadbackLimit::FiniteReadbacks(limit))
+                    if node.run_readbacks().1 >= limit * node.readback_targets =>
+                {
+                    ComputeNodeStatus::Completed
+                }
+                _ if node.complete_requested => ComputeNodeStatus::Completed,
+                _ => {
+                    // Skipped frames don't count towards the limit.
+                    if !node.skipped {
+                        node.count += 1;
+                        node.started.get_or_insert_with(Instant::now);
+                    }
+                    ComputeNodeStatus::Ready
+                }
+            },
+            CachedPipelineState::Creating(_) => ComputeNodeStatus::Loading,
+            CachedPipelineState::Queued => ComputeNodeStatus::Loading,
+            CachedPipelineState::Err(err) => {
+                error = Some(error_report(err));
+                ComputeNodeStatus::Error
+            }
+        };
+
+        if node.status != next_status {
+            node.status = next_status;
+            state.set(next_status, error);
+        }
+        let next_progress = ComputeProgress::of(&node);
+        if let Some(mut progress) = progress
+            && *progress != next_progress
+        {
+            *progress = next_progress;
+        }
+    }
+
+    /// Encode the passes. Takes the whole world read-only for the encode hooks.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run(
+        pipeline_cache: Res<PipelineCache>,
+        pipeline: Res<ComputePipeline<S>>,
+        bind_group: Option<Res<ComputeShaderBindGroup<S>>>,
+        mut ctx: RenderContext,
+        buffers: Res<RenderAssets<GpuShaderBuffer>>,
+        node: Res<Self>,
+        input: Res<S>,
+        mut initialized: Local<Option<usize>>,
+        world: &World,
+    ) {
+        if bind_group.is_none() && pipeline.has_bind_group() {
+            return;
+        }
+        let view_bind_group = world
+            .get_resource::<ComputeViewBindGroup<S>>()
+            .and_then(|view| view.bind_group.as_ref());
+        if pipeline.view_layout.is_some() && view_bind_group.is_none() {
+            return;
+        }
+        // The buffer and its usage were checked by `can_dispatch`.
+        let indirect = match input.indirect_dispatch() {
+            Some(handle) => {
+                let Some(buffer) = buffers.get(&handle) else {
+                    return;
+                };
+                Some(&buffer.buffer)
+            }
+            None => None,
+        };
+        if node.status == ComputeNodeStatus::Ready && !node.skipped {
+            let passes = pipeline
+                .passes
+                .iter()
+                .map(|(spec, id)| Some((spec, pipeline_cache.get_compute_pipeline(*id)?)))
+                .collect::<Option<Vec<_>>>();
+            if let Some(passes) = passes {
+                let dynamic_offsets = input.dynamic_offsets();
+                if let Some(events) = world.get_resource::<GpuEventOutputs<S>>() {
+                    events.clear(&mut ctx, &buffers);
+                }
+                if let Some(events) = world.get_resource::<GpuEventInputs<S>>() {
+                    let queue = world.resource::<RenderQueue>();
+                    for input in &events.inputs {
+                        input.upload(S::shader_label(), queue, &buffers);
+                    }
+                }
+                input.encode_pre_dispatch(&mut ctx, world);
+                let mut pass = ctx
+                    .command_encoder()
+                    .begin_compute_pass(&ComputePassDescriptor {
+                        label: Some(S::shader_label()),
+                        ..Default::default()
+                    });
+                if let Some(bind_group) = &bind_group {
+                    let group = S::bind_group_index();
+                    pass.set_bind_group(group, &bind_group.bind_group, &dynamic_offsets);
+                    for (index, extra_bind_group) in bind_group.extra_bind_groups.iter().enumerate()
+                    {
+                        // Extra groups fill the indices around the shader's own group.
+                        let index = index as u32;
+                        let index = if index < group { index } else { index + 1 };
+                        pass.set_bind_group(index, extra_bind_group, &[]);
+                    }
+                }
+                if let Some(view_bind_group) = view_bind_group {
+                    pass.set_bind_group(pipeline.view_group_index(), view_bind_group, &[]);
+                }
+                let immediates = input.immediates();
+                // Each dispatch is its own usage scope, so wgpu orders storage writes from
+                // earlier dispatches (passes and iterations) before later ones.
+                let iterations = input.iterations_per_frame().max(1) * node.ticks;
+                for iteration in 0..iterations {
+                    for (spec, compute_pipeline) in &passes {
+                        if spec.once && (*initialized == Some(node.generation) || iteration > 0) {
+                            continue;
+                        }
+                        pass.set_pipeline(compute_pipeline);
+                        if let Some(immediates) = &immediates {
+                            pass.set_immediates(0, immediates);
+                        }
+                        match (spec.workgroups, indirect) {
+                            (None, Some(buffer)) => pass.dispatch_workgroups_indirect(
+                                buffer,
+                                input.indirect_dispatch_offset(),
+                            ),
+                            (workgroups, _) => {
+                                let workgroups =
+                                    workgroups.unwrap_or_else(|| input.dispatch_workgroups());
+                                pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+                            }
+                        }
+                    }
+                }
+                drop(pass);
+                *initialized = Some(node.generation);
+                input.encode_post_dispatch(&mut ctx, world);
+                if let Some(region) = world.get_resource::<ReadbackRegion<S>>() {
+                    region.encode(&mut ctx, world);
+                }
+                if let Some(downsample) = world.get_resource::<ReadbackDownsample<S>>() {
+                    downsample.encode(&mut ctx, world);
+                }
+                if let (Some(tiles), Some(tile)) = (
+                    world.get_resource::<ReadbackTiles<S>>(),
+                    world
+                        .get_resource::<TileCursor<S>>()
+                        .and_then(|cursor| cursor.tile),
+                ) {
+                    tiles.encode(tile, &mut ctx, world);
+                }
+                for (buffer, image) in input.buffer_images() {
+                    copy_buffer_to_image::<S>(&mut ctx, world, &buffer, &image);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use bevy::{
+        asset::Handle,
+        ecs::system::{Command, RunSystemOnce},
+        image::Image,
+        math::UVec2,
+        render::{
+            MainWorld,
+            render_resource::{
+                BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, BufferBindingType,
+                Extent3d, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+                TextureUsages,
+            },
+            renderer::RenderDevice,
+        },
+    };
+
+    use super::*;
+    use crate::{
+        DispatchComputeShader,
+        pipeline::tests::{empty_pipeline, noop_render_world},
+        shader::tests::TestComputeShader,
+    };
+
+    type Node = ComputeNode<TestComputeShader>;
+
+    /// Render world with a ready node and one readback entity.
+    fn render_world(limit: ReadbackLimit) -> (World, Entity) {
+        let mut world = World::new();
+        world.insert_resource(Node {
+            status: ComputeNodeStatus::Ready,
+            limit,
+            plugin_limit: limit,
+            ..default()
+        });
+        world.init_resource::<ReadbackEntities<TestComputeShader>>();
+        world.init_resource::<FrameCount>();
+        world.init_resource::<MainWorld>();
+        world
+            .resource_mut::<MainWorld>()
+            .insert_resource(ReadbackTracker::<TestComputeShader>::default());
+        let entity = world.spawn_empty().id();
+        (world, entity)
+    }
+
+    /// Extract the main world requests and the readback entity, then gate the readbacks.
+    pub(crate) fn frame(world: &mut World, entity: Entity) {
+        frame_with(world, entity, Readback::buffer(Handle::default()));
+    }
+
+    /// [`frame`] reading back `readback`.
+    fn frame_with(world: &mut World, entity: Entity, readback: Readback) {
+        world
+            .run_system_once(ReadbackTracker::<TestComputeShader>::extract_requested)
+            .unwrap();
+        world.entity_mut(entity).insert(readback);
+        let mut readback_entities = world.resource_mut::<ReadbackEntities<TestComputeShader>>();
+        readback_entities.entities = vec![entity];
+        readback_entities.main_entities = vec![entity];
+        readback_entities.ranges = vec![None];
+        Node::gate_readbacks(world);
+    }
+
+    /// Count, generation and status after changing the input mid-run.
+    fn after_change(reset_limit_on_change: bool) -> (usize, usize, ComputeNodeStatus) {
+        let (mut world, _) = render_world(ReadbackLimit::Finite(1));
+        world.resource_mut::<Node>().reset_limit_on_change = reset_limit_on_change;
+        world.insert_resource(ComputeNodeState::<TestComputeShader>::from(
+            ComputeNodeStatus::Ready,
+        ));
+        world.init_resource::<TestComputeShader>();
+        let reset = world.register_system(Node::reset_on_change);
+        // The first input always starts a run.
+        world.run_system(reset).unwrap();
+        let node = world.resource::<Node>();
+        assert_eq!((node.count, node.generation), (0, 1));
+
+        world.resource_mut::<Node>().count = 1;
+        *world.resource_mut::<ComputeNodeState<TestComputeShader>>() =
+            ComputeNodeStatus::Completed.into();
+        world.resource_mut::<TestComputeShader>().value = 1;
+        world.run_system(reset).unwrap();
+        let node = world.resource::<Node>();
+        let state = world.resource::<ComputeNodeState<TestComputeShader>>();
+        (node.count, node.generation, state.status)
+    }
+
+    /// `width` by `height` texture on the noop device.
+    fn gpu_image(render_device: &RenderDevice, width: u32, height: u32) -> GpuImage {
+        let texture_descriptor = TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::COPY_SRC,
+            view_formats: &[],
+        };
+        let texture = render_device.create_texture(&texture_descriptor);
+        GpuImage {
+            texture_view: texture.create_view(&default()),
+            texture,
+            sampler: render_device.create_sampler(&default()),
+            texture_descriptor,
+            texture_view_descriptor: None,
+            had_data: false,
+        }
+    }
+
+    #[test]
+    fn reset_on_change_restarts_the_limit() {
+        assert_eq!(after_change(true), (0, 2, ComputeNodeStatus::Loading));
+    }
+
+    #[test]
+    fn reset_on_change_keeps_the_limit() {
+        assert_eq!(after_change(false), (1, 1, ComputeNodeStatus::Completed));
+    }
+
+    #[test]
+    fn in_flight_readbacks_stall_until_lost() {
+        let (mut world, entity) = render_world(ReadbackLimit::Infinite);
+        world.resource_mut::<Node>().in_flight.max = 1;
+        frame(&mut world, entity);
+        assert_eq!(world.resource::<Node>().requested, 1);
+        // Dispatches go on without readbacks while one is in flight.
+        for stalled in 1..=DROPPED_READBACK_FRAMES {
+            frame(&mut world, entity);
+            let node = world.resource::<Node>();
+            assert!(!node.skipped);
+            assert_eq!(
+                (node.requested, node.in_flight.stalled, node.in_flight.lost),
+                (1, stalled, 0)
+            );
+            assert!(world.get::<Readback>(entity).is_none());
+        }
+        // Then the readback in flight is assumed lost.
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert_eq!(
+            (node.requested, node.in_flight.stalled, node.in_flight.lost),
+            (1, 0, 1)
+        );
+        frame(&mut world, entity);
+        assert_eq!(world.resource::<Node>().requested, 2);
+        assert!(world.get::<Readback>(entity).is_some());
+    }
+
+    #[test]
+    fn in_flight_readbacks_hold_the_final_dispatch() {
+        let (mut world, entity) = render_world(ReadbackLimit::Finite(2));
+        world.resource_mut::<Node>().in_flight.max = 1;
+        frame(&mut world, entity);
+        world.resource_mut::<Node>().count = 1;
+        // The final dispatch waits for its readback instead of skipping it.
+        for _ in 0..=DROPPED_READBACK_FRAMES {
+            frame(&mut world, entity);
+            let node = world.resource::<Node>();
+            assert!(node.skipped && node.readback_deferred);
+            assert_eq!(node.requested, 1);
+        }
+        assert_eq!(world.resource::<Node>().in_flight.lost, 1);
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert!(!node.skipped && !node.readback_deferred);
+        assert_eq!(node.requested, 2);
+    }
+
+    #[test]
+    fn manual_dispatch_requests_coalesce() {
+        let (mut world, entity) = render_world(ReadbackLimit::Manual);
+        frame(&mut world, entity);
+        assert!(world.resource::<Node>().skipped);
+        assert!(world.get::<Readback>(entity).is_none());
+
+        for _ in 0..3 {
+            DispatchComputeShader::<TestComputeShader>::default()
+                .apply(&mut world.resource_mut::<MainWorld>());
+        }
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert!(!node.skipped);
+        assert_eq!(node.requested, 1);
+        assert!(world.get::<Readback>(entity).is_some());
+
+        // The other requests of the same frame don't dispatch again.
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert!(node.skipped);
+        assert_eq!(node.requested, 1);
+        assert!(world.get::<Readback>(entity).is_none());
+    }
+
+    #[test]
+    fn missing_view_textures_skip_the_frame() {
+        let (mut world, entity) = render_world(ReadbackLimit::Finite(2));
+        world.init_resource::<TestComputeShader>();
+        let mut pipeline = empty_pipeline::<TestComputeShader>();
+        pipeline.view_layout = Some(BindGroupLayoutDescriptor::new("view", &[]));
+        world.insert_resource(pipeline);
+        world.init_resource::<ComputeViewBindGroup<TestComputeShader>>();
+        // Without a view, neither the dispatch nor its readback goes ahead.
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert!(node.skipped);
+        assert_eq!(node.requested, 0);
+        assert!(world.get::<Readback>(entity).is_none());
+
+        let view = world.spawn_empty().id();
+        world
+            .resource_mut::<ComputeViewBindGroup<TestComputeShader>>()
+            .view = Some(view);
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert!(!node.skipped);
+        assert_eq!(node.requested, 1);
+        assert!(world.get::<Readback>(entity).is_some());
+    }
+
+    #[test]
+    fn invalid_dynamic_offsets_skip_the_frame() {
+        let (mut world, entity) = render_world(ReadbackLimit::Finite(2));
+        world.init_resource::<TestComputeShader>();
+        let mut pipeline = empty_pipeline::<TestComputeShader>();
+        // One dynamic offset expected, none provided.
+        pipeline.layout = BindGroupLayoutDescriptor::new(
+            "dynamic",
+            &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        );
+        world.insert_resource(pipeline);
+        frame(&mut world, entity);
+        let node = world.resource::<Node>();
+        assert!(node.skipped);
+        assert_eq!(node.requested, 0);
+        assert!(world.get::<Readback>(entity).is_none());
+    }
+
+    #[test]
+    fn readback_budget_reserves_denied_shaders() {
+        let (a, b) = (TypeId::of::<u8>(), TypeId::of::<u16>());
+        let mut budget = ReadbackBudget::default();
+        assert!(budget.request(0, 100, a, 60));
+        // Over what's left this frame, so it goes first the next.
+        assert!(!budget.request(0, 100, b, 60));
+        assert!(!budget.request(1, 100, a, 60));
+        assert!(budget.request(1, 100, b, 60));
+        assert!(budget.request(2, 100, a, 60));
+        assert!(budget.request(2, 100, b, 40));
+        assert!(!budget.request(2, 100, b, 1));
+    }
+
+    #[test]
+    fn readback_budget_defers_readbacks_across_frames() {
+        let (mut world, entity) = render_world(ReadbackLimit::Infinite);
+        world.init_resource::<ReadbackBudget>();
+        world.init_resource::<RenderAssets<GpuShaderBuffer>>();
+        world.insert_resource(ComputeReadbackSettings {
+            max_bytes_per_frame: 32,
+        });
+        let readback = Readback::buffer_range(Handle::default(), 0, 64);
+        // Larger than the whole budget: denied, then reserved the next frame.
+        for frame in 0..4 {
+            world.resource_mut::<FrameCount>().0 = frame;
+            frame_with(&mut world, entity, readback.clone());
+            let node = world.resource::<Node>();
+            assert!(!node.skipped);
+            assert_eq!(node.requested, frame as usize / 2 + frame as usize % 2);
+            assert_eq!(world.get::<Readback>(entity).is_some(), frame % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn tile_cursor_wraps_around_the_grid() {
+        let (mut world, entity) = render_world(ReadbackLimit::Infinite);
+        let render_device = noop_render_world()
+            .remove_resource::<RenderDevice>()
+            .unwrap();
+        let texture = Handle::<Image>::default();
+        let mut images = RenderAssets::<GpuImage>::default();
+        images.insert(&texture, gpu_image(&render_device, 5, 4));
+        world.insert_resource(images);
+        world.insert_resource(ReadbackTiles::<TestComputeShader>::new(
+            texture,
+            UVec2::new(2, 2),
+            Handle::default(),
+        ));
+        world.init_resource::<TileCursor<TestComputeShader>>();
+        let tile = |world: &mut World| {
+            frame(world, entity);
+            let tile = world
+                .resource::<TileCursor<TestComputeShader>>()
+                .tile
+                .unwrap();
+            (tile.tile, tile.origin, tile.size)
+        };
+        let tiles = [
+            (UVec2::new(0, 0), UVec2::new(0, 0), UVec2::new(2, 2)),
+            (UVec2::new(1, 0), UVec2::new(2, 0), UVec2::new(3, 2)),
+            (UVec2::new(0, 1), UVec2::new(0, 2), UVec2::new(2, 2)),
+            (UVec2::new(1, 1), UVec2::new(2, 2), UVec2::new(3, 2)),
+        ];
+        for expected in tiles.into_iter().chain(tiles.into_iter().take(2)) {
+            assert_eq!(tile(&mut world), expected);
+        }
+        // A new run starts over from the first tile.
+        world.resource_mut::<Node>().generation += 1;
+        assert_eq!(tile(&mut world), tiles[0]);
+    }
+}
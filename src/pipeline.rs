@@ -0,0 +1,665 @@
+//! Compute pipelines and bind groups.
+
+use std::{fmt::Debug, hash::Hash, marker::PhantomData};
+
+use bevy::{
+    asset::DirectAssetAccessExt,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        resource::Resource,
+        system::{Query, Res, ResMut, StaticSystemParam},
+        world::{FromWorld, World},
+    },
+    log::{error, warn_once},
+    render::{
+        Extract,
+        render_resource::{
+            AsBindGroup, BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry, BindingResource, BindingResources, BindingType,
+            BufferInitDescriptor, BufferUsages, CachedComputePipelineId, CachedPipelineState,
+            OwnedBindingResource, PipelineCache, ShaderStages, TextureSampleType,
+            binding_types::{texture_2d, texture_depth_2d},
+        },
+        renderer::{RenderDevice, RenderQueue},
+        settings::WgpuFeatures,
+        sync_world::RenderEntity,
+        view::{ViewDepthTexture, ViewTarget},
+    },
+    shader::ShaderRef,
+};
+use thiserror::Error;
+
+use crate::{ComputeNode, ComputePassSpec, ComputeShader};
+
+/// Errors that prevent a compute shader from running.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ComputeShaderError {
+    #[error("{0} uses immediates, but the render device does not support WgpuFeatures::IMMEDIATES")]
+    ImmediatesUnsupported(&'static str),
+    #[error("{0} failed to create its bind group: {1}")]
+    BindGroup(&'static str, String),
+    #[error(
+        "{shader} provided {actual} dynamic offsets, but its bind group layout expects {expected}"
+    )]
+    DynamicOffsetCount {
+        shader: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("{0} dispatches indirectly from a buffer without BufferUsages::INDIRECT")]
+    IndirectUsage(&'static str),
+    #[error("{shader} reads back up to byte {end}, past the end of its {size} byte buffer")]
+    ReadbackRange {
+        shader: &'static str,
+        end: u64,
+        size: u64,
+    },
+}
+
+/// Stores prepared bind group data for the compute shader.
+#[derive(Resource)]
+pub struct ComputeShaderBindGroup<S: ComputeShader> {
+    pub bind_group: BindGroup,
+    /// Resources owned by the bind group, kept to write `#[data]` changes in place.
+    pub bindings: BindingResources,
+    /// Bind groups for [`ComputeShader::extra_bind_group_layouts`], in order.
+    pub extra_bind_groups: Vec<BindGroup>,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ComputeShaderBindGroup<S> {
+    /// Create the bind group with [`ComputeShader::create_bind_group`].
+    pub(crate) fn prepare_manual(world: &mut World) {
+        let pipeline = world.resource::<ComputePipeline<S>>();
+        let layout = world
+            .resource::<PipelineCache>()
+            .get_bind_group_layout(&pipeline.layout);
+        let bind_group = world.resource::<S>().create_bind_group(
+            world.resource::<RenderDevice>(),
+            &layout,
+            world,
+        );
+        if let Some(bind_group) = bind_group {
+            world.insert_resource(Self {
+                bind_group,
+                bindings: BindingResources(Vec::new()),
+                extra_bind_groups: Vec::new(),
+                _marker: PhantomData,
+            });
+        }
+    }
+    /// Create the bind group from the bindings of [`AsBindGroup::unprepared_bind_group`].
+    /// `#[data]` bindings are put in uniform buffers owned by the bind group, so later changes
+    /// to them can be written in place by [`ComputeShaderBindGroup::write_uniforms`].
+    pub fn from_bindings(
+        bindings: BindingResources,
+        layout: &BindGroupLayout,
+        render_device: &RenderDevice,
+    ) -> Self {
+        let bindings = BindingResources(
+            bindings
+                .0
+                .into_iter()
+                .map(|(index, binding)| match binding {
+                    OwnedBindingResource::Data(data) => (
+                        index,
+                        OwnedBindingResource::Buffer(render_device.create_buffer_with_data(
+                            &BufferInitDescriptor {
+                                label: Some(S::shader_label()),
+                                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                                contents: &data,
+                            },
+                        )),
+                    ),
+                    binding => (index, binding),
+                })
+                .collect(),
+        );
+        let entries = bindings
+            .iter()
+            .map(|(index, binding)| BindGroupEntry {
+                binding: *index,
+                resource: binding.get_binding(),
+            })
+            .collect::<Vec<_>>();
+        Self {
+            bind_group: render_device.create_bind_group(S::label(), layout, &entries),
+            bindings,
+            extra_bind_groups: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+    /// Write a change of the input into the existing bind group when only its `#[data]`
+    /// bindings changed. [`ComputeShader::prepare_bind_group`] is skipped if this succeeds.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare_uniforms(
+        bind_group: Option<Res<Self>>,
+        pipeline: Res<ComputePipeline<S>>,
+        pipeline_cache: Res<PipelineCache>,
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        input: Res<S>,
+        param: StaticSystemParam<<S as AsBindGroup>::Param>,
+        mut written: ResMut<UniformsWritten<S>>,
+    ) {
+        written.written = false;
+        let Some(bind_group) = bind_group else {
+            return;
+        };
+        if matches!(pipeline.error, Some(ComputeShaderError::BindGroup(..))) {
+            return;
+        }
+        let layout = pipeline_cache.get_bind_group_layout(&pipeline.layout);
+        if let Ok(unprepared) =
+            input.unprepared_bind_group(&layout, &render_device, &mut param.into_inner(), false)
+        {
+            written.written = bind_group.write_uniforms(&unprepared.bindings, &render_queue);
+        }
+    }
+    /// Write the `#[data]` bytes of freshly prepared `bindings` into the bound buffers.
+    /// Returns false without writing anything if any other binding changed, including
+    /// `#[uniform]` fields, which [`AsBindGroup`] puts in a new buffer each time.
+    pub fn write_uniforms(&self, bindings: &BindingResources, render_queue: &RenderQueue) -> bool {
+        if bindings.len() != self.bindings.len() {
+            return false;
+        }
+        let mut writes = Vec::new();
+        for (index, binding) in bindings.iter() {
+            let Some((_, bound)) = self.bindings.iter().find(|(bound, _)| bound == index) else {
+                return false;
+            };
+            match (bound, binding) {
+                (OwnedBindingResource::Buffer(bound), OwnedBindingResource::Buffer(buffer))
+                    if bound.id() == buffer.id() => {}
+                (OwnedBindingResource::Buffer(bound), OwnedBindingResource::Data(data))
+                    if bound.usage().contains(BufferUsages::COPY_DST)
+                        && bound.size() == data.len() as u64 =>
+                {
+                    writes.push((bound, data));
+                }
+                (
+                    OwnedBindingResource::TextureView(_, bound),
+                    OwnedBindingResource::TextureView(_, view),
+                ) if bound.id() == view.id() => {}
+                (
+                    OwnedBindingResource::Sampler(_, bound),
+                    OwnedBindingResource::Sampler(_, sampler),
+                ) if bound.id() == sampler.id() => {}
+                _ => return false,
+            }
+        }
+        for (buffer, data) in writes {
+            render_queue.write_buffer(buffer, 0, data);
+        }
+        true
+    }
+    /// Add the shader's extra bind groups to the prepared bind group.
+    pub(crate) fn prepare_extra_bind_groups(world: &mut World) {
+        let pipeline = world.resource::<ComputePipeline<S>>();
+        if pipeline.extra_layouts.is_empty() || !world.contains_resource::<Self>() {
+            return;
+        }
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let layouts = pipeline
+            .extra_layouts
+            .iter()
+            .map(|layout| pipeline_cache.get_bind_group_layout(layout))
+            .collect::<Vec<_>>();
+        match S::extra_bind_groups(world, &layouts) {
+            Some(extra_bind_groups) if extra_bind_groups.len() == layouts.len() => {
+                world.resource_mut::<Self>().extra_bind_groups = extra_bind_groups;
+            }
+            Some(extra_bind_groups) => {
+                error!(
+                    "{}: expected {} extra bind groups, got {}",
+                    S::shader_label(),
+                    layouts.len(),
+                    extra_bind_groups.len()
+                );
+                world.remove_resource::<Self>();
+            }
+            None => {
+                world.remove_resource::<Self>();
+            }
+        }
+    }
+}
+
+/// Whether this frame's change of the input was written into the existing
+/// [`ComputeShaderBindGroup`], so [`ComputeShader::prepare_bind_group`] is skipped.
+#[derive(Resource)]
+pub(crate) struct UniformsWritten<S: ComputeShader> {
+    written: bool,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for UniformsWritten<S> {
+    fn default() -> Self {
+        Self {
+            written: false,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> UniformsWritten<S> {
+    pub(crate) fn is_written(written: Res<Self>) -> bool {
+        written.written
+    }
+}
+
+/// A texture of a camera's view, bound with [`ComputeShader::view_textures`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComputeViewTexture {
+    /// The depth texture, as `texture_depth_2d`. The camera needs MSAA off and
+    /// `TextureUsages::TEXTURE_BINDING` in its depth texture usages.
+    Depth,
+    /// The main color target, as `texture_2d<f32>`, in the HDR format when the camera is HDR.
+    Main,
+}
+impl ComputeViewTexture {
+    fn layout_entry(self, binding: u32) -> BindGroupLayoutEntry {
+        match self {
+            Self::Depth => texture_depth_2d(),
+            Self::Main => texture_2d(TextureSampleType::Float { filterable: true }),
+        }
+        .build(binding, ShaderStages::COMPUTE)
+    }
+}
+
+/// Marks the camera whose view textures are bound with [`ComputeShader::view_textures`].
+/// Only one camera per shader is bound.
+#[derive(Component, Debug)]
+pub struct ComputeViewCamera<S: ComputeShader>(PhantomData<S>);
+impl<S: ComputeShader> Default for ComputeViewCamera<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Bind group for [`ComputeShader::view_textures`], prepared every frame in the render world.
+#[derive(Resource)]
+pub struct ComputeViewBindGroup<S: ComputeShader> {
+    /// Render entity of the camera marked with [`ComputeViewCamera`].
+    pub view: Option<Entity>,
+    /// `None` while the view doesn't have all of the textures.
+    pub bind_group: Option<BindGroup>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeViewBindGroup<S> {
+    fn default() -> Self {
+        Self {
+            view: None,
+            bind_group: None,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ComputeViewBindGroup<S> {
+    /// Find the render entity of the marked camera.
+    pub(crate) fn extract(
+        cameras: Extract<Query<RenderEntity, With<ComputeViewCamera<S>>>>,
+        mut view_bind_group: ResMut<Self>,
+    ) {
+        if cameras.iter().count() > 1 {
+            warn_once!(
+                "{}: multiple cameras have ComputeViewCamera, only one is bound",
+                S::shader_label()
+            );
+        }
+        view_bind_group.view = cameras.iter().next();
+    }
+    /// Whether the view had the textures to bind when it was last prepared. Checked before
+    /// this frame's textures are prepared, to skip the dispatch and its readbacks without them.
+    pub(crate) fn has_textures(world: &World) -> bool {
+        let Some(view) = world.get_resource::<Self>().and_then(|view| view.view) else {
+            return false;
+        };
+        let Ok(view) = world.get_entity(view) else {
+            return false;
+        };
+        S::view_textures()
+            .into_iter()
+            .all(|(_, texture)| match texture {
+                ComputeViewTexture::Depth => view.contains::<ViewDepthTexture>(),
+                ComputeViewTexture::Main => view.contains::<ViewTarget>(),
+            })
+    }
+    /// Bind this frame's textures of the view.
+    pub(crate) fn prepare(
+        mut view_bind_group: ResMut<Self>,
+        pipeline: Res<ComputePipeline<S>>,
+        pipeline_cache: Res<PipelineCache>,
+        render_device: Res<RenderDevice>,
+        views: Query<(Option<&ViewDepthTexture>, Option<&ViewTarget>)>,
+    ) {
+        view_bind_group.bind_group = None;
+        let (Some(layout), Some(view)) = (&pipeline.view_layout, view_bind_group.view) else {
+            return;
+        };
+        let Ok((depth, target)) = views.get(view) else {
+            return;
+        };
+        let entries = S::view_textures()
+            .into_iter()
+            .map(|(binding, texture)| {
+                let view = match texture {
+                    ComputeViewTexture::Depth => depth?.view(),
+                    ComputeViewTexture::Main => target?.main_texture_view(),
+                };
+                Some(BindGroupEntry {
+                    binding,
+                    resource: BindingResource::TextureView(view),
+                })
+            })
+            .collect::<Option<Vec<_>>>();
+        let Some(entries) = entries else {
+            return;
+        };
+        view_bind_group.bind_group = Some(render_device.create_bind_group(
+            "compute_view_textures",
+            &pipeline_cache.get_bind_group_layout(layout),
+            &entries,
+        ));
+    }
+}
+
+/// Defines the pipeline for the compute shader.
+#[derive(Resource)]
+pub struct ComputePipeline<S: ComputeShader> {
+    pub layout: BindGroupLayoutDescriptor,
+    /// Layouts from [`ComputeShader::extra_bind_group_layouts`].
+    pub extra_layouts: Vec<BindGroupLayoutDescriptor>,
+    /// Layout for [`ComputeShader::view_textures`], bound after all the other groups.
+    pub view_layout: Option<BindGroupLayoutDescriptor>,
+    /// Passes from [`ComputeShader::passes`], with one pipeline queued per pass.
+    pub(crate) passes: Vec<(ComputePassSpec, CachedComputePipelineId)>,
+    pub(crate) error: Option<ComputeShaderError>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ComputePipeline<S> {
+    /// Whether the shader has any bindings. Without them, no bind group is prepared or set.
+    pub fn has_bind_group(&self) -> bool {
+        !self.layout.entries.is_empty()
+            || !self.extra_layouts.is_empty()
+            || self.view_layout.is_some()
+    }
+    /// Group index of the view bind group, after the shader's own and the extra groups.
+    pub(crate) fn view_group_index(&self) -> u32 {
+        self.extra_layouts.len() as u32 + 1
+    }
+    /// Queue the pipelines again for a [`RetryComputeShader`], also checking the layouts again.
+    ///
+    /// [`RetryComputeShader`]: crate::RetryComputeShader
+    pub(crate) fn retry(world: &mut World) {
+        if !std::mem::take(&mut world.resource_mut::<ComputeNode<S>>().retry_pending) {
+            return;
+        }
+        let pipeline = Self::from_world(world);
+        world.insert_resource(pipeline);
+    }
+    /// Run condition for the bind group systems.
+    pub(crate) fn uses_bind_group(pipeline: Option<Res<Self>>) -> bool {
+        pipeline.is_some_and(|pipeline| pipeline.has_bind_group())
+    }
+    /// Combined state of all pass pipelines: any error, then anything still loading.
+    pub(crate) fn state<'a>(&self, pipeline_cache: &'a PipelineCache) -> &'a CachedPipelineState {
+        let states = self
+            .passes
+            .iter()
+            .map(|(_, id)| pipeline_cache.get_compute_pipeline_state(*id));
+        states
+            .clone()
+            .find(|state| matches!(state, CachedPipelineState::Err(_)))
+            .or_else(|| {
+                states
+                    .clone()
+                    .find(|state| !matches!(state, CachedPipelineState::Ok(_)))
+            })
+            .or_else(|| states.clone().next())
+            .unwrap_or(&CachedPipelineState::Queued)
+    }
+    /// Check the number of dynamic offsets against the layout, so a mismatch is reported
+    /// instead of failing wgpu validation.
+    pub(crate) fn validate_dynamic_offsets(
+        &self,
+        offsets: &[u32],
+    ) -> Result<(), ComputeShaderError> {
+        let expected = self
+            .layout
+            .entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.ty,
+                    BindingType::Buffer {
+                        has_dynamic_offset: true,
+                        ..
+                    }
+                )
+            })
+            .count();
+        if offsets.len() == expected {
+            Ok(())
+        } else {
+            Err(ComputeShaderError::DynamicOffsetCount {
+                shader: S::shader_label(),
+                expected,
+                actual: offsets.len(),
+            })
+        }
+    }
+}
+impl<S: ComputeShader> FromWorld for ComputePipeline<S> {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = S::shader_bind_group_layout(render_device);
+        let extra_layouts = S::extra_bind_group_layouts(render_device);
+        let view_textures = S::view_textures();
+        let view_layout = (!view_textures.is_empty()).then(|| {
+            let entries = view_textures
+                .iter()
+                .map(|(binding, texture)| texture.layout_entry(*binding))
+                .collect::<Vec<_>>();
+            BindGroupLayoutDescriptor::new("compute_view_textures", &entries)
+        });
+        let shader = match S::compute_shader() {
+            ShaderRef::Default => panic!("Must define compute_shader."),
+            ShaderRef::Handle(handle) => handle,
+            ShaderRef::Path(path) => world.load_asset(path),
+        };
+        // Shaders without any bindings don't get a bind group at all.
+        let has_bind_group =
+            !layout.entries.is_empty() || !extra_layouts.is_empty() || view_layout.is_some();
+        let index = S::bind_group_index() as usize;
+        assert!(
+            !has_bind_group || index <= extra_layouts.len(),
+            "{}: bind_group_index {index} needs extra_bind_group_layouts for the {index} lower \
+            groups, but only {} were provided",
+            S::shader_label(),
+            extra_layouts.len()
+        );
+        let descriptors = S::passes()
+            .into_iter()
+            .map(|spec| {
+                let mut layouts = Vec::new();
+                if has_bind_group {
+                    layouts.clone_from(&extra_layouts);
+                    layouts.insert(index.min(layouts.len()), layout.clone());
+                    layouts.extend(view_layout.clone());
+                }
+                let descriptor = S::pipeline_descriptor(layouts, shader.clone(), &spec);
+                assert!(
+                    !has_bind_group || descriptor.layout.get(index) == Some(&layout),
+                    "{}: the descriptor from pipeline_descriptor must keep the AsBindGroup \
+                    layout at index {index}, which prepare_bind_group creates the bind group for",
+                    S::shader_label()
+                );
+                (spec, descriptor)
+            })
+            .collect::<Vec<_>>();
+        let uses_immediates = descriptors
+            .iter()
+            .any(|(_, descriptor)| descriptor.immediate_size > 0);
+        let error =
+            if uses_immediates && !render_device.features().contains(WgpuFeatures::IMMEDIATES) {
+                Some(ComputeShaderError::ImmediatesUnsupported(S::shader_label()))
+            } else {
+                None
+            };
+        if let Some(error) = error {
+            error!("{error}");
+            return Self {
+                layout,
+                extra_layouts,
+                view_layout,
+                passes: Vec::new(),
+                error: Some(error),
+                _marker: PhantomData,
+            };
+        }
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let passes = descriptors
+            .into_iter()
+            .map(|(spec, descriptor)| (spec, pipeline_cache.queue_compute_pipeline(descriptor)))
+            .collect();
+        Self {
+            layout,
+            extra_layouts,
+            view_layout,
+            passes,
+            error: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use bevy::{
+        asset::{Handle, uuid_handle},
+        render::{
+            extract_resource::ExtractResource,
+            render_resource::AsBindGroup,
+            renderer::{RenderAdapter, WgpuWrapper},
+        },
+        shader::{Shader, ShaderDefVal},
+        tasks::block_on,
+    };
+
+    use super::*;
+    use crate::PipelineConstant;
+
+    /// Render world with a device, queue and pipeline cache on wgpu's noop backend, for tests
+    /// creating GPU resources without a GPU.
+    pub(crate) fn noop_render_world() -> World {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::NOOP,
+            backend_options: wgpu::BackendOptions {
+                noop: wgpu::NoopBackendOptions { enable: true },
+                ..Default::default()
+            },
+            ..wgpu::InstanceDescriptor::new_without_display_handle()
+        });
+        let adapter = block_on(instance.request_adapter(&Default::default()))
+            .expect("the noop backend always has an adapter");
+        let (device, queue) = block_on(adapter.request_device(&Default::default()))
+            .expect("the noop adapter always creates a device");
+        let render_device = RenderDevice::from(device);
+        let render_adapter = RenderAdapter(Arc::new(WgpuWrapper::new(adapter)));
+        let mut world = World::new();
+        world.insert_resource(PipelineCache::new(
+            render_device.clone(),
+            render_adapter,
+            true,
+        ));
+        world.insert_resource(render_device);
+        world.insert_resource(RenderQueue(Arc::new(WgpuWrapper::new(queue))));
+        world
+    }
+
+    /// Pipeline without passes, for tests without a render device.
+    pub(crate) fn empty_pipeline<S: ComputeShader>() -> ComputePipeline<S> {
+        ComputePipeline {
+            layout: BindGroupLayoutDescriptor::new(S::shader_label(), &[]),
+            extra_layouts: Vec::new(),
+            view_layout: None,
+            passes: Vec::new(),
+            error: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// One shader file whose overrides are set by [`ComputeShader::pipeline_constants`].
+    const TILES_SHADER: Handle<Shader> = uuid_handle!("3f0c8a52-5d2e-4b8e-9a34-1c6f0e7d9b21");
+    const TILES_WGSL: &str = "
+        override TILE: u32 = #{TILE};
+        override OFFSET: i32 = #{OFFSET};
+
+        @compute @workgroup_size(1)
+        fn main(@builtin(local_invocation_index) index: u32) {
+            _ = i32(index % TILE) + OFFSET;
+        }
+    ";
+
+    #[derive(AsBindGroup, Resource, ExtractResource, Clone, Default)]
+    struct TilesComputeShader<const TILE: u32, const OFFSET: i32> {}
+    impl<const TILE: u32, const OFFSET: i32> ComputeShader for TilesComputeShader<TILE, OFFSET> {
+        fn compute_shader() -> ShaderRef {
+            TILES_SHADER.into()
+        }
+        fn pipeline_constants() -> Vec<(String, PipelineConstant)> {
+            vec![
+                ("TILE".into(), TILE.into()),
+                ("OFFSET".into(), OFFSET.into()),
+            ]
+        }
+    }
+
+    #[test]
+    fn pipeline_constants_specialize_one_shader() {
+        let mut world = noop_render_world();
+        world.resource_mut::<PipelineCache>().set_shader(
+            TILES_SHADER.id(),
+            Shader::from_wgsl(TILES_WGSL, "tiles.wgsl"),
+        );
+        let small = ComputePipeline::<TilesComputeShader<8, -1>>::from_world(&mut world);
+        let large = ComputePipeline::<TilesComputeShader<64, 1>>::from_world(&mut world);
+        let mut pipeline_cache = world.resource_mut::<PipelineCache>();
+        pipeline_cache.process_queue();
+        let (small, large) = (small.passes[0].1, large.passes[0].1);
+        assert_ne!(small, large);
+        // Both compile against the declared types, which a negative `u32` wouldn't.
+        for id in [small, large] {
+            assert!(
+                matches!(
+                    pipeline_cache.get_compute_pipeline_state(id),
+                    CachedPipelineState::Ok(_)
+                ),
+                "{:?}",
+                pipeline_cache.get_compute_pipeline_state(id)
+            );
+        }
+        let defs = |id| {
+            pipeline_cache
+                .get_compute_pipeline_descriptor(id)
+                .shader_defs
+                .clone()
+        };
+        assert_eq!(
+            defs(small),
+            [
+                ShaderDefVal::UInt("TILE".into(), 8),
+                ShaderDefVal::Int("OFFSET".into(), -1)
+            ]
+        );
+        assert_eq!(
+            defs(large),
+            [
+                ShaderDefVal::UInt("TILE".into(), 64),
+                ShaderDefVal::Int("OFFSET".into(), 1)
+            ]
+        );
+    }
+}
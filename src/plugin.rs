@@ -0,0 +1,1634 @@
+//! The [`ComputeShaderPlugin`], the app extension adding it and the commands controlling a shader.
+
+use std::{
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{Arc, mpsc::SyncSender},
+    time::Duration,
+};
+
+use bevy::{
+    app::{App, First, Last, Plugin, PluginsState, Update},
+    asset::{AssetServer, Assets},
+    ecs::{
+        bundle::Bundle,
+        change_detection::DetectChangesMut,
+        entity::Entity,
+        message::Message,
+        observer::Observer,
+        query::{Has, With},
+        resource::Resource,
+        schedule::{
+            InternedScheduleLabel, InternedSystemSet, IntoScheduleConfigs, Schedule, ScheduleLabel,
+            SystemCondition, SystemSet,
+            common_conditions::{
+                any_with_component, not, resource_changed, resource_exists,
+                resource_exists_and_changed,
+            },
+        },
+        system::{Command, IntoObserverSystem, Query, Res, ResMut},
+        world::World,
+    },
+    log::error_once,
+    math::UVec2,
+    render::{
+        ExtractSchedule, MainWorld, Render, RenderApp, RenderSystems,
+        extract_resource::{ExtractResource, ExtractResourcePlugin, extract_resource},
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{
+            ShaderSize, ShaderType,
+            encase::internal::{CreateFrom, WriteInto},
+        },
+        renderer::RenderGraph,
+        storage::ShaderBuffer,
+    },
+    shader::{ShaderRef, load_shader_library},
+    state::{
+        app::AppExtStates,
+        condition::in_state,
+        state::{NextState, State, States},
+    },
+    time::{Time, Virtual},
+    window::Window,
+};
+
+use crate::{
+    ComputeError, ComputeNode, ComputeNodeState, ComputeNodeStatus, ComputePipeline,
+    ComputeProgress, ComputeReadbackEntities, ComputeReadbackSettings, ComputeReadbackTarget,
+    ComputeShader, ComputeShaderBindGroup, ComputeShaderConfig, ComputeShaderReadback,
+    ComputeStatusChanged, ComputeViewBindGroup, DispatchRate, DownsamplePlugin, GpuEventBuffer,
+    GpuEventInput, GpuEventInputs, GpuEventOutputs, ImageReadback, InputSnapshot, LatestReadback,
+    ProcessedReadback, ReadbackBindings, ReadbackBudget, ReadbackChannel, ReadbackData,
+    ReadbackDedupe, ReadbackDownsample, ReadbackEntities, ReadbackEvent, ReadbackHistory,
+    ReadbackInfo, ReadbackInfos, ReadbackLatencyStats, ReadbackObserver, ReadbackObservers,
+    ReadbackPacket, ReadbackProcessing, ReadbackProcessor, ReadbackQueue, ReadbackRange,
+    ReadbackRegion, ReadbackResources, ReadbackTile, ReadbackTiles, ReadbackTilesCovered,
+    ReadbackTracker, StatusTransitions, TileCoverage, TileCursor, UniformsWritten, decode_single,
+};
+
+/// Plugin to create all the required systems for using a custom compute shader.
+pub struct ComputeShaderPlugin<S: ComputeShader> {
+    pub limit: ReadbackLimit,
+    /// Tear the shader down with [`RemoveComputeShader`] once a run completes and its final
+    /// readback is delivered. Use [`RestartComputeShader`] to run it again.
+    pub remove_on_complete: bool,
+    /// Read back every Nth dispatch. The final dispatch of a finite run is always read back.
+    pub readback_interval: usize,
+    /// Whether changes to `S` restart the run, resetting [`ReadbackLimit::Finite`]. Defaults to
+    /// true, making the limit per change. When false the limit applies once per app run:
+    /// changes still refresh the bind group, but a completed run stays completed.
+    pub reset_limit_on_change: bool,
+    /// Number of dispatches at the start of each run that aren't read back, e.g. while the
+    /// output stabilizes. [`ReadbackLimit::Finite`] and `readback_interval` count the
+    /// dispatches after the warmup, and a reset restarts it.
+    pub warmup: usize,
+    /// Dispatch every Nth frame once ready. Frames in between are skipped like
+    /// [`ComputeShader::should_dispatch`]: not dispatched, read back or counted towards
+    /// [`ReadbackLimit::Finite`], and the status stays [`ComputeNodeStatus::Ready`].
+    pub dispatch_interval: usize,
+    /// Dispatch at a fixed rate instead of once per frame, independent of the frame rate.
+    /// See [`DispatchRate`]. Rates that aren't finite and positive are ignored.
+    pub dispatch_rate: Option<DispatchRate>,
+    /// Only dispatch once the previous readbacks were delivered to the main world, so
+    /// `on_readback` never falls behind. Frames waiting on a readback are skipped.
+    pub synchronized: bool,
+    /// Most readbacks requested but not yet delivered, to bound memory when the main world
+    /// stalls. At the cap new readbacks are skipped, except final and requested dispatches, which
+    /// wait. Defaults to no cap.
+    pub max_in_flight_readbacks: usize,
+    /// Follow `Time<Virtual>`: suspend dispatching while it's paused, and scale
+    /// [`ComputeShaderPlugin::dispatch_rate`] by its relative speed.
+    /// Set with [`ComputeShaderPlugin::pause_with_virtual_time`], which adds the run condition.
+    pub pause_with_virtual_time: bool,
+    /// Skip the built-in [`ExtractResourcePlugin`] for `S`. The app must then insert and update
+    /// `S` in the render world itself, e.g. from an [`ExtractSchedule`] system.
+    pub custom_extraction: bool,
+    /// Also write each readback as a [`ReadbackEvent`] message.
+    pub readback_events: bool,
+    /// Keep the most recent readback in the [`LatestReadback`] resource.
+    pub latest_readback: bool,
+    /// Also forward each readback to a channel, for consumers outside the ECS.
+    /// See [`ReadbackChannel`].
+    pub channel: Option<SyncSender<ReadbackPacket>>,
+    /// Keep the last readbacks in the [`ReadbackHistory`] resource, up to this many.
+    /// Defaults to 0, keeping none.
+    pub readback_history: usize,
+    /// Push each readback into the [`ReadbackQueue`] resource, holding up to this many,
+    /// for systems to drain instead of observing. Defaults to 0, no queue.
+    pub readback_queue: usize,
+    /// Keep the input each dispatch ran with and deliver it with its readbacks, e.g. in
+    /// [`ReadbackEvent::input`], as `S` may have changed by the time they arrive.
+    pub readback_inputs: bool,
+    /// Read back textures box-filtered down to this size on the GPU instead of at full
+    /// resolution. See [`ReadbackDownsample`].
+    pub readback_downsample: Option<UVec2>,
+    /// Skip delivering readbacks identical to the previous one. See [`ReadbackDedupe`].
+    pub dedupe: Option<ReadbackDedupe<S>>,
+    /// Only dispatch while this condition holds. See [`ComputeShaderPlugin::run_if`].
+    pub(crate) run_condition: RunCondition,
+    /// Main world schedule that handles status transitions: inserting and removing the
+    /// readbacks, [`ComputeShader::on_status_change`] and the other hooks. Defaults to [`Update`].
+    /// Transitions are queued, so none are lost in schedules that don't run every frame.
+    pub main_schedule: InternedScheduleLabel,
+    /// Render world schedule the dispatch is encoded in. Defaults to [`RenderGraph`].
+    pub render_schedule: InternedScheduleLabel,
+    /// Optional set within [`ComputeShaderPlugin::render_schedule`] to position the dispatch,
+    /// e.g. between two passes whose systems are ordered around it.
+    pub render_set: Option<InternedSystemSet>,
+    /// Sets in [`ComputeShaderPlugin::render_schedule`] that the dispatch runs before.
+    pub before: Vec<InternedSystemSet>,
+    /// Sets in [`ComputeShaderPlugin::render_schedule`] that the dispatch runs after.
+    pub after: Vec<InternedSystemSet>,
+    /// Shaders whose output this shader consumes. See [`ComputeShaderPlugin::depends_on`].
+    pub dependencies: Vec<ComputeDependency>,
+    /// Initial input, inserted instead of `S::from_world`.
+    pub resource: Option<S>,
+    /// Don't insert `S` at all. The shader stays [`ComputeNodeStatus::Loading`] until the app
+    /// inserts it, e.g. once the config or assets it's built from are loaded.
+    pub defer_resource: bool,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeShaderPlugin<S> {
+    fn default() -> Self {
+        Self {
+            limit: ReadbackLimit::default(),
+            remove_on_complete: false,
+            readback_interval: 1,
+            reset_limit_on_change: true,
+            warmup: 0,
+            dispatch_interval: 1,
+            dispatch_rate: None,
+            synchronized: false,
+            max_in_flight_readbacks: usize::MAX,
+            pause_with_virtual_time: false,
+            custom_extraction: false,
+            readback_events: false,
+            latest_readback: false,
+            channel: None,
+            readback_history: 0,
+            readback_queue: 0,
+            readback_inputs: false,
+            readback_downsample: None,
+            dedupe: None,
+            run_condition: RunCondition::default(),
+            main_schedule: Update.intern(),
+            render_schedule: RenderGraph.intern(),
+            render_set: None,
+            before: Vec::new(),
+            after: Vec::new(),
+            dependencies: Vec::new(),
+            resource: None,
+            defer_resource: false,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ComputeShaderPlugin<S> {
+    /// Plugin with default options, to be configured with the `with_*` methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set [`ComputeShaderPlugin::limit`].
+    pub fn with_limit(mut self, limit: ReadbackLimit) -> Self {
+        self.limit = limit;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::remove_on_complete`].
+    pub fn with_remove_on_complete(mut self, remove_on_complete: bool) -> Self {
+        self.remove_on_complete = remove_on_complete;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::readback_interval`].
+    pub fn with_readback_interval(mut self, readback_interval: usize) -> Self {
+        self.readback_interval = readback_interval;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::resource`].
+    pub fn with_resource(mut self, resource: S) -> Self {
+        self.resource = Some(resource);
+        self
+    }
+    /// Set [`ComputeShaderPlugin::defer_resource`].
+    pub fn with_deferred_resource(mut self) -> Self {
+        self.defer_resource = true;
+        self
+    }
+    /// Only read back the final dispatch of a run, so a finite run delivers exactly one
+    /// readback. Each reset starts a new run with its own final readback.
+    /// Infinite runs are then never read back.
+    pub fn with_final_readback_only(self) -> Self {
+        self.with_readback_interval(usize::MAX)
+    }
+    /// Set [`ComputeShaderPlugin::reset_limit_on_change`].
+    pub fn with_reset_limit_on_change(mut self, reset_limit_on_change: bool) -> Self {
+        self.reset_limit_on_change = reset_limit_on_change;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::warmup`].
+    pub fn with_warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::dispatch_interval`].
+    pub fn with_dispatch_interval(mut self, dispatch_interval: usize) -> Self {
+        self.dispatch_interval = dispatch_interval;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::dispatch_rate`].
+    pub fn with_dispatch_rate(mut self, dispatch_rate: DispatchRate) -> Self {
+        self.dispatch_rate = Some(dispatch_rate);
+        self
+    }
+    /// Set [`ComputeShaderPlugin::synchronized`].
+    pub fn with_synchronized(mut self, synchronized: bool) -> Self {
+        self.synchronized = synchronized;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::max_in_flight_readbacks`].
+    pub fn with_max_in_flight_readbacks(mut self, max_in_flight_readbacks: usize) -> Self {
+        self.max_in_flight_readbacks = max_in_flight_readbacks;
+        self
+    }
+    /// Enable [`ComputeShaderPlugin::readback_events`].
+    pub fn with_readback_events(mut self) -> Self {
+        self.readback_events = true;
+        self
+    }
+    /// Enable [`ComputeShaderPlugin::latest_readback`].
+    pub fn with_latest_readback(mut self) -> Self {
+        self.latest_readback = true;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::channel`].
+    pub fn with_channel(mut self, sender: SyncSender<ReadbackPacket>) -> Self {
+        self.channel = Some(sender);
+        self
+    }
+    /// Set [`ComputeShaderPlugin::readback_history`].
+    pub fn with_readback_history(mut self, capacity: usize) -> Self {
+        self.readback_history = capacity;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::readback_queue`].
+    pub fn with_readback_queue(mut self, capacity: usize) -> Self {
+        self.readback_queue = capacity;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::readback_downsample`].
+    pub fn with_readback_downsample(mut self, size: UVec2) -> Self {
+        self.readback_downsample = Some(size);
+        self
+    }
+    /// Enable [`ComputeShaderPlugin::readback_inputs`].
+    pub fn with_readback_inputs(mut self) -> Self {
+        self.readback_inputs = true;
+        self
+    }
+    /// Set [`ComputeShaderPlugin::dedupe`], e.g. `ReadbackDedupe::new()` to hash whole readbacks.
+    pub fn with_dedupe(mut self, dedupe: ReadbackDedupe<S>) -> Self {
+        self.dedupe = Some(dedupe);
+        self
+    }
+    /// Set [`ComputeShaderPlugin::custom_extraction`].
+    pub fn with_custom_extraction(mut self, custom_extraction: bool) -> Self {
+        self.custom_extraction = custom_extraction;
+        self
+    }
+    /// Handle status transitions in another main world schedule, e.g. `FixedUpdate`
+    /// to line up with the gameplay logic reacting to them.
+    pub fn in_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.main_schedule = schedule.intern();
+        self
+    }
+    /// Encode the dispatch in another render world schedule, e.g. one that runs the passes
+    /// consuming its output. Schedules that run once per view still dispatch once per frame.
+    pub fn with_render_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.render_schedule = schedule.intern();
+        self
+    }
+    /// Encode the dispatch in a set of [`ComputeShaderPlugin::render_schedule`].
+    pub fn in_render_set(mut self, set: impl SystemSet) -> Self {
+        self.render_set = Some(set.intern());
+        self
+    }
+    /// Dispatch before another compute shader, e.g. one that reads this shader's output.
+    pub fn before<T: ComputeShader>(self) -> Self {
+        self.before_set(ComputeNodeLabel::<T>::default())
+    }
+    /// Dispatch after another compute shader, e.g. one that writes this shader's input.
+    pub fn after<T: ComputeShader>(self) -> Self {
+        self.after_set(ComputeNodeLabel::<T>::default())
+    }
+    /// Consume the output of another compute shader: dispatch after it, only become ready
+    /// once it has dispatched in its current run, and reset whenever it resets.
+    pub fn depends_on<T: ComputeShader>(mut self) -> Self {
+        self.dependencies.push(ComputeDependency::on::<T>());
+        self
+    }
+    /// Dispatch before any set in the same render schedule.
+    pub fn before_set(mut self, set: impl SystemSet) -> Self {
+        self.before.push(set.intern());
+        self
+    }
+    /// Dispatch after any set in the same render schedule.
+    pub fn after_set(mut self, set: impl SystemSet) -> Self {
+        self.after.push(set.intern());
+        self
+    }
+    /// Only dispatch while a main world condition holds, with the status
+    /// [`ComputeNodeStatus::Paused`] meanwhile. Calling it again adds another condition.
+    pub fn run_if<M>(
+        mut self,
+        condition: impl SystemCondition<M> + Clone + Send + Sync + 'static,
+    ) -> Self {
+        self.run_condition.0.push(Arc::new(move |app: &mut App| {
+            app.add_systems(
+                Last,
+                ComputeShaderGate::<S>::request
+                    .run_if(resource_exists::<ComputeShaderGate<S>>)
+                    .run_if(condition.clone())
+                    .before(ComputeShaderGate::<S>::commit),
+            );
+        }));
+        self
+    }
+    /// Only dispatch while in the given state. See [`ComputeShaderPlugin::run_if`].
+    pub fn enabled_in_state<T: States>(self, state: T) -> Self {
+        self.run_if(in_state(state))
+    }
+    /// Suspend dispatching while `Time<Virtual>` is paused, like systems driven by virtual
+    /// time. See [`ComputeShaderPlugin::pause_with_virtual_time`].
+    pub fn pause_with_virtual_time(mut self) -> Self {
+        self.pause_with_virtual_time = true;
+        self.run_if(virtual_time_running)
+    }
+    /// Suspend dispatching while no window is focused, e.g. while minimized, resuming where
+    /// the run left off. Apps without windows are unaffected. See [`any_window_focused`].
+    pub fn suspend_when_unfocused(self) -> Self {
+        self.run_if(any_window_focused)
+    }
+}
+
+/// System set of the render world systems that update and dispatch `S`.
+/// Order against it to run other render systems before or after the dispatch.
+#[derive(SystemSet)]
+pub struct ComputeNodeLabel<S: ComputeShader>(PhantomData<S>);
+// Implemented by hand so that `S` doesn't need to implement these traits.
+impl<S: ComputeShader> Default for ComputeNodeLabel<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: ComputeShader> Clone for ComputeNodeLabel<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<S: ComputeShader> Copy for ComputeNodeLabel<S> {}
+impl<S: ComputeShader> Debug for ComputeNodeLabel<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ComputeNodeLabel<{}>", S::shader_label())
+    }
+}
+impl<S: ComputeShader> Hash for ComputeNodeLabel<S> {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+impl<S: ComputeShader> PartialEq for ComputeNodeLabel<S> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+impl<S: ComputeShader> Eq for ComputeNodeLabel<S> {}
+
+/// Extension methods on [`App`] for compute shaders.
+pub trait ComputeShaderAppExt {
+    /// Add [`ComputeShaderPlugin<S>`] with default options.
+    /// Panics if `S` was already added.
+    fn add_compute_shader<S: ComputeShader>(&mut self) -> &mut Self;
+    /// Add a configured [`ComputeShaderPlugin<S>`], e.g.
+    /// `app.add_compute_shader_with(ComputeShaderPlugin::<S>::new().with_limit(limit))`.
+    /// Panics if `S` was already added.
+    fn add_compute_shader_with<S: ComputeShader>(
+        &mut self,
+        plugin: ComputeShaderPlugin<S>,
+    ) -> &mut Self;
+    /// Remove everything [`ComputeShaderPlugin<S>`] created. See [`RemoveComputeShader`].
+    fn remove_compute_shader<S: ComputeShader>(&mut self) -> &mut Self;
+    /// Start a new run of a removed or finished shader. See [`RestartComputeShader`].
+    fn restart_compute_shader<S: ComputeShader>(&mut self) -> &mut Self;
+    /// Dispatch a [`ReadbackLimit::Manual`] shader once. See [`DispatchComputeShader`].
+    fn dispatch_compute_shader<S: ComputeShader>(&mut self) -> &mut Self;
+    /// Decode the first readback target of `S` as a single `R` with [`decode_single`], and
+    /// insert it as a resource after each readback. Decode errors are logged once.
+    fn add_compute_readback_resource<S, R>(&mut self) -> &mut Self
+    where
+        S: ComputeShader,
+        R: Resource + ShaderType + ShaderSize + CreateFrom;
+    /// Like [`ComputeShaderAppExt::add_compute_readback_resource`], decoding with `decode`.
+    fn add_compute_readback_resource_with<S, R, E>(
+        &mut self,
+        decode: impl Fn(&[u8]) -> Result<R, E> + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        S: ComputeShader,
+        R: Resource,
+        E: fmt::Display;
+    /// Process each readback of `S` with `process` on the [`AsyncComputeTaskPool`] and write the
+    /// results as [`ProcessedReadback<S, T>`] messages, possibly frames later.
+    ///
+    /// [`AsyncComputeTaskPool`]: bevy::tasks::AsyncComputeTaskPool
+    fn add_compute_readback_processor<S, T>(
+        &mut self,
+        processing: ReadbackProcessing,
+        process: impl Fn(ReadbackData, ReadbackInfo) -> T + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        S: ComputeShader,
+        T: Send + Sync + 'static;
+    /// Attach another observer of [`ReadbackComplete`] to the readback entities of `S`, also
+    /// when they are respawned. It isn't ordered relative to the crate's own observers.
+    fn observe_compute_readback<S, B, M>(
+        &mut self,
+        observer: impl IntoObserverSystem<ReadbackComplete, B, M> + Clone + Sync,
+    ) -> &mut Self
+    where
+        S: ComputeShader,
+        B: Bundle;
+    /// Let `S` emit events of type `E` from the GPU, written as messages in the main world.
+    /// Creates a [`GpuEventBuffer<S, E>`] for up to `capacity` events.
+    fn add_compute_event_output<S, E>(&mut self, capacity: u32) -> &mut Self
+    where
+        S: ComputeShader,
+        E: Message + ShaderType + ShaderSize + CreateFrom;
+    /// Let `S` read events of type `E` sent in the main world, e.g. paint strokes.
+    /// Creates a [`GpuEventInput<S, E>`] for up to `capacity` events. Add it before
+    /// [`ComputeShaderPlugin<S>`] so `S::from_world` can bind the buffer.
+    fn add_compute_event_input<S, E>(&mut self, capacity: u32) -> &mut Self
+    where
+        S: ComputeShader,
+        E: Message + Clone + ShaderType + ShaderSize + WriteInto;
+}
+impl ComputeShaderAppExt for App {
+    fn add_compute_shader<S: ComputeShader>(&mut self) -> &mut Self {
+        self.add_compute_shader_with(ComputeShaderPlugin::<S>::new())
+    }
+    fn add_compute_shader_with<S: ComputeShader>(
+        &mut self,
+        plugin: ComputeShaderPlugin<S>,
+    ) -> &mut Self {
+        // Each shader is keyed by its type, so a second plugin would share its resources.
+        assert!(
+            !self.is_plugin_added::<ComputeShaderPlugin<S>>(),
+            "{}: compute shader was already added. Use `ComputeShaderInstance` to run several \
+            instances of the same shader.",
+            S::shader_label()
+        );
+        self.add_plugins(plugin)
+    }
+    fn remove_compute_shader<S: ComputeShader>(&mut self) -> &mut Self {
+        RemoveComputeShader::<S>::default().apply(self.world_mut());
+        self
+    }
+    fn restart_compute_shader<S: ComputeShader>(&mut self) -> &mut Self {
+        RestartComputeShader::<S>::default().apply(self.world_mut());
+        self
+    }
+    fn dispatch_compute_shader<S: ComputeShader>(&mut self) -> &mut Self {
+        DispatchComputeShader::<S>::default().apply(self.world_mut());
+        self
+    }
+    fn add_compute_readback_resource<S, R>(&mut self) -> &mut Self
+    where
+        S: ComputeShader,
+        R: Resource + ShaderType + ShaderSize + CreateFrom,
+    {
+        self.add_compute_readback_resource_with::<S, R, _>(decode_single::<R>)
+    }
+    fn add_compute_readback_resource_with<S, R, E>(
+        &mut self,
+        decode: impl Fn(&[u8]) -> Result<R, E> + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        S: ComputeShader,
+        R: Resource,
+        E: fmt::Display,
+    {
+        self.world_mut()
+            .get_resource_or_init::<ReadbackResources<S>>()
+            .decoders
+            .push(Arc::new(move |data, commands| match decode(data) {
+                Ok(resource) => commands.insert_resource(resource),
+                Err(error) => error_once!(
+                    "{}: failed to decode readback resource {}: {error}",
+                    S::shader_label(),
+                    std::any::type_name::<R>(),
+                ),
+            }));
+        self
+    }
+    fn add_compute_readback_processor<S, T>(
+        &mut self,
+        processing: ReadbackProcessing,
+        process: impl Fn(ReadbackData, ReadbackInfo) -> T + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        S: ComputeShader,
+        T: Send + Sync + 'static,
+    {
+        self.add_message::<ProcessedReadback<S, T>>()
+            .insert_resource(ReadbackProcessor::<S, T>::new(process, processing))
+            .add_systems(
+                First,
+                ReadbackProcessor::<S, T>::poll.run_if(resource_exists::<ReadbackProcessor<S, T>>),
+            );
+        self.world_mut()
+            .get_resource_or_init::<ReadbackResources<S>>()
+            .processors
+            .push(Arc::new(ReadbackProcessor::<S, T>::spawn));
+        self
+    }
+    fn observe_compute_readback<S, B, M>(
+        &mut self,
+        observer: impl IntoObserverSystem<ReadbackComplete, B, M> + Clone + Sync,
+    ) -> &mut Self
+    where
+        S: ComputeShader,
+        B: Bundle,
+    {
+        let observer: ReadbackObserver =
+            Arc::new(move |entity| Observer::new(observer.clone()).with_entity(entity));
+        let world = self.world_mut();
+        world
+            .get_resource_or_init::<ReadbackObservers<S>>()
+            .observers
+            .push(observer.clone());
+        let entities = world
+            .get_resource::<ComputeReadbackEntities<S>>()
+            .map(|entities| {
+                entities
+                    .iter()
+                    .map(|(_, entity)| entity)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for entity in entities {
+            world.spawn(observer(entity));
+        }
+        self
+    }
+    fn add_compute_event_output<S, E>(&mut self, capacity: u32) -> &mut Self
+    where
+        S: ComputeShader,
+        E: Message + ShaderType + ShaderSize + CreateFrom,
+    {
+        if !self.is_plugin_added::<ComputeEventsPlugin>() {
+            self.add_plugins(ComputeEventsPlugin);
+        }
+        self.add_message::<E>();
+        let events = GpuEventBuffer::<S, E>::new(
+            &mut self.world_mut().resource_mut::<Assets<ShaderBuffer>>(),
+            capacity,
+        );
+        let id = events.buffer.id();
+        self.world_mut()
+            .get_resource_or_init::<GpuEventOutputs<S>>()
+            .outputs
+            .push((id, Arc::new(GpuEventBuffer::<S, E>::write)));
+        if let Some(render_app) = self.get_sub_app_mut(RenderApp) {
+            render_app
+                .world_mut()
+                .get_resource_or_init::<GpuEventOutputs<S>>()
+                .buffers
+                .push(events.buffer.clone());
+        }
+        self.insert_resource(events);
+        self
+    }
+    fn add_compute_event_input<S, E>(&mut self, capacity: u32) -> &mut Self
+    where
+        S: ComputeShader,
+        E: Message + Clone + ShaderType + ShaderSize + WriteInto,
+    {
+        if !self.is_plugin_added::<ComputeEventsPlugin>() {
+            self.add_plugins(ComputeEventsPlugin);
+        }
+        self.add_message::<E>();
+        let input = GpuEventInput::<S, E>::new(
+            &mut self.world_mut().resource_mut::<Assets<ShaderBuffer>>(),
+            capacity,
+        );
+        if let Some(render_app) = self.get_sub_app_mut(RenderApp) {
+            render_app
+                .world_mut()
+                .get_resource_or_init::<GpuEventInputs<S>>()
+                .inputs
+                .push(input.queue.clone());
+        }
+        self.insert_resource(input).add_systems(
+            Last,
+            GpuEventInput::<S, E>::collect.run_if(resource_exists::<GpuEventInput<S, E>>),
+        )
+    }
+}
+
+/// Loads the `bevy_compute_readback::events` shader import.
+struct ComputeEventsPlugin;
+impl Plugin for ComputeEventsPlugin {
+    fn build(&self, app: &mut App) {
+        load_shader_library!(app, "shaders/events.wgsl");
+    }
+}
+
+/// Add several compute shaders with the same options, returning a [`ComputeShaderGroup`].
+///
+/// ```ignore
+/// let bakes = add_compute_shaders!(&mut app, [BakeA, BakeB, BakeC], |plugin| {
+///     plugin.with_limit(ReadbackLimit::Finite(1)).with_remove_on_complete(true)
+/// });
+/// ```
+#[macro_export]
+macro_rules! add_compute_shaders {
+    ($app:expr, [$($shader:ty),* $(,)?], $options:expr $(,)?) => {{
+        use $crate::ComputeShaderAppExt as _;
+        let app: &mut _ = &mut *$app;
+        let mut group = $crate::ComputeShaderGroup::default();
+        $(
+            app.add_compute_shader_with(($options)($crate::ComputeShaderPlugin::<$shader>::new()));
+            group.push::<$shader>();
+        )*
+        group
+    }};
+    ($app:expr, [$($shader:ty),* $(,)?] $(,)?) => {
+        $crate::add_compute_shaders!($app, [$($shader),*], |plugin| plugin)
+    };
+}
+
+/// Set of compute shaders watched together, e.g. by a loading screen.
+/// See [`add_compute_shaders!`].
+#[derive(Default, Clone)]
+pub struct ComputeShaderGroup {
+    statuses: Vec<fn(&World) -> Option<ComputeNodeStatus>>,
+}
+impl ComputeShaderGroup {
+    /// Add `S` to the group.
+    pub fn push<S: ComputeShader>(&mut self) {
+        self.statuses.push(|world| {
+            world
+                .get_resource::<State<ComputeNodeState<S>>>()
+                .map(|state| state.get().status())
+        });
+    }
+    /// Number of shaders in the group.
+    pub fn len(&self) -> usize {
+        self.statuses.len()
+    }
+    /// Whether the group has no shaders.
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+    }
+    /// Number of shaders that completed. Shaders removed with [`RemoveComputeShader`], e.g. by
+    /// [`ComputeShaderPlugin::remove_on_complete`], count as completed.
+    pub fn completed(&self, world: &World) -> usize {
+        self.statuses
+            .iter()
+            .filter(|status| {
+                status(world).is_none_or(|status| status == ComputeNodeStatus::Completed)
+            })
+            .count()
+    }
+    /// Whether every shader in the group completed.
+    pub fn all_completed(&self, world: &World) -> bool {
+        self.completed(world) == self.len()
+    }
+    /// Whether any shader in the group is in [`ComputeNodeStatus::Error`].
+    pub fn any_error(&self, world: &World) -> bool {
+        self.statuses
+            .iter()
+            .any(|status| status(world) == Some(ComputeNodeStatus::Error))
+    }
+}
+
+/// Command to start a new run of a compute shader, resetting its readback limit.
+/// After [`RemoveComputeShader`] this recreates what it removed, using the given input or
+/// `S::from_world`.
+pub struct RestartComputeShader<S: ComputeShader>(pub Option<S>);
+impl<S: ComputeShader> Default for RestartComputeShader<S> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+impl<S: ComputeShader> Command for RestartComputeShader<S> {
+    type Out = ();
+    fn apply(self, world: &mut World) {
+        if world.contains_resource::<ReadbackTracker<S>>() {
+            match self.0 {
+                Some(input) => {
+                    world.insert_resource(input);
+                }
+                None => {
+                    if let Some(mut input) = world.get_resource_mut::<S>() {
+                        input.set_changed();
+                    }
+                }
+            }
+            return;
+        }
+        let Some(config) = world.get_resource::<ComputeShaderConfig<S>>() else {
+            return;
+        };
+        let tracker = config.tracker();
+        let conditions = config.conditions;
+        match self.0 {
+            Some(input) => {
+                world.insert_resource(input);
+            }
+            None => {
+                world.init_resource::<S>();
+            }
+        }
+        world.insert_resource(tracker);
+        world.init_resource::<StatusTransitions<S>>();
+        if conditions > 0 {
+            world.insert_resource(ComputeShaderGate::<S>::new(conditions));
+        }
+        world.insert_resource(State::new(ComputeNodeState::<S>::default()));
+        world.init_resource::<NextState<ComputeNodeState<S>>>();
+    }
+}
+impl<S: ComputeShader> RestartComputeShader<S> {
+    /// Recreate the render world resources once the main world ones are back.
+    fn restore_render_world(world: &mut World) {
+        if world.contains_resource::<ComputeNode<S>>() {
+            return;
+        }
+        let main_world = world.resource::<MainWorld>();
+        if !main_world.contains_resource::<ReadbackTracker<S>>() {
+            return;
+        }
+        let mut node = main_world.resource::<ComputeShaderConfig<S>>().node();
+        if let Some(limit) = world.get_resource::<ReadbackLimitOverride<S>>() {
+            node.limit = limit.limit.unwrap_or(node.plugin_limit);
+        }
+        world.insert_resource(node);
+        world.init_resource::<ComputePipeline<S>>();
+        world.init_resource::<ComputeNodeState<S>>();
+        world.init_resource::<ComputeProgress<S>>();
+        world.init_resource::<ReadbackEntities<S>>();
+        world.init_resource::<TileCursor<S>>();
+    }
+}
+
+/// Command to tear down a compute shader at runtime, removing its resources, entities and
+/// state in both worlds. Readbacks in flight are dropped.
+pub struct RemoveComputeShader<S: ComputeShader>(PhantomData<S>);
+impl<S: ComputeShader> Default for RemoveComputeShader<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: ComputeShader> Command for RemoveComputeShader<S> {
+    type Out = ();
+    fn apply(self, world: &mut World) {
+        let entities = world
+            .query_filtered::<(Entity, Has<ComputeReadbackTarget<S>>), With<ComputeShaderReadback<S>>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for (entity, is_target) in entities {
+            if is_target {
+                world
+                    .entity_mut(entity)
+                    .remove::<(ComputeShaderReadback<S>, Readback, ReadbackRange)>();
+            } else {
+                world.despawn(entity);
+            }
+        }
+        if let Some(infos) = world.get_resource::<ReadbackInfos<S>>() {
+            infos.clear();
+        }
+        world.remove_resource::<ComputeReadbackEntities<S>>();
+        world.remove_resource::<S>();
+        world.remove_resource::<ReadbackTracker<S>>();
+        world.remove_resource::<StatusTransitions<S>>();
+        world.remove_resource::<ComputeError<S>>();
+        world.remove_resource::<ComputeShaderGate<S>>();
+        world.remove_resource::<NextState<ComputeNodeState<S>>>();
+        world.remove_resource::<State<ComputeNodeState<S>>>();
+    }
+}
+impl<S: ComputeShader> RemoveComputeShader<S> {
+    /// Remove the render world resources once the main world ones are gone.
+    fn remove_render_world(world: &mut World) {
+        if !world.contains_resource::<ComputeNode<S>>()
+            || world
+                .resource::<MainWorld>()
+                .contains_resource::<ReadbackTracker<S>>()
+        {
+            return;
+        }
+        world.remove_resource::<S>();
+        world.remove_resource::<ComputeShaderGate<S>>();
+        world.remove_resource::<ComputePipeline<S>>();
+        world.remove_resource::<ComputeShaderBindGroup<S>>();
+        world.remove_resource::<InputSnapshot<S>>();
+        world.remove_resource::<ComputeNodeState<S>>();
+        world.remove_resource::<ComputeProgress<S>>();
+        world.remove_resource::<ReadbackEntities<S>>();
+        world.remove_resource::<TileCursor<S>>();
+        world.remove_resource::<ComputeNode<S>>();
+    }
+}
+
+/// Command to complete the current run from the main world, e.g. once a readback shows the
+/// result converged. The render world sees it on the next extraction.
+pub struct CompleteComputeShader<S: ComputeShader>(PhantomData<S>);
+impl<S: ComputeShader> Default for CompleteComputeShader<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: ComputeShader> Command for CompleteComputeShader<S> {
+    type Out = ();
+    fn apply(self, world: &mut World) {
+        let ready = world
+            .get_resource::<State<ComputeNodeState<S>>>()
+            .is_some_and(|state| {
+                matches!(
+                    state.get().status,
+                    ComputeNodeStatus::Ready | ComputeNodeStatus::Paused
+                )
+            });
+        if let (true, Some(mut tracker)) = (ready, world.get_resource_mut::<ReadbackTracker<S>>()) {
+            tracker.completion_requests += 1;
+        }
+    }
+}
+
+/// Command to dispatch a shader with [`ReadbackLimit::Manual`] once, on the next frame it's
+/// [`ComputeNodeStatus::Ready`]. Requests made before that dispatch coalesce into one.
+pub struct DispatchComputeShader<S: ComputeShader>(PhantomData<S>);
+impl<S: ComputeShader> Default for DispatchComputeShader<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: ComputeShader> Command for DispatchComputeShader<S> {
+    type Out = ();
+    fn apply(self, world: &mut World) {
+        if let Some(mut tracker) = world.get_resource_mut::<ReadbackTracker<S>>() {
+            tracker.dispatch_requests += 1;
+        }
+    }
+}
+
+/// Command to recover a shader from [`ComputeNodeStatus::Error`] by reloading its asset
+/// and queueing the pipelines again. Does nothing in other statuses.
+pub struct RetryComputeShader<S: ComputeShader>(PhantomData<S>);
+impl<S: ComputeShader> Default for RetryComputeShader<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: ComputeShader> Command for RetryComputeShader<S> {
+    type Out = ();
+    fn apply(self, world: &mut World) {
+        let failed = world
+            .get_resource::<State<ComputeNodeState<S>>>()
+            .is_some_and(|state| state.get().status == ComputeNodeStatus::Error);
+        if !failed {
+            return;
+        }
+        if let (ShaderRef::Path(path), Some(asset_server)) =
+            (S::compute_shader(), world.get_resource::<AssetServer>())
+        {
+            asset_server.reload(path);
+        }
+        if let Some(mut tracker) = world.get_resource_mut::<ReadbackTracker<S>>() {
+            tracker.retry_requests += 1;
+        }
+    }
+}
+
+/// Another compute shader that a shader depends on. See [`ComputeShaderPlugin::depends_on`].
+#[derive(Clone, Copy)]
+pub struct ComputeDependency {
+    set: InternedSystemSet,
+    /// Generation and dispatch count of the dependency's current run.
+    pub(crate) progress: fn(&World) -> Option<(usize, usize)>,
+}
+impl ComputeDependency {
+    /// Depend on the compute shader `T`.
+    pub fn on<T: ComputeShader>() -> Self {
+        Self {
+            set: ComputeNodeLabel::<T>::default().intern(),
+            progress: |world| {
+                world
+                    .get_resource::<ComputeNode<T>>()
+                    .map(|node| (node.generation, node.count))
+            },
+        }
+    }
+}
+
+/// Run condition that holds while any window is focused, or when there are no windows.
+pub fn any_window_focused(windows: Query<&Window>) -> bool {
+    windows.is_empty() || windows.iter().any(|window| window.focused)
+}
+
+/// Run condition that holds while `Time<Virtual>` isn't paused.
+pub fn virtual_time_running(time: Option<Res<Time<Virtual>>>) -> bool {
+    time.is_none_or(|time| !time.is_paused())
+}
+
+/// Adds a [`ComputeShaderPlugin::run_if`] condition to the app.
+type AddCondition = Arc<dyn Fn(&mut App) + Send + Sync>;
+
+/// Run conditions added by [`ComputeShaderPlugin::run_if`], added to the app on build.
+#[derive(Default, Clone)]
+pub(crate) struct RunCondition(Vec<AddCondition>);
+
+/// Pauses dispatching while `paused` is set, keeping the current run and its progress.
+/// The status is [`ComputeNodeStatus::Paused`] meanwhile. Extracted to the render world.
+#[derive(Resource, ExtractResource, Clone)]
+pub struct ComputePaused<S: ComputeShader> {
+    pub paused: bool,
+    /// Number of [`ComputePaused::step`] requests, each dispatching one frame while paused.
+    pub(crate) steps: usize,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputePaused<S> {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            steps: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ComputePaused<S> {
+    /// Stop dispatching.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /// Resume dispatching.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    /// Dispatch a single frame while paused, e.g. to step through a simulation.
+    /// Steps add up, dispatching one per frame.
+    pub fn step(&mut self) {
+        self.steps += 1;
+    }
+}
+
+/// Replaces [`ComputeShaderPlugin::limit`] at runtime, e.g. from a debug UI.
+/// `None` restores the plugin's limit. Extracted to the render world.
+#[derive(Resource, ExtractResource, Clone)]
+pub struct ReadbackLimitOverride<S: ComputeShader> {
+    pub limit: Option<ReadbackLimit>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ReadbackLimitOverride<S> {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+impl<S: ComputeShader> ReadbackLimitOverride<S> {
+    /// Override with `limit`, or restore the plugin's limit with `None`.
+    pub fn new(limit: Option<ReadbackLimit>) -> Self {
+        Self {
+            limit,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Whether the plugin's run condition held this frame. Extracted to the render world.
+#[derive(Resource, ExtractResource, Clone)]
+pub(crate) struct ComputeShaderGate<S: ComputeShader> {
+    pub(crate) enabled: bool,
+    /// Number of run conditions, which must all hold.
+    conditions: usize,
+    /// Number of conditions that held this frame, until committed at the end of the frame.
+    held: usize,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ComputeShaderGate<S> {
+    fn new(conditions: usize) -> Self {
+        Self {
+            enabled: true,
+            conditions,
+            held: 0,
+            _marker: PhantomData,
+        }
+    }
+    /// Runs only when its condition holds.
+    fn request(mut gate: ResMut<Self>) {
+        gate.bypass_change_detection().held += 1;
+    }
+    /// Update `enabled`, only marking the gate changed when it flips.
+    fn commit(mut gate: ResMut<Self>) {
+        let held = std::mem::take(&mut gate.bypass_change_detection().held);
+        let enabled = held == gate.conditions;
+        if gate.enabled != enabled {
+            gate.enabled = enabled;
+        }
+    }
+    /// Whether dispatching is enabled in the render world.
+    fn is_enabled(gate: Option<Res<Self>>) -> bool {
+        gate.is_none_or(|gate| gate.enabled)
+    }
+}
+impl<S: ComputeShader> Plugin for ComputeShaderPlugin<S> {
+    fn build(&self, app: &mut App) {
+        if self.readback_events {
+            app.add_message::<ReadbackEvent<S>>();
+        }
+        if self.latest_readback {
+            app.init_resource::<LatestReadback<S>>();
+        }
+        if self.readback_history > 0 {
+            app.insert_resource(ReadbackHistory::<S>::new(self.readback_history));
+        }
+        if self.readback_queue > 0 {
+            app.insert_resource(ReadbackQueue::<S>::new(self.readback_queue));
+        }
+        if let Some(dedupe) = &self.dedupe {
+            app.insert_resource(dedupe.clone());
+        }
+        if let Some(sender) = &self.channel {
+            app.insert_resource(ReadbackChannel::<S>::new(sender.clone()));
+        }
+        if let Some(size) = self.readback_downsample {
+            app.insert_resource(ReadbackDownsample::<S>::new(size))
+                .add_plugins(ExtractResourcePlugin::<ReadbackDownsample<S>>::default());
+            if !app.is_plugin_added::<DownsamplePlugin>() {
+                app.add_plugins(DownsamplePlugin);
+            }
+        }
+        if !self.custom_extraction {
+            app.add_plugins(ExtractResourcePlugin::<S>::default());
+        }
+        let add_conditions = &self.run_condition.0;
+        let config = ComputeShaderConfig::<S> {
+            limit: self.limit,
+            readback_interval: self.readback_interval.max(1),
+            reset_limit_on_change: self.reset_limit_on_change,
+            warmup: self.warmup,
+            dispatch_interval: self.dispatch_interval.max(1),
+            dispatch_rate: self.dispatch_rate.filter(DispatchRate::is_valid),
+            synchronized: self.synchronized,
+            max_in_flight_readbacks: self.max_in_flight_readbacks,
+            dependencies: self.dependencies.clone(),
+            remove_on_complete: self.remove_on_complete,
+            conditions: add_conditions.len(),
+            _marker: PhantomData,
+        };
+        if !add_conditions.is_empty() {
+            app.insert_resource(ComputeShaderGate::<S>::new(add_conditions.len()))
+                .add_plugins(ExtractResourcePlugin::<ComputeShaderGate<S>>::default())
+                .add_systems(
+                    Last,
+                    ComputeShaderGate::<S>::commit.run_if(resource_exists::<ComputeShaderGate<S>>),
+                );
+            for add_condition in add_conditions {
+                add_condition(app);
+            }
+        }
+        match &self.resource {
+            Some(resource) => {
+                app.insert_resource(resource.clone());
+            }
+            None if !self.defer_resource => {
+                app.init_resource::<S>();
+            }
+            None => {}
+        }
+        // Shared by all shaders, so only the first plugin extracts it.
+        if !app.is_plugin_added::<ExtractResourcePlugin<ComputeReadbackSettings>>() {
+            app.add_plugins(ExtractResourcePlugin::<ComputeReadbackSettings>::default());
+        }
+        app.init_state::<ComputeNodeState<S>>()
+            .insert_resource(config.tracker())
+            .insert_resource(config)
+            .init_resource::<StatusTransitions<S>>()
+            .add_message::<ComputeStatusChanged<S>>()
+            .init_resource::<ComputeProgress<S>>()
+            .init_resource::<ReadbackInfos<S>>()
+            .init_resource::<ReadbackLatencyStats<S>>()
+            .init_resource::<ReadbackBindings<S>>()
+            .init_resource::<ComputePaused<S>>()
+            .add_plugins(ExtractResourcePlugin::<ComputePaused<S>>::default())
+            .init_resource::<ReadbackLimitOverride<S>>()
+            .add_plugins(ExtractResourcePlugin::<ReadbackLimitOverride<S>>::default())
+            .add_plugins(ExtractResourcePlugin::<ReadbackRegion<S>>::default())
+            .add_plugins(ExtractResourcePlugin::<ReadbackTiles<S>>::default())
+            .init_resource::<TileCoverage<S>>()
+            .add_message::<ReadbackTile<S>>()
+            .add_message::<ReadbackTilesCovered<S>>()
+            .init_resource::<ComputeReadbackSettings>()
+            .add_observer(ComputeReadbackTarget::<S>::on_add)
+            .add_observer(ComputeReadbackTarget::<S>::on_remove)
+            // Not in `Startup`, so plugins added to a running app still spawn their observers.
+            .add_systems(
+                First,
+                ComputeShaderReadback::<S>::spawn
+                    .run_if(resource_exists::<S>)
+                    .run_if(not(any_with_component::<ComputeShaderReadback<S>>)),
+            )
+            .add_systems(
+                self.main_schedule,
+                (
+                    StatusTransitions::<S>::on_status_change,
+                    ReadbackTracker::<S>::complete_when_delivered,
+                )
+                    .run_if(resource_exists::<ReadbackTracker<S>>),
+            )
+            .add_systems(
+                self.main_schedule,
+                ImageReadback::<S>::retry.run_if(resource_exists::<ImageReadback<S>>),
+            )
+            .add_systems(
+                self.main_schedule,
+                ComputeShaderReadback::<S>::update_ranges
+                    .run_if(resource_exists_and_changed::<S>)
+                    .after(StatusTransitions::<S>::on_status_change),
+            );
+        if !S::readback_bindings().is_empty() {
+            app.add_systems(
+                self.main_schedule,
+                ReadbackBindings::<S>::update.after(StatusTransitions::<S>::on_status_change),
+            );
+        }
+        // Plugins added after the app finished building don't get `finish` called.
+        if matches!(
+            app.plugins_state(),
+            PluginsState::Finished | PluginsState::Cleaned
+        ) {
+            self.finish(app);
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Add the compute shader resources and systems to the render app.
+        let node = app.world().resource::<ComputeShaderConfig<S>>().node();
+        let infos = app.world().resource::<ReadbackInfos<S>>().clone();
+        let readback_inputs = self.readback_inputs;
+        let bindings = app.world().resource::<ReadbackBindings<S>>().clone();
+        let render_app = app.sub_app_mut(RenderApp);
+        if !S::readback_bindings().is_empty() {
+            render_app.insert_resource(bindings).add_systems(
+                Render,
+                ReadbackBindings::<S>::resolve
+                    .in_set(RenderSystems::PrepareBindGroups)
+                    .after(ComputeShaderBindGroup::<S>::prepare_extra_bind_groups)
+                    .run_if(resource_exists_and_changed::<ComputeShaderBindGroup<S>>),
+            );
+        }
+        if !S::view_textures().is_empty() {
+            render_app
+                .init_resource::<ComputeViewBindGroup<S>>()
+                .add_systems(ExtractSchedule, ComputeViewBindGroup::<S>::extract)
+                .add_systems(
+                    Render,
+                    ComputeViewBindGroup::<S>::prepare
+                        .in_set(RenderSystems::PrepareBindGroups)
+                        .run_if(resource_exists::<ComputePipeline<S>>),
+                );
+        }
+        render_app
+            .insert_resource(infos)
+            .init_resource::<ComputePipeline<S>>()
+            .init_resource::<ComputeNodeState<S>>()
+            .init_resource::<ComputeProgress<S>>()
+            .init_resource::<ReadbackEntities<S>>()
+            .init_resource::<TileCursor<S>>()
+            .init_resource::<UniformsWritten<S>>()
+            .init_resource::<ReadbackBudget>()
+            .insert_resource(node)
+            .add_systems(
+                ExtractSchedule,
+                ComputeNode::<S>::reset_on_change
+                    .run_if(resource_exists_and_changed::<S>)
+                    .after(extract_resource::<S, _>),
+            )
+            .add_systems(
+                ExtractSchedule,
+                ComputeNode::<S>::apply_limit_override
+                    .run_if(resource_exists::<ComputeNode<S>>)
+                    .run_if(resource_exists_and_changed::<ReadbackLimitOverride<S>>)
+                    .after(extract_resource::<ReadbackLimitOverride<S>, _>)
+                    .after(RestartComputeShader::<S>::restore_render_world),
+            )
+            .add_systems(
+                ExtractSchedule,
+                ComputeNode::<S>::validate_dispatch
+                    .run_if(resource_exists_and_changed::<S>)
+                    .after(extract_resource::<S, _>),
+            )
+            .add_systems(
+                ExtractSchedule,
+                InputSnapshot::<S>::update
+                    .run_if(move || readback_inputs)
+                    .run_if(resource_exists_and_changed::<S>)
+                    .after(extract_resource::<S, _>),
+            )
+            .add_systems(
+                ExtractSchedule,
+                (
+                    RemoveComputeShader::<S>::remove_render_world,
+                    RestartComputeShader::<S>::restore_render_world,
+                )
+                    .chain()
+                    .before(extract_resource::<S, _>),
+            )
+            .add_systems(
+                ExtractSchedule,
+                ComputeNodeState::<S>::extract_to_main
+                    .run_if(resource_exists_and_changed::<ComputeNodeState<S>>)
+                    .after(RemoveComputeShader::<S>::remove_render_world)
+                    .after(ComputeNode::<S>::reset_on_change),
+            )
+            .add_systems(
+                ExtractSchedule,
+                ComputeProgress::<S>::extract_to_main
+                    .run_if(resource_exists_and_changed::<ComputeProgress<S>>)
+                    .after(RemoveComputeShader::<S>::remove_render_world),
+            )
+            .add_systems(
+                ExtractSchedule,
+                (
+                    ReadbackEntities::<S>::extract,
+                    ReadbackTracker::<S>::extract_requested,
+                )
+                    .run_if(resource_exists::<ComputeNode<S>>)
+                    .after(RemoveComputeShader::<S>::remove_render_world)
+                    .after(ComputeNode::<S>::reset_on_change),
+            )
+            .add_systems(
+                ExtractSchedule,
+                ComputePipeline::<S>::retry
+                    .run_if(resource_exists::<ComputeNode<S>>)
+                    .after(ReadbackTracker::<S>::extract_requested),
+            )
+            .add_systems(
+                Render,
+                ComputeNode::<S>::gate_readbacks
+                    .in_set(RenderSystems::PrepareViews)
+                    .run_if(resource_exists::<ComputeNode<S>>),
+            );
+        render_app.edit_schedule(Render, Self::add_bind_group_systems);
+        if self.pause_with_virtual_time {
+            render_app.add_systems(
+                ExtractSchedule,
+                ComputeNode::<S>::extract_time_scale.run_if(resource_exists::<ComputeNode<S>>),
+            );
+        }
+        render_app.edit_schedule(self.render_schedule, |schedule| {
+            self.add_node_systems(schedule);
+        });
+    }
+}
+impl<S: ComputeShader> ComputeShaderPlugin<S> {
+    /// Add the systems preparing the bind groups to the `Render` schedule.
+    fn add_bind_group_systems(schedule: &mut Schedule) {
+        schedule.add_systems(
+            (
+                ComputeShaderBindGroup::<S>::prepare_uniforms.run_if(|| !S::manual_bind_group()),
+                S::prepare_bind_group.run_if(
+                    (|| !S::manual_bind_group()).and_then(not(UniformsWritten::<S>::is_written)),
+                ),
+                ComputeShaderBindGroup::<S>::prepare_manual.run_if(S::manual_bind_group),
+                ComputeShaderBindGroup::<S>::prepare_extra_bind_groups,
+            )
+                .chain()
+                .in_set(RenderSystems::PrepareBindGroups)
+                // Checked first, so changes made while disabled are seen once enabled.
+                .run_if(
+                    resource_exists::<S>
+                        .and_then(ComputeShaderGate::<S>::is_enabled)
+                        .and_then(ComputePipeline::<S>::uses_bind_group)
+                        .and_then(
+                            not(resource_exists::<ComputeShaderBindGroup<S>>)
+                                .or_else(resource_changed::<S>),
+                        ),
+                ),
+        );
+    }
+    /// Add the systems updating and dispatching the node to the render schedule, in the
+    /// `render_set` and ordered around the `before`, `after` and dependency sets.
+    fn add_node_systems(&self, schedule: &mut Schedule) {
+        let node_systems = (
+            ComputeNode::<S>::check_dependencies,
+            ComputeNode::<S>::update,
+            ComputeNode::<S>::run,
+        )
+            .chain()
+            .in_set(ComputeNodeLabel::<S>::default())
+            .run_if(resource_exists::<ComputeNode<S>>)
+            // Stays loading until a deferred input is inserted.
+            .run_if(resource_exists::<S>)
+            .run_if(ComputeNode::<S>::once_per_frame);
+        for set in &self.before {
+            schedule.configure_sets(ComputeNodeLabel::<S>::default().before(*set));
+        }
+        let dependency_sets = self.dependencies.iter().map(|dependency| &dependency.set);
+        for set in self.after.iter().chain(dependency_sets) {
+            schedule.configure_sets(ComputeNodeLabel::<S>::default().after(*set));
+        }
+        match self.render_set {
+            Some(set) => schedule.add_systems(node_systems.in_set(set)),
+            None => schedule.add_systems(node_systems),
+        };
+    }
+}
+
+/// How many readbacks should be sent per initialization of the shader.
+#[derive(Default, Debug, Copy, Clone)]
+pub enum ReadbackLimit {
+    /// No limit, readback will continue indefinitely.
+    #[default]
+    Infinite,
+    /// Finite readback limit, measured in number of frames.
+    Finite(usize),
+    /// Keep dispatching for this long after the first dispatch, regardless of frame rate.
+    /// Pipeline compilation doesn't count, so a run always gets at least one dispatch.
+    /// The final state is read back on the frame the run completes.
+    Duration(Duration),
+    /// Finite limit measured in readbacks delivered to the main world, per readback target.
+    /// Readbacks not delivered within a second or so are requested again.
+    FiniteReadbacks(usize),
+    /// Only dispatch when requested with [`DispatchComputeShader`], reading back every dispatch.
+    /// The node stays [`ComputeNodeStatus::Ready`] while idle.
+    Manual,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        diagnostic::FrameCount,
+        ecs::{system::SystemParamItem, world::FromWorld},
+        render::{
+            MainWorld,
+            render_resource::{
+                AsBindGroup, AsBindGroupError, BindGroupLayout, BindGroupLayoutEntries,
+                BindGroupLayoutEntry, BindingResources, Buffer, BufferDescriptor, BufferUsages,
+                OwnedBindingResource, OwnedData, ShaderStages, UnpreparedBindGroup,
+                binding_types::{storage_buffer, uniform_buffer},
+            },
+            renderer::RenderDevice,
+        },
+        state::app::StatesPlugin,
+    };
+
+    use super::*;
+    use crate::{
+        node::tests::frame,
+        pipeline::tests::{empty_pipeline, noop_render_world},
+        shader::tests::TestComputeShader,
+    };
+
+    /// Field type without `Debug`, which shaders don't need to implement.
+    #[derive(Clone, Default)]
+    struct NotDebug;
+
+    #[derive(AsBindGroup, Resource, ExtractResource, Clone, Default)]
+    struct NotDebugComputeShader {
+        #[uniform(0)]
+        value: u32,
+        _field: NotDebug,
+    }
+    impl ComputeShader for NotDebugComputeShader {
+        fn compute_shader() -> ShaderRef {
+            "test.wgsl".into()
+        }
+    }
+
+    #[test]
+    fn plugin_builds_without_debug() {
+        let mut app = App::new();
+        app.add_plugins((
+            StatesPlugin,
+            ComputeShaderPlugin::<NotDebugComputeShader>::new(),
+        ));
+        let state = app
+            .world()
+            .resource::<State<ComputeNodeState<NotDebugComputeShader>>>();
+        assert!(format!("{state:?}").contains("Loading"));
+    }
+
+    #[test]
+    fn restart_after_remove_on_complete() {
+        type Shader = TestComputeShader;
+        let mut app = App::new();
+        app.add_plugins((
+            StatesPlugin,
+            ComputeShaderPlugin::<Shader>::new()
+                .with_limit(ReadbackLimit::Finite(1))
+                .with_remove_on_complete(true),
+        ));
+        // Render world, seeing the main world as during extraction.
+        let mut render = World::new();
+        render.init_resource::<FrameCount>();
+        render.init_resource::<MainWorld>();
+        std::mem::swap(&mut **render.resource_mut::<MainWorld>(), app.world_mut());
+        let entity = render.spawn_empty().id();
+        let run = |render: &mut World| {
+            render.insert_resource(empty_pipeline::<Shader>());
+            RestartComputeShader::<Shader>::restore_render_world(render);
+            // The pipeline is ready.
+            render.resource_mut::<ComputeNode<Shader>>().status = ComputeNodeStatus::Ready;
+            frame(render, entity);
+            render.resource::<ComputeNode<Shader>>().requested
+        };
+        assert_eq!(run(&mut render), 1);
+
+        // What `remove_on_complete` queues once the final readback is delivered.
+        RemoveComputeShader::<Shader>::default().apply(&mut render.resource_mut::<MainWorld>());
+        RemoveComputeShader::<Shader>::remove_render_world(&mut render);
+        assert!(!render.contains_resource::<ComputeNode<Shader>>());
+
+        RestartComputeShader(Some(Shader { value: 1 }))
+            .apply(&mut render.resource_mut::<MainWorld>());
+        assert_eq!(run(&mut render), 1);
+        assert!(render.get::<Readback>(entity).is_some());
+        let main_world = render.resource::<MainWorld>();
+        assert_eq!(main_world.resource::<Shader>().value, 1);
+        assert_eq!(
+            main_world
+                .resource::<State<ComputeNodeState<Shader>>>()
+                .get()
+                .status,
+            ComputeNodeStatus::Loading
+        );
+    }
+
+    /// Plain data at binding 0 and a buffer at binding 1, bound like `#[data(0, u32)]` and
+    /// `#[storage(1, buffer)]`, without the render assets the derive takes as its param.
+    #[derive(Resource, ExtractResource, Clone)]
+    struct DataComputeShader {
+        value: u32,
+        buffer: Buffer,
+    }
+    impl AsBindGroup for DataComputeShader {
+        type Data = ();
+        type Param = ();
+
+        fn label() -> &'static str {
+            "data_compute_shader"
+        }
+
+        fn unprepared_bind_group(
+            &self,
+            _layout: &BindGroupLayout,
+            _render_device: &RenderDevice,
+            _param: &mut SystemParamItem<'_, '_, Self::Param>,
+            _force_no_bindless: bool,
+        ) -> Result<UnpreparedBindGroup, AsBindGroupError> {
+            let data = OwnedData(self.value.to_le_bytes().to_vec());
+            Ok(UnpreparedBindGroup {
+                bindings: BindingResources(vec![
+                    (0, OwnedBindingResource::Data(data)),
+                    (1, OwnedBindingResource::Buffer(self.buffer.clone())),
+                ]),
+            })
+        }
+
+        fn bind_group_layout_entries(
+            _render_device: &RenderDevice,
+            _force_no_bindless: bool,
+        ) -> Vec<BindGroupLayoutEntry> {
+            BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (uniform_buffer::<u32>(false), storage_buffer::<u32>(false)),
+            )
+            .to_vec()
+        }
+
+        fn bind_group_data(&self) -> Self::Data {}
+    }
+    impl ComputeShader for DataComputeShader {
+        fn compute_shader() -> ShaderRef {
+            "test.wgsl".into()
+        }
+    }
+    impl FromWorld for DataComputeShader {
+        fn from_world(world: &mut World) -> Self {
+            Self {
+                value: 0,
+                buffer: world
+                    .resource::<RenderDevice>()
+                    .create_buffer(&BufferDescriptor {
+                        label: None,
+                        size: 4,
+                        usage: BufferUsages::STORAGE,
+                        mapped_at_creation: false,
+                    }),
+            }
+        }
+    }
+
+    #[test]
+    fn data_change_keeps_bind_group() {
+        type Shader = DataComputeShader;
+        let mut world = noop_render_world();
+        let mut pipeline = empty_pipeline::<Shader>();
+        pipeline.layout = Shader::bind_group_layout_descriptor(world.resource::<RenderDevice>());
+        world.insert_resource(pipeline);
+        world.init_resource::<UniformsWritten<Shader>>();
+        world.init_resource::<Shader>();
+        let mut schedule = Schedule::default();
+        ComputeShaderPlugin::<Shader>::add_bind_group_systems(&mut schedule);
+        let mut bind_group_id = |world: &mut World| {
+            schedule.run(world);
+            world
+                .resource::<ComputeShaderBindGroup<Shader>>()
+                .bind_group
+                .id()
+        };
+        let id = bind_group_id(&mut world);
+
+        // Only the data changed, so it's written into the same bind group.
+        world.resource_mut::<Shader>().value = 1;
+        assert_eq!(bind_group_id(&mut world), id);
+
+        // A new buffer needs a new bind group.
+        let buffer = Shader::from_world(&mut world).buffer;
+        world.resource_mut::<Shader>().buffer = buffer;
+        assert_ne!(bind_group_id(&mut world), id);
+    }
+
+    /// Render schedule sets and a record of the order their systems ran in.
+    #[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+    enum Pass {
+        Prepass,
+        Post,
+    }
+    #[derive(Resource, Default)]
+    struct Order(Vec<&'static str>);
+
+    #[test]
+    fn node_runs_between_ordered_sets() {
+        let record = |name| move |mut order: ResMut<Order>| order.0.push(name);
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            record("prepass").in_set(Pass::Prepass),
+            record("node").in_set(ComputeNodeLabel::<TestComputeShader>::default()),
+            record("post").in_set(Pass::Post),
+        ));
+        ComputeShaderPlugin::<TestComputeShader>::new()
+            .after_set(Pass::Prepass)
+            .before_set(Pass::Post)
+            .add_node_systems(&mut schedule);
+        let mut world = World::new();
+        world.init_resource::<Order>();
+        world.init_resource::<FrameCount>();
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<Order>().0, ["prepass", "node", "post"]);
+    }
+
+    fn gate_enabled(plugin: &ComputeShaderPlugin<TestComputeShader>) -> bool {
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin);
+        plugin.build(&mut app);
+        app.world_mut().run_schedule(Last);
+        app.world()
+            .resource::<ComputeShaderGate<TestComputeShader>>()
+            .enabled
+    }
+
+    #[test]
+    fn invalid_dispatch_rates_are_ignored() {
+        for (hz, valid) in [
+            (60.0, true),
+            (0.0, false),
+            (-1.0, false),
+            (f64::NAN, false),
+            (f64::INFINITY, false),
+        ] {
+            let mut app = App::new();
+            app.add_plugins((
+                StatesPlugin,
+                ComputeShaderPlugin::<TestComputeShader>::new()
+                    .with_dispatch_rate(DispatchRate::hz(hz)),
+            ));
+            let config = app
+                .world()
+                .resource::<ComputeShaderConfig<TestComputeShader>>();
+            assert_eq!(config.dispatch_rate.is_some(), valid, "{hz} Hz");
+        }
+    }
+
+    #[test]
+    fn run_conditions_combine_across_builds() {
+        let plugin = ComputeShaderPlugin::<TestComputeShader>::new()
+            .run_if(|| true)
+            .suspend_when_unfocused();
+        // Building again adds the same conditions, e.g. for a second app.
+        assert!(gate_enabled(&plugin));
+        assert!(gate_enabled(&plugin));
+        let plugin = plugin.run_if(|| false);
+        assert!(!gate_enabled(&plugin));
+        assert!(!gate_enabled(&plugin));
+    }
+}
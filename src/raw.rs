@@ -0,0 +1,253 @@
+//! One-off readbacks of arbitrary GPU buffers.
+
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    app::{App, First, Plugin},
+    ecs::{
+        message::{Message, Messages},
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Res, ResMut},
+    },
+    log::error,
+    render::{
+        Render, RenderApp, RenderSystems,
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, COPY_BUFFER_ALIGNMENT,
+            CommandEncoderDescriptor, MapMode,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+
+use crate::ReadbackData;
+
+/// Reads back buffers created directly in the render world, which have no
+/// [`ShaderBuffer`] asset to give to `Readback`.
+///
+/// [`ShaderBuffer`]: bevy::render::storage::ShaderBuffer
+pub struct RawReadbackPlugin;
+impl Plugin for RawReadbackPlugin {
+    fn build(&self, app: &mut App) {
+        let results = RawReadbackResults::default();
+        app.add_message::<RawReadbackComplete>()
+            .insert_resource(results.clone())
+            .add_systems(First, RawReadbackResults::deliver);
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(results)
+                .init_resource::<RawReadbacks>()
+                .add_systems(Render, RawReadbacks::submit.in_set(RenderSystems::Cleanup));
+        }
+    }
+}
+
+/// A buffer requested with [`RawReadbacks::push`].
+struct RawReadbackRequest {
+    buffer: Buffer,
+    range: Range<u64>,
+    tag: Cow<'static, str>,
+}
+
+/// Render world queue of buffers to read back, delivered as [`RawReadbackComplete`] messages.
+/// Push from a [`Render`] system once the work writing the buffer is encoded.
+#[derive(Resource, Default)]
+pub struct RawReadbacks {
+    requested: Vec<RawReadbackRequest>,
+}
+impl RawReadbacks {
+    /// Read back `range` of `buffer`, or all of it, tagged to tell readbacks apart. Empty and
+    /// unaligned ranges are logged and dropped.
+    pub fn push(
+        &mut self,
+        buffer: Buffer,
+        range: Option<Range<u64>>,
+        tag: impl Into<Cow<'static, str>>,
+    ) {
+        let range = range.unwrap_or(0..buffer.size());
+        self.requested.push(RawReadbackRequest {
+            buffer,
+            range,
+            tag: tag.into(),
+        });
+    }
+    /// Number of buffers pushed this frame.
+    pub fn len(&self) -> usize {
+        self.requested.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.requested.is_empty()
+    }
+    /// Copy this frame's buffers into staging buffers and map them.
+    fn submit(
+        mut readbacks: ResMut<Self>,
+        render_device: Res<RenderDevice>,
+        render_queue: Res<RenderQueue>,
+        results: Res<RawReadbackResults>,
+    ) {
+        if readbacks.is_empty() {
+            return;
+        }
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("raw_readbacks"),
+        });
+        let mut staged = Vec::new();
+        for request in readbacks.requested.drain(..) {
+            let Range { start, end } = request.range;
+            // Empty ranges would map an empty staging buffer, which wgpu rejects.
+            if start >= end
+                || end > request.buffer.size()
+                || start % COPY_BUFFER_ALIGNMENT != 0
+                || end % COPY_BUFFER_ALIGNMENT != 0
+                || !request.buffer.usage().contains(BufferUsages::COPY_SRC)
+            {
+                error!(
+                    "raw readback {}: can't copy {start}..{end} of a {} byte buffer with usage \
+                    {:?}",
+                    request.tag,
+                    request.buffer.size(),
+                    request.buffer.usage()
+                );
+                continue;
+            }
+            let staging = render_device.create_buffer(&BufferDescriptor {
+                label: Some("raw_readback_staging"),
+                size: end - start,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_buffer_to_buffer(&request.buffer, start, &staging, 0, end - start);
+            staged.push((request, staging));
+        }
+        render_queue.submit([encoder.finish()]);
+        for (request, staging) in staged {
+            let results = results.clone();
+            let mapped = staging.clone();
+            staging.slice(..).map_async(MapMode::Read, move |result| {
+                // The request holds the source buffer until now.
+                let RawReadbackRequest { tag, .. } = request;
+                if let Err(error) = result {
+                    error!("raw readback {tag}: failed to map the staging buffer: {error}");
+                    return;
+                }
+                let data = ReadbackData::new(&mapped.slice(..).get_mapped_range());
+                mapped.unmap();
+                results
+                    .0
+                    .lock()
+                    .unwrap()
+                    .push(RawReadbackComplete { tag, data });
+            });
+        }
+    }
+}
+
+/// Readback of a [`RawReadbacks`] buffer, written in [`First`].
+#[derive(Message, Clone, Debug)]
+pub struct RawReadbackComplete {
+    /// Tag passed to [`RawReadbacks::push`].
+    pub tag: Cow<'static, str>,
+    /// Raw bytes of the buffer range.
+    pub data: ReadbackData,
+}
+
+/// Raw readbacks mapped in the render world, waiting to be delivered in the main world.
+#[derive(Resource, Clone, Default)]
+struct RawReadbackResults(Arc<Mutex<Vec<RawReadbackComplete>>>);
+impl RawReadbackResults {
+    fn deliver(results: Res<Self>, mut messages: ResMut<Messages<RawReadbackComplete>>) {
+        let results = std::mem::take(&mut *results.0.lock().unwrap());
+        if !results.is_empty() {
+            messages.write_batch(results);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{system::RunSystemOnce, world::World};
+
+    use super::*;
+    use crate::pipeline::tests::noop_render_world;
+
+    fn buffer(world: &World, usage: BufferUsages) -> Buffer {
+        world
+            .resource::<RenderDevice>()
+            .create_buffer(&BufferDescriptor {
+                label: None,
+                size: 16,
+                usage,
+                mapped_at_creation: false,
+            })
+    }
+
+    #[test]
+    fn push_defaults_to_the_whole_buffer() {
+        let world = noop_render_world();
+        let mut readbacks = RawReadbacks::default();
+        readbacks.push(buffer(&world, BufferUsages::COPY_SRC), None, "whole");
+        readbacks.push(
+            buffer(&world, BufferUsages::COPY_SRC),
+            Some(4..8),
+            String::from("range"),
+        );
+        assert_eq!(readbacks.len(), 2);
+        let requested: Vec<_> = readbacks
+            .requested
+            .iter()
+            .map(|request| (request.range.clone(), request.tag.as_ref()))
+            .collect();
+        assert_eq!(requested, [(0..16, "whole"), (4..8, "range")]);
+    }
+
+    #[test]
+    fn submit_drops_buffers_that_cant_be_copied() {
+        let mut world = noop_render_world();
+        let results = RawReadbackResults::default();
+        world.insert_resource(results.clone());
+        let mut readbacks = RawReadbacks::default();
+        let copy_src = buffer(&world, BufferUsages::COPY_SRC);
+        readbacks.push(buffer(&world, BufferUsages::STORAGE), None, "usage");
+        readbacks.push(copy_src.clone(), Some(0..32), "past the end");
+        readbacks.push(copy_src.clone(), Some(2..8), "unaligned");
+        readbacks.push(copy_src, Some(8..8), "empty");
+        world.insert_resource(readbacks);
+        world.run_system_once(RawReadbacks::submit).unwrap();
+        assert!(world.resource::<RawReadbacks>().is_empty());
+        assert!(results.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn results_are_delivered_in_order() {
+        let mut world = World::new();
+        let results = RawReadbackResults::default();
+        world.insert_resource(results.clone());
+        world.init_resource::<Messages<RawReadbackComplete>>();
+        results
+            .0
+            .lock()
+            .unwrap()
+            .extend(["a", "b"].map(|tag| RawReadbackComplete {
+                tag: tag.into(),
+                data: ReadbackData::new(tag.as_bytes()),
+            }));
+        world.run_system_once(RawReadbackResults::deliver).unwrap();
+        world.run_system_once(RawReadbackResults::deliver).unwrap();
+        let delivered: Vec<_> = world
+            .resource_mut::<Messages<RawReadbackComplete>>()
+            .drain()
+            .map(|complete| (complete.tag, complete.data.to_vec()))
+            .collect();
+        assert_eq!(
+            delivered,
+            [("a".into(), b"a".to_vec()), ("b".into(), b"b".to_vec())]
+        );
+        assert!(results.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,1847 @@
+//! Delivering readbacks to the main world.
+
+use std::{
+    any::TypeId,
+    collections::VecDeque,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+    ops::{Deref, Range},
+    sync::{
+        Arc, Mutex,
+        mpsc::{SyncSender, TrySendError},
+    },
+    time::Duration,
+};
+
+use bevy::{
+    asset::{AssetId, Assets, Handle, RenderAssetUsages},
+    camera::RenderTarget,
+    diagnostic::FrameCount,
+    ecs::{
+        bundle::Bundle,
+        component::Component,
+        entity::Entity,
+        lifecycle::{Add, Remove},
+        message::{Message, Messages},
+        observer::{Observer, On},
+        query::{Has, With},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+        world::{DeferredWorld, Mut, World},
+    },
+    image::Image,
+    log::{error_once, warn, warn_once},
+    math::UVec3,
+    platform::{collections::HashMap, hash::FixedHasher, time::Instant},
+    render::{
+        MainWorld,
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferUsages, COPY_BUFFER_ALIGNMENT, Extent3d, Origin3d, OwnedBindingResource,
+            ShaderSize, ShaderType, TexelCopyBufferInfo, TexelCopyBufferLayout,
+            TexelCopyTextureInfo, TextureAspect, TextureFormat, encase::internal::CreateFrom,
+        },
+        renderer::RenderContext,
+        storage::{GpuShaderBuffer, ShaderBuffer},
+        texture::GpuImage,
+    },
+    state::state::State,
+    tasks::{AsyncComputeTaskPool, Task, block_on},
+    utils::default,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    CompleteComputeShader, ComputeNode, ComputeNodeState, ComputeNodeStatus, ComputeShader,
+    ComputeShaderBindGroup, GpuEventOutputs, ReadbackDecodeError, ReadbackDownsample,
+    ReadbackTileInfo, ReadbackTiles, RemoveComputeShader, TextureRowLayout, decode_readback,
+    decode_single, unpad_texture_data,
+};
+
+/// Message written for each readback of a compute shader with
+/// [`ComputeShaderPlugin::readback_events`], in addition to the [`ComputeShader::on_readback`] hook.
+///
+/// [`ComputeShaderPlugin::readback_events`]: crate::ComputeShaderPlugin::readback_events
+#[derive(Message, Debug)]
+pub struct ReadbackEvent<S: ComputeShader> {
+    /// Raw bytes of the readback, shared by all readers.
+    pub data: ReadbackData,
+    /// Main world frame the readback was delivered in.
+    pub frame: u64,
+    /// Index of the readback target in [`ComputeShader::readbacks`].
+    pub index: usize,
+    /// Dispatch that produced the readback.
+    pub info: ReadbackInfo,
+    /// Input of that dispatch, with [`ComputeShaderPlugin::readback_inputs`].
+    ///
+    /// [`ComputeShaderPlugin::readback_inputs`]: crate::ComputeShaderPlugin::readback_inputs
+    pub input: Option<Arc<S>>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Clone for ReadbackEvent<S> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            frame: self.frame,
+            index: self.index,
+            info: self.info,
+            input: self.input.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ReadbackEvent<S> {
+    /// Decode the data as an array of `T`, see [`decode_readback`].
+    pub fn decode<T: ShaderType + ShaderSize + CreateFrom>(
+        &self,
+    ) -> Result<Vec<T>, ReadbackDecodeError> {
+        decode_readback(&self.data)
+    }
+    /// Decode the data as a single `T`, see [`decode_single`].
+    pub fn decode_single<T: ShaderType + ShaderSize + CreateFrom>(
+        &self,
+    ) -> Result<T, ReadbackDecodeError> {
+        decode_single(&self.data)
+    }
+}
+
+/// Most recent readback of a compute shader with [`ComputeShaderPlugin::latest_readback`],
+/// overwritten on each delivery. Use change detection to tell when new data arrived.
+///
+/// [`ComputeShaderPlugin::latest_readback`]: crate::ComputeShaderPlugin::latest_readback
+#[derive(Resource, Clone, Debug)]
+pub struct LatestReadback<S: ComputeShader> {
+    /// Raw bytes of the readback, cheap to clone.
+    pub data: ReadbackData,
+    /// Main world frame the readback was delivered in.
+    pub frame: u64,
+    /// Number of readbacks delivered so far, 0 until the first one.
+    pub sequence: u64,
+    /// Index of the readback target in [`ComputeShader::readbacks`].
+    pub index: usize,
+    /// Dispatch that produced the readback.
+    pub info: ReadbackInfo,
+    /// Input of that dispatch, with [`ComputeShaderPlugin::readback_inputs`].
+    ///
+    /// [`ComputeShaderPlugin::readback_inputs`]: crate::ComputeShaderPlugin::readback_inputs
+    pub input: Option<Arc<S>>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for LatestReadback<S> {
+    fn default() -> Self {
+        Self {
+            data: ReadbackData::default(),
+            frame: 0,
+            sequence: 0,
+            index: 0,
+            info: ReadbackInfo::default(),
+            input: None,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> LatestReadback<S> {
+    /// Decode the data as an array of `T`, see [`decode_readback`].
+    pub fn decode<T: ShaderType + ShaderSize + CreateFrom>(
+        &self,
+    ) -> Result<Vec<T>, ReadbackDecodeError> {
+        decode_readback(&self.data)
+    }
+    /// Decode the data as a single `T`, see [`decode_single`].
+    pub fn decode_single<T: ShaderType + ShaderSize + CreateFrom>(
+        &self,
+    ) -> Result<T, ReadbackDecodeError> {
+        decode_single(&self.data)
+    }
+}
+
+/// Readback data shared by every delivery of a readback: the [`ReadbackHistory`],
+/// [`ReadbackChannel`], [`ReadbackEvent`], [`LatestReadback`] and the hook, through
+/// [`ComputeShaderReadback::data_of`]. Cloning it doesn't copy the data.
+///
+/// The bytes are 16-byte aligned, so [`ReadbackBytes::as_pod_slice`] can view them as any
+/// [`Pod`] type up to `Vec4`.
+///
+/// [`ReadbackBytes::as_pod_slice`]: crate::ReadbackBytes::as_pod_slice
+#[derive(Clone, Default)]
+pub struct ReadbackData {
+    blocks: Arc<[Align16]>,
+    len: usize,
+}
+impl ReadbackData {
+    /// Copy `bytes` into a new aligned allocation.
+    pub fn new(bytes: &[u8]) -> Self {
+        let blocks = bytes
+            .chunks(size_of::<Align16>())
+            .map(|chunk| {
+                let mut block = Align16::zeroed();
+                block.0[..chunk.len()].copy_from_slice(chunk);
+                block
+            })
+            .collect();
+        Self {
+            blocks,
+            len: bytes.len(),
+        }
+    }
+}
+impl Deref for ReadbackData {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &bytemuck::cast_slice(&self.blocks)[..self.len]
+    }
+}
+impl AsRef<[u8]> for ReadbackData {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+impl From<&[u8]> for ReadbackData {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes)
+    }
+}
+impl PartialEq for ReadbackData {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+impl Eq for ReadbackData {}
+impl Debug for ReadbackData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+/// Unit of the [`ReadbackData`] allocations, aligned like a `vec4<f32>`.
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C, align(16))]
+struct Align16([u8; 16]);
+
+/// Copy the readback data into a `Vec`, e.g. for `Image::data`.
+pub fn readback_into_vec(data: ReadbackData) -> Vec<u8> {
+    data.to_vec()
+}
+
+/// Readback kept in the [`ReadbackHistory`].
+#[derive(Clone, Debug)]
+pub struct ReadbackRecord {
+    /// Raw bytes of the readback, shared with the other deliveries.
+    pub data: ReadbackData,
+    /// Main world frame the readback was delivered in.
+    pub frame: u64,
+    /// Index of the readback target in [`ComputeShader::readbacks`].
+    pub index: usize,
+    /// Dispatch that produced the readback.
+    pub info: ReadbackInfo,
+}
+
+/// The last readbacks of `S`, oldest first, at most `capacity` of them.
+/// See [`ComputeShaderPlugin::with_readback_history`].
+///
+/// [`ComputeShaderPlugin::with_readback_history`]: crate::ComputeShaderPlugin::with_readback_history
+#[derive(Resource, Clone, Debug)]
+pub struct ReadbackHistory<S: ComputeShader> {
+    records: VecDeque<ReadbackRecord>,
+    capacity: usize,
+    /// Clear the history when a new run starts, e.g. when the input changes. Defaults to true.
+    pub clear_on_reset: bool,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ReadbackHistory<S> {
+    /// Keep up to `capacity` readbacks.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            clear_on_reset: true,
+            _marker: PhantomData,
+        }
+    }
+    /// Set [`ReadbackHistory::clear_on_reset`].
+    pub fn with_clear_on_reset(mut self, clear_on_reset: bool) -> Self {
+        self.clear_on_reset = clear_on_reset;
+        self
+    }
+    /// Most readbacks kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Number of readbacks kept.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+    /// The most recent readback.
+    pub fn latest(&self) -> Option<&ReadbackRecord> {
+        self.records.back()
+    }
+    /// The readback `n_back` readbacks before the latest, so `get(0)` is the latest.
+    pub fn get(&self, n_back: usize) -> Option<&ReadbackRecord> {
+        self.records
+            .len()
+            .checked_sub(n_back + 1)
+            .and_then(|index| self.records.get(index))
+    }
+    /// All readbacks kept, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &ReadbackRecord> {
+        self.records.iter()
+    }
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+    fn push(&mut self, record: ReadbackRecord) {
+        if self.clear_on_reset
+            && self
+                .latest()
+                .is_some_and(|latest| latest.info.run != record.info.run)
+        {
+            self.records.clear();
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        while self.records.len() >= self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+}
+
+/// Skips delivering readbacks of `S` whose contents didn't change in the current run, e.g.
+/// once the output converges. [`ComputeShader::is_converged`] still sees every readback.
+/// See [`ComputeShaderPlugin::with_dedupe`].
+///
+/// [`ComputeShaderPlugin::with_dedupe`]: crate::ComputeShaderPlugin::with_dedupe
+#[derive(Resource, Clone, Debug)]
+pub struct ReadbackDedupe<S: ComputeShader> {
+    /// Only hash the first bytes of each readback. Defaults to all of them.
+    pub max_bytes: usize,
+    /// Only hash one out of every `stride` blocks of 64 bytes, to bound the cost for large
+    /// readbacks. Changes in the skipped blocks go unnoticed. Defaults to 1, every block.
+    pub stride: usize,
+    targets: HashMap<usize, DedupeState>,
+    _marker: PhantomData<S>,
+}
+#[derive(Clone, Copy, Debug)]
+struct DedupeState {
+    hash: u64,
+    run: usize,
+    changed_frame: u64,
+    frame: u64,
+    unchanged: usize,
+}
+impl<S: ComputeShader> Default for ReadbackDedupe<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<S: ComputeShader> ReadbackDedupe<S> {
+    const BLOCK: usize = 64;
+    /// Hash every byte of each readback.
+    pub fn new() -> Self {
+        Self {
+            max_bytes: usize::MAX,
+            stride: 1,
+            targets: HashMap::default(),
+            _marker: PhantomData,
+        }
+    }
+    /// Set [`ReadbackDedupe::max_bytes`].
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+    /// Set [`ReadbackDedupe::stride`].
+    pub fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+    /// Number of readbacks of the target at `index` skipped since its contents last changed.
+    pub fn unchanged(&self, index: usize) -> usize {
+        self.targets.get(&index).map_or(0, |state| state.unchanged)
+    }
+    /// Frames between the last change of the target at `index` and its latest readback.
+    pub fn frames_since_change(&self, index: usize) -> u64 {
+        self.targets
+            .get(&index)
+            .map_or(0, |state| state.frame - state.changed_frame)
+    }
+    /// Forget the stored hashes, so the next readbacks are delivered.
+    pub fn clear(&mut self) {
+        self.targets.clear();
+    }
+    fn hash(&self, data: &[u8]) -> u64 {
+        let data = &data[..data.len().min(self.max_bytes)];
+        let mut hasher = FixedHasher.build_hasher();
+        hasher.write_usize(data.len());
+        if self.stride <= 1 {
+            hasher.write(data);
+        } else {
+            for block in data.chunks(Self::BLOCK).step_by(self.stride) {
+                hasher.write(block);
+            }
+        }
+        hasher.finish()
+    }
+    /// Record a readback, returning whether it's unchanged and should be skipped.
+    /// A new run, e.g. after the input changed, always delivers.
+    fn is_duplicate(&mut self, index: usize, info: ReadbackInfo, frame: u64, data: &[u8]) -> bool {
+        let hash = self.hash(data);
+        if let Some(state) = self.targets.get_mut(&index)
+            && state.hash == hash
+            && state.run == info.run
+        {
+            state.frame = frame;
+            state.unchanged += 1;
+            return true;
+        }
+        self.targets.insert(
+            index,
+            DedupeState {
+                hash,
+                run: info.run,
+                changed_frame: frame,
+                frame,
+                unchanged: 0,
+            },
+        );
+        false
+    }
+}
+
+/// Readbacks of `S` waiting to be drained by a system, oldest first. When full, the oldest
+/// is dropped. See [`ComputeShaderPlugin::with_readback_queue`].
+///
+/// [`ComputeShaderPlugin::with_readback_queue`]: crate::ComputeShaderPlugin::with_readback_queue
+#[derive(Resource, Clone, Debug)]
+pub struct ReadbackQueue<S: ComputeShader> {
+    records: VecDeque<ReadbackRecord>,
+    capacity: usize,
+    /// Readbacks dropped because the queue was full.
+    pub dropped: u64,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ReadbackQueue<S> {
+    /// Hold up to `capacity` readbacks.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            dropped: 0,
+            _marker: PhantomData,
+        }
+    }
+    /// Most readbacks held.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Number of readbacks waiting.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+    /// Take the oldest readback.
+    pub fn pop(&mut self) -> Option<ReadbackRecord> {
+        self.records.pop_front()
+    }
+    /// Take every readback, oldest first.
+    pub fn drain(&mut self) -> impl DoubleEndedIterator<Item = ReadbackRecord> + '_ {
+        self.records.drain(..)
+    }
+    fn push(&mut self, record: ReadbackRecord) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        while self.records.len() >= self.capacity {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(record);
+    }
+}
+
+/// Readback forwarded to a [`ReadbackChannel`].
+#[derive(Clone, Debug)]
+pub struct ReadbackPacket {
+    /// Raw bytes of the readback, shared with the other deliveries.
+    pub data: ReadbackData,
+    /// Type of the compute shader, to tell shaders sharing a channel apart.
+    pub shader: TypeId,
+    /// [`ComputeShader::shader_label`] of the compute shader.
+    pub shader_label: &'static str,
+    /// Index of the readback target in [`ComputeShader::readbacks`].
+    pub index: usize,
+    /// Dispatch that produced the readback.
+    pub info: ReadbackInfo,
+    /// When the readback was delivered to the main world.
+    pub time: Instant,
+}
+
+/// Channel that readbacks of `S` are forwarded to, for consumers outside the ECS. Sending
+/// never blocks: readbacks are dropped while the channel is full. See
+/// [`ComputeShaderPlugin::with_channel`].
+///
+/// [`ComputeShaderPlugin::with_channel`]: crate::ComputeShaderPlugin::with_channel
+#[derive(Resource)]
+pub struct ReadbackChannel<S: ComputeShader> {
+    pub sender: SyncSender<ReadbackPacket>,
+    /// Readbacks dropped because the channel was full.
+    pub dropped: usize,
+    /// Whether the receiver was dropped, after which nothing is sent.
+    pub disconnected: bool,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ReadbackChannel<S> {
+    /// Forward readbacks to `sender`, e.g. from `std::sync::mpsc::sync_channel(capacity)`.
+    pub fn new(sender: SyncSender<ReadbackPacket>) -> Self {
+        Self {
+            sender,
+            dropped: 0,
+            disconnected: false,
+            _marker: PhantomData,
+        }
+    }
+    fn send(&mut self, packet: ReadbackPacket) {
+        match self.sender.try_send(packet) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+            Err(TrySendError::Disconnected(_)) => self.disconnected = true,
+        }
+    }
+}
+
+/// Where a readback came from, tagged when the render world requests it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadbackInfo {
+    /// Number of dispatches before the one read back in its run, counting from 0.
+    /// With [`ComputeShader::iterations_per_frame`], the last iteration of the frame is read.
+    pub dispatch: usize,
+    /// Render world frame the readback was requested in.
+    pub frame: u64,
+    /// Incremented whenever the shader restarts its run, e.g. when the input changes.
+    pub run: usize,
+    /// Byte offset and size of the buffer range read, see [`ComputeShader::readback_range`].
+    pub range: Option<(u64, u64)>,
+    /// Tile of [`ReadbackTiles`] copied for the readback.
+    pub tile: Option<ReadbackTileInfo>,
+    /// Frames and time from the dispatch to delivery, set once delivered.
+    pub latency: Option<ReadbackLatency>,
+}
+
+/// How stale a readback is, in [`ReadbackInfo::latency`]. Bevy picks up mapped readbacks
+/// while extracting the render world and they're delivered right away, so the frame and time
+/// of delivery are also when the mapping was seen to complete.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadbackLatency {
+    /// Render world frame the dispatch was encoded in, as [`ReadbackInfo::frame`].
+    pub dispatch_frame: u64,
+    /// Main world frame the readback was delivered in.
+    pub delivered_frame: u64,
+    /// Time from encoding the dispatch to delivery.
+    pub elapsed: Duration,
+}
+impl ReadbackLatency {
+    /// Frames from the dispatch to delivery, i.e. how many frames old the data is.
+    pub fn frames(&self) -> u64 {
+        self.delivered_frame.saturating_sub(self.dispatch_frame)
+    }
+}
+
+/// Running averages of the [`ReadbackLatency`] of `S` over its last readbacks, e.g. to
+/// extrapolate gameplay from results that describe the world a few frames ago.
+#[derive(Resource, Clone, Debug)]
+pub struct ReadbackLatencyStats<S: ComputeShader> {
+    samples: VecDeque<ReadbackLatency>,
+    window: usize,
+    count: u64,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ReadbackLatencyStats<S> {
+    fn default() -> Self {
+        Self::new(120)
+    }
+}
+impl<S: ComputeShader> ReadbackLatencyStats<S> {
+    /// Average over the last `window` readbacks.
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(1),
+            count: 0,
+            _marker: PhantomData,
+        }
+    }
+    /// Latency of the last readback delivered.
+    pub fn latest(&self) -> Option<ReadbackLatency> {
+        self.samples.back().copied()
+    }
+    /// Average number of frames from dispatch to delivery.
+    pub fn average_frames(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let frames = self
+            .samples
+            .iter()
+            .map(ReadbackLatency::frames)
+            .sum::<u64>();
+        frames as f64 / self.samples.len() as f64
+    }
+    /// Average time from dispatch to delivery.
+    pub fn average_elapsed(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let elapsed = self
+            .samples
+            .iter()
+            .map(|latency| latency.elapsed)
+            .sum::<Duration>();
+        elapsed / self.samples.len() as u32
+    }
+    /// Largest number of frames from dispatch to delivery.
+    pub fn max_frames(&self) -> u64 {
+        self.samples
+            .iter()
+            .map(ReadbackLatency::frames)
+            .max()
+            .unwrap_or(0)
+    }
+    /// Number of readbacks averaged, at most the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    /// Number of readbacks measured in total.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+    fn push(&mut self, latency: ReadbackLatency) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+        self.count += 1;
+    }
+}
+
+/// Buffer range requested by [`ComputeShader::readback_range`], before alignment.
+#[derive(Component, Clone, Copy)]
+pub(crate) struct ReadbackRange {
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+}
+impl ReadbackRange {
+    /// Widen the readback to copy the range, aligned to [`COPY_BUFFER_ALIGNMENT`].
+    fn apply(readback: Readback, range: Option<Range<u64>>) -> (Readback, Option<Self>) {
+        match (readback, range) {
+            (Readback::Buffer { buffer, .. }, Some(range)) => {
+                let start = range.start / COPY_BUFFER_ALIGNMENT * COPY_BUFFER_ALIGNMENT;
+                let end = range
+                    .end
+                    .max(range.start)
+                    .next_multiple_of(COPY_BUFFER_ALIGNMENT);
+                let readback = Readback::Buffer {
+                    buffer,
+                    start_offset_and_size: Some((start, (end - start).max(COPY_BUFFER_ALIGNMENT))),
+                };
+                let range = Self {
+                    offset: range.start,
+                    size: range.end.saturating_sub(range.start),
+                };
+                (readback, Some(range))
+            }
+            (readback, _) => (readback, None),
+        }
+    }
+    /// Trim the aligned copy back to the requested range.
+    fn trim(offset: u64, size: u64, data: &mut Vec<u8>) {
+        let skip = (offset % COPY_BUFFER_ALIGNMENT) as usize;
+        data.drain(..skip.min(data.len()));
+        data.truncate(size as usize);
+    }
+}
+
+/// Render world copy of the input dispatches run with, shared with the readbacks
+/// requested until it changes. See [`ComputeShaderPlugin::readback_inputs`].
+///
+/// [`ComputeShaderPlugin::readback_inputs`]: crate::ComputeShaderPlugin::readback_inputs
+#[derive(Resource)]
+pub(crate) struct InputSnapshot<S: ComputeShader> {
+    pub(crate) input: Arc<S>,
+}
+impl<S: ComputeShader> InputSnapshot<S> {
+    pub(crate) fn update(mut commands: Commands, input: Res<S>) {
+        commands.insert_resource(Self {
+            input: Arc::new(input.clone()),
+        });
+    }
+}
+
+/// Readback requested by the render world, waiting for delivery.
+struct PendingReadback<S: ComputeShader> {
+    info: ReadbackInfo,
+    input: Option<Arc<S>>,
+    requested: Instant,
+}
+
+/// Info of readbacks requested by the render world and not yet delivered, per main world
+/// receiver. Shared by both worlds, so the info is there before the readback completes.
+#[derive(Resource)]
+pub(crate) struct ReadbackInfos<S: ComputeShader> {
+    infos: Arc<Mutex<HashMap<Entity, VecDeque<PendingReadback<S>>>>>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ReadbackInfos<S> {
+    fn default() -> Self {
+        Self {
+            infos: Arc::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> Clone for ReadbackInfos<S> {
+    fn clone(&self) -> Self {
+        Self {
+            infos: self.infos.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ReadbackInfos<S> {
+    pub(crate) fn push(&self, entity: Entity, info: ReadbackInfo, input: Option<Arc<S>>) {
+        let mut infos = self.infos.lock().unwrap();
+        infos.entry(entity).or_default().push_back(PendingReadback {
+            info,
+            input,
+            requested: Instant::now(),
+        });
+    }
+    /// Take the info and input of the oldest readback in flight, as readbacks are delivered
+    /// in order, with its latency as of delivery in `frame`.
+    fn pop(&self, entity: Entity, frame: u64) -> Option<(ReadbackInfo, Option<Arc<S>>)> {
+        let mut infos = self.infos.lock().unwrap();
+        let pending = infos.get_mut(&entity).and_then(VecDeque::pop_front)?;
+        let latency = ReadbackLatency {
+            dispatch_frame: pending.info.frame,
+            delivered_frame: frame,
+            elapsed: pending.requested.elapsed(),
+        };
+        let info = ReadbackInfo {
+            latency: Some(latency),
+            ..pending.info
+        };
+        Some((info, pending.input))
+    }
+    pub(crate) fn clear(&self) {
+        self.infos.lock().unwrap().clear();
+    }
+}
+
+/// An asset bound at one of [`ComputeShader::readback_bindings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BindingAsset {
+    Buffer(AssetId<ShaderBuffer>),
+    Texture(AssetId<Image>),
+}
+
+/// Assets bound at [`ComputeShader::readback_bindings`], found in the render world from the
+/// prepared bind group. Shared by both worlds.
+#[derive(Resource)]
+pub(crate) struct ReadbackBindings<S: ComputeShader> {
+    /// One entry per binding, `None` while it isn't found, and whether they changed since the
+    /// main world last looked.
+    assets: Arc<Mutex<(Vec<Option<BindingAsset>>, bool)>>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ReadbackBindings<S> {
+    fn default() -> Self {
+        Self {
+            assets: Arc::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> Clone for ReadbackBindings<S> {
+    fn clone(&self) -> Self {
+        Self {
+            assets: self.assets.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ReadbackBindings<S> {
+    /// Readbacks of the bound assets, `None` for the ones not found yet.
+    fn readbacks(
+        &self,
+        buffers: &mut Assets<ShaderBuffer>,
+        images: &mut Assets<Image>,
+    ) -> Vec<Option<Readback>> {
+        let assets = self.assets.lock().unwrap();
+        (0..S::readback_bindings().len())
+            .map(|index| match assets.0.get(index).copied().flatten()? {
+                BindingAsset::Buffer(id) => buffers.get_strong_handle(id).map(Readback::buffer),
+                BindingAsset::Texture(id) => images.get_strong_handle(id).map(Readback::texture),
+            })
+            .collect()
+    }
+    /// Find the assets bound at the readback bindings, after the bind group is prepared.
+    pub(crate) fn resolve(
+        bindings: Res<Self>,
+        bind_group: Res<ComputeShaderBindGroup<S>>,
+        buffers: Res<RenderAssets<GpuShaderBuffer>>,
+        images: Res<RenderAssets<GpuImage>>,
+    ) {
+        let assets = S::readback_bindings()
+            .into_iter()
+            .map(|binding| {
+                let resource = bind_group
+                    .bindings
+                    .iter()
+                    .find(|(index, _)| *index == binding)
+                    .map(|(_, resource)| resource);
+                let asset = match resource {
+                    Some(OwnedBindingResource::Buffer(buffer)) => buffers
+                        .iter()
+                        .find(|(_, gpu)| gpu.buffer.id() == buffer.id())
+                        .map(|(id, _)| BindingAsset::Buffer(id)),
+                    Some(OwnedBindingResource::TextureView(_, view)) => images
+                        .iter()
+                        .find(|(_, gpu)| gpu.texture_view.id() == view.id())
+                        .map(|(id, _)| BindingAsset::Texture(id)),
+                    _ => None,
+                };
+                if asset.is_none() {
+                    error_once!(
+                        "{}: readback binding {binding} isn't a storage buffer or texture asset",
+                        S::shader_label()
+                    );
+                }
+                asset
+            })
+            .collect::<Vec<_>>();
+        let mut current = bindings.assets.lock().unwrap();
+        if current.0 != assets {
+            *current = (assets, true);
+        }
+    }
+    /// Insert the readbacks again once the bind group is prepared with other assets.
+    pub(crate) fn update(world: &mut World) {
+        let changed = std::mem::take(&mut world.resource::<Self>().assets.lock().unwrap().1);
+        let ready = world
+            .get_resource::<State<ComputeNodeState<S>>>()
+            .is_some_and(|state| {
+                matches!(
+                    state.status,
+                    ComputeNodeStatus::Ready | ComputeNodeStatus::Paused
+                )
+            });
+        if changed && ready && world.contains_resource::<S>() {
+            let _ = world.run_system_cached(ComputeShaderReadback::<S>::on_shader_ready);
+        }
+    }
+}
+
+/// Component that receives readback events from the compute shader.
+/// One entity is spawned per entry of [`ComputeShader::readbacks`].
+#[derive(Component)]
+pub struct ComputeShaderReadback<S: ComputeShader> {
+    /// Index of the readback in [`ComputeShader::readbacks`].
+    pub index: usize,
+    /// Info of the last readback delivered, set before [`ComputeShader::on_readback`] runs.
+    pub info: Option<ReadbackInfo>,
+    /// Data of the readback being delivered, while [`ComputeShader::on_readback`] runs,
+    /// if [`ComputeShader::readback_data_in_trigger`] is false.
+    pub data: Option<ReadbackData>,
+    /// Input of the dispatch read back last, with [`ComputeShaderPlugin::readback_inputs`].
+    ///
+    /// [`ComputeShaderPlugin::readback_inputs`]: crate::ComputeShaderPlugin::readback_inputs
+    pub input: Option<Arc<S>>,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeShaderReadback<S> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+impl<S: ComputeShader> ComputeShaderReadback<S> {
+    /// Create a readback receiver for the readback at `index`.
+    pub fn new(index: usize) -> Self {
+        Self {
+            index,
+            info: None,
+            data: None,
+            input: None,
+            _marker: PhantomData,
+        }
+    }
+    /// Look up which readback target an entity receives, e.g. from `on_readback`.
+    pub fn index_of(world: &DeferredWorld, entity: Entity) -> Option<usize> {
+        world.get::<Self>(entity).map(|readback| readback.index)
+    }
+    /// Look up the data of the readback being delivered to an entity, from `on_readback`
+    /// when [`ComputeShader::readback_data_in_trigger`] is false.
+    pub fn data_of(world: &DeferredWorld, entity: Entity) -> Option<ReadbackData> {
+        world
+            .get::<Self>(entity)
+            .and_then(|readback| readback.data.clone())
+    }
+    /// Look up which dispatch produced the readback an entity received, e.g. from
+    /// `on_readback` to name output files after the iteration.
+    pub fn info_of(world: &DeferredWorld, entity: Entity) -> Option<ReadbackInfo> {
+        world.get::<Self>(entity).and_then(|readback| readback.info)
+    }
+    /// Look up the input of the dispatch that produced the readback an entity received,
+    /// with [`ComputeShaderPlugin::readback_inputs`].
+    ///
+    /// [`ComputeShaderPlugin::readback_inputs`]: crate::ComputeShaderPlugin::readback_inputs
+    pub fn input_of(world: &DeferredWorld, entity: Entity) -> Option<Arc<S>> {
+        world
+            .get::<Self>(entity)
+            .and_then(|readback| readback.input.clone())
+    }
+    /// Spawn a readback receiver with the readback observers attached.
+    fn spawn_receiver(commands: &mut Commands, index: usize, bundle: impl Bundle) -> Entity {
+        let entity = commands.spawn((Self::new(index), bundle)).id();
+        commands.queue(move |world: &mut World| Self::register_receiver(world, index, entity));
+        entity
+    }
+    /// Record a receiver and attach the readback observers, unless they're still attached
+    /// from an earlier run as a [`ComputeReadbackTarget`].
+    fn register_receiver(world: &mut World, index: usize, entity: Entity) {
+        let Ok(mut receiver) = world.get_entity_mut(entity) else {
+            return;
+        };
+        if receiver.contains::<ReceiverObserved<S>>() {
+            world
+                .get_resource_or_init::<ComputeReadbackEntities<S>>()
+                .insert(index, entity);
+            return;
+        }
+        receiver
+            .insert(ReceiverObserved::<S>(PhantomData))
+            .observe(Self::on_readback)
+            .observe(ReadbackTracker::<S>::on_delivered);
+        world
+            .get_resource_or_init::<ComputeReadbackEntities<S>>()
+            .insert(index, entity);
+        let observers = world
+            .get_resource::<ReadbackObservers<S>>()
+            .map(|observers| observers.observers.clone())
+            .unwrap_or_default();
+        for observer in observers {
+            world.spawn(observer(entity));
+        }
+    }
+    /// Tag the readback with its [`ReadbackInfo`], trim it to its range, skip it if unchanged,
+    /// deliver it to the enabled outputs and run the hooks.
+    fn on_readback(mut trigger: On<ReadbackComplete>, mut world: DeferredWorld) {
+        let entity = trigger.event().entity;
+        let frame = world
+            .get_resource::<FrameCount>()
+            .map_or(0, |frame| frame.0.into());
+        let (info, input) = world
+            .get_resource::<ReadbackInfos<S>>()
+            .and_then(|infos| infos.pop(entity, frame))
+            .map_or((None, None), |(info, input)| (Some(info), input));
+        if let Some(latency) = info.and_then(|info| info.latency)
+            && let Some(mut stats) = world.get_resource_mut::<ReadbackLatencyStats<S>>()
+        {
+            stats.push(latency);
+        }
+        if let Some((offset, size)) = info.and_then(|info| info.range) {
+            ReadbackRange::trim(offset, size, &mut trigger.event_mut().data);
+        }
+        if let Some(index) = ComputeShaderReadback::<S>::index_of(&world, entity) {
+            GpuEventOutputs::<S>::write(&mut world, index, &trigger.event().data);
+        }
+        if world
+            .get_resource::<S>()
+            .is_some_and(|input| input.is_converged(&trigger.event().data))
+        {
+            world
+                .commands()
+                .queue(CompleteComputeShader::<S>::default());
+        }
+        let Some(mut readback) = world.get_mut::<Self>(entity) else {
+            return;
+        };
+        readback.info = info;
+        readback.input = input.clone();
+        let index = readback.index;
+        let info = info.unwrap_or_default();
+        let data = &trigger.event().data;
+        // Tiles of alike data aren't duplicates.
+        if info.tile.is_none()
+            && let Some(mut dedupe) = world.get_resource_mut::<ReadbackDedupe<S>>()
+            && dedupe.is_duplicate(index, info, frame, data)
+        {
+            return;
+        }
+        // Copy the data once into an aligned allocation, shared with every delivery.
+        let data = ReadbackData::new(&trigger.event().data);
+        if let Some(mut history) = world.get_resource_mut::<ReadbackHistory<S>>() {
+            history.push(ReadbackRecord {
+                data: data.clone(),
+                frame,
+                index,
+                info,
+            });
+        }
+        if let Some(mut queue) = world.get_resource_mut::<ReadbackQueue<S>>() {
+            queue.push(ReadbackRecord {
+                data: data.clone(),
+                frame,
+                index,
+                info,
+            });
+        }
+        if let Some(mut channel) = world.get_resource_mut::<ReadbackChannel<S>>()
+            && !channel.disconnected
+        {
+            channel.send(ReadbackPacket {
+                data: data.clone(),
+                shader: TypeId::of::<S>(),
+                shader_label: S::shader_label(),
+                index,
+                info,
+                time: Instant::now(),
+            });
+        }
+        if let Some(mut messages) = world.get_resource_mut::<Messages<ReadbackEvent<S>>>() {
+            messages.write(ReadbackEvent {
+                data: data.clone(),
+                frame,
+                index,
+                info,
+                input: input.clone(),
+                _marker: PhantomData,
+            });
+        }
+        if let Some(mut latest) = world.get_resource_mut::<LatestReadback<S>>() {
+            latest.data = data.clone();
+            latest.frame = frame;
+            latest.sequence += 1;
+            latest.index = index;
+            latest.info = info;
+            latest.input = input.clone();
+        }
+        if index == 0 {
+            let decoders = world
+                .get_resource::<ReadbackResources<S>>()
+                .map(|resources| resources.decoders.clone())
+                .unwrap_or_default();
+            for decoder in decoders {
+                decoder(&data, &mut world.commands());
+            }
+        }
+        let processors = world
+            .get_resource::<ReadbackResources<S>>()
+            .map(|resources| resources.processors.clone())
+            .unwrap_or_default();
+        for processor in processors {
+            processor(&data, index, info, &mut world);
+        }
+        if let Some(tile) = info.tile {
+            ReadbackTiles::<S>::deliver(&mut world, index, info, tile, &data);
+        }
+        if index == 0
+            && let Some(image) = world
+                .get_resource::<S>()
+                .and_then(|input| input.readback_image_target())
+        {
+            let pending = ImageReadback::<S> {
+                image,
+                data: data.clone(),
+                _marker: PhantomData,
+            };
+            // A newer readback replaces any that is still waiting on the image.
+            if pending.apply(&mut world.resource_mut::<Assets<Image>>()) {
+                world.commands().remove_resource::<ImageReadback<S>>();
+            } else {
+                world.commands().insert_resource(pending);
+            }
+        }
+        S::on_readback_commands(&data, index, info, &mut world.commands());
+        if S::readback_data_in_trigger() {
+            S::on_readback(trigger, world);
+        } else {
+            // Only the shared copy is kept.
+            trigger.event_mut().data = Vec::new();
+            if let Some(mut readback) = world.get_mut::<Self>(entity) {
+                readback.data = Some(data);
+            }
+            S::on_readback(trigger, world.reborrow());
+            if let Some(mut readback) = world.get_mut::<Self>(entity) {
+                readback.data = None;
+            }
+        }
+    }
+    /// Spawn the readback observers once the plugin is added, on the
+    /// [`ComputeReadbackTarget`] entities if any.
+    pub(crate) fn spawn(
+        mut commands: Commands,
+        compute_shader: Res<S>,
+        targets: Query<(Entity, &ComputeReadbackTarget<S>)>,
+    ) {
+        for index in 0..compute_shader.readbacks().len() {
+            match targets.iter().find(|(_, target)| target.index == index) {
+                Some((entity, _)) => {
+                    commands.entity(entity).insert(Self::new(index));
+                    commands.queue(move |world: &mut World| {
+                        Self::register_receiver(world, index, entity)
+                    });
+                }
+                None => {
+                    Self::spawn_receiver(&mut commands, index, ());
+                }
+            }
+        }
+    }
+    /// Insert GPU readback components only when the shader is ready.
+    /// Then [`ComputeShader::readback_bindings`] and the cameras marked with
+    /// [`ComputeReadbackCamera`] are read back after the shader's readbacks.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn on_shader_ready(
+        mut commands: Commands,
+        compute_shader: Res<S>,
+        compute_shader_readbacks: Query<(Entity, &Self)>,
+        cameras: Query<(Entity, &RenderTarget), With<ComputeReadbackCamera<S>>>,
+        bindings: Res<ReadbackBindings<S>>,
+        mut buffers: ResMut<Assets<ShaderBuffer>>,
+        mut images: ResMut<Assets<Image>>,
+        mut downsample: Option<ResMut<ReadbackDownsample<S>>>,
+    ) {
+        let mut cameras: Vec<_> = cameras
+            .iter()
+            .filter_map(|(camera, target)| match target {
+                RenderTarget::Image(target) => Some((camera, target.handle.clone())),
+                _ => {
+                    warn_once!(
+                        "{}: only cameras rendering to an image can be read back",
+                        S::shader_label()
+                    );
+                    None
+                }
+            })
+            .collect();
+        cameras.sort_by_key(|(camera, _)| *camera);
+        let readbacks = compute_shader
+            .readbacks()
+            .into_iter()
+            .enumerate()
+            .map(|(index, readback)| match (readback, &mut downsample) {
+                (Readback::Texture(source), Some(downsample)) => {
+                    let target = downsample.target(index, source, &mut images);
+                    (Some(Readback::texture(target)), None)
+                }
+                (readback, _) => (Some(readback), None),
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .chain(
+                bindings
+                    .readbacks(&mut buffers, &mut images)
+                    .into_iter()
+                    .map(|readback| (readback, None)),
+            )
+            .chain(
+                cameras
+                    .into_iter()
+                    .map(|(camera, image)| (Some(Readback::texture(image)), Some(camera))),
+            );
+        let mut count = 0;
+        for (index, (readback, camera)) in readbacks.enumerate() {
+            count += 1;
+            // Bindings not found yet are inserted once the bind group is prepared.
+            let Some(readback) = readback else {
+                continue;
+            };
+            let (readback, range) =
+                ReadbackRange::apply(readback, compute_shader.readback_range(index));
+            let entity = match compute_shader_readbacks
+                .iter()
+                .find(|(_, compute_shader_readback)| compute_shader_readback.index == index)
+            {
+                Some((entity, _)) => {
+                    commands.entity(entity).insert(readback);
+                    entity
+                }
+                None => Self::spawn_receiver(&mut commands, index, readback),
+            };
+            match range {
+                Some(range) => commands.entity(entity).insert(range),
+                None => commands.entity(entity).remove::<ReadbackRange>(),
+            };
+            match camera {
+                Some(camera) => commands
+                    .entity(entity)
+                    .insert(ComputeReadbackCameraSource { camera }),
+                None => commands
+                    .entity(entity)
+                    .remove::<ComputeReadbackCameraSource>(),
+            };
+        }
+        // Stop reading back cameras that were unmarked since the last run.
+        for (entity, compute_shader_readback) in &compute_shader_readbacks {
+            if compute_shader_readback.index >= count {
+                commands
+                    .entity(entity)
+                    .remove::<(Readback, ReadbackRange, ComputeReadbackCameraSource)>();
+            }
+        }
+    }
+    /// Update the [`ComputeShader::readback_range`] of active readbacks when the input changes.
+    pub(crate) fn update_ranges(
+        mut commands: Commands,
+        compute_shader: Res<S>,
+        compute_shader_readbacks: Query<(Entity, &Self, &Readback)>,
+    ) {
+        for (entity, readback, current) in &compute_shader_readbacks {
+            let range = compute_shader.readback_range(readback.index);
+            let (updated, range) = ReadbackRange::apply(current.clone(), range);
+            let mut entity = commands.entity(entity);
+            if let Some(range) = range {
+                entity.insert((updated, range));
+            } else {
+                entity.remove::<ReadbackRange>();
+            }
+        }
+    }
+    /// Disable the shader when it's done.
+    pub(crate) fn on_shader_complete(
+        mut commands: Commands,
+        mut compute_shader_readbacks: Query<Entity, With<Self>>,
+        mut tracker: ResMut<ReadbackTracker<S>>,
+    ) {
+        for entity in compute_shader_readbacks.iter_mut() {
+            commands.entity(entity).remove::<Readback>();
+        }
+        tracker.complete_pending = true;
+    }
+}
+
+/// Marks entities the readback observers are attached to, so they aren't attached twice.
+#[derive(Component)]
+struct ReceiverObserved<S: ComputeShader>(PhantomData<S>);
+
+/// Marks an entity to receive the readback at `index` in [`ComputeShader::readbacks`] instead
+/// of an entity spawned by the plugin. When the marker is removed or the entity despawned, the
+/// readback moves back to a spawned entity.
+#[derive(Component, Debug)]
+pub struct ComputeReadbackTarget<S: ComputeShader> {
+    pub index: usize,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeReadbackTarget<S> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+impl<S: ComputeShader> ComputeReadbackTarget<S> {
+    /// Receive the readback at `index`.
+    pub fn new(index: usize) -> Self {
+        Self {
+            index,
+            _marker: PhantomData,
+        }
+    }
+    /// Move the readback from its current receiver to the new target.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn on_add(
+        add: On<Add, Self>,
+        mut commands: Commands,
+        targets: Query<&Self>,
+        receivers: Query<(
+            Entity,
+            &ComputeShaderReadback<S>,
+            Option<&Readback>,
+            Option<&ReadbackRange>,
+            Has<Self>,
+        )>,
+        tracker: Option<Res<ReadbackTracker<S>>>,
+    ) {
+        let entity = add.entity;
+        // Removed shaders attach to targets when restarted.
+        if tracker.is_none() || receivers.contains(entity) {
+            return;
+        }
+        let Ok(target) = targets.get(entity) else {
+            return;
+        };
+        let index = target.index;
+        let mut readback = None;
+        for (receiver, compute_shader_readback, current, range, is_target) in &receivers {
+            if compute_shader_readback.index != index {
+                continue;
+            }
+            readback = current.cloned().map(|current| (current, range.cloned()));
+            if is_target {
+                warn!(
+                    "{}: readback {index} already has a target, moving it to {entity}",
+                    S::shader_label()
+                );
+                commands
+                    .entity(receiver)
+                    .try_remove::<(ComputeShaderReadback<S>, Readback, ReadbackRange)>();
+            } else {
+                commands.entity(receiver).despawn();
+            }
+        }
+        let mut target = commands.entity(entity);
+        target.insert(ComputeShaderReadback::<S>::new(index));
+        match readback {
+            Some((readback, Some(range))) => target.insert((readback, range)),
+            Some((readback, None)) => target.insert(readback),
+            None => &mut target,
+        };
+        commands.queue(move |world: &mut World| {
+            ComputeShaderReadback::<S>::register_receiver(world, index, entity)
+        });
+    }
+    /// Move the readback back to a spawned receiver.
+    pub(crate) fn on_remove(
+        remove: On<Remove, Self>,
+        mut commands: Commands,
+        receivers: Query<(
+            &ComputeShaderReadback<S>,
+            Option<&Readback>,
+            Option<&ReadbackRange>,
+        )>,
+    ) {
+        let entity = remove.entity;
+        let Ok((receiver, readback, range)) = receivers.get(entity) else {
+            return;
+        };
+        let index = receiver.index;
+        match (readback.cloned(), range.cloned()) {
+            (Some(readback), Some(range)) => {
+                ComputeShaderReadback::<S>::spawn_receiver(&mut commands, index, (readback, range))
+            }
+            (Some(readback), None) => {
+                ComputeShaderReadback::<S>::spawn_receiver(&mut commands, index, readback)
+            }
+            _ => ComputeShaderReadback::<S>::spawn_receiver(&mut commands, index, ()),
+        };
+        commands
+            .entity(entity)
+            .try_remove::<(ComputeShaderReadback<S>, Readback, ReadbackRange)>();
+    }
+}
+
+/// Marks a camera whose render target, an image with `COPY_SRC`, is read back with the shader's
+/// readbacks, at the indices after [`ComputeShader::readbacks`] and [`ComputeShader::readback_bindings`].
+/// The receiver gets a [`ComputeReadbackCameraSource`] naming the camera.
+#[derive(Component, Debug)]
+pub struct ComputeReadbackCamera<S: ComputeShader>(PhantomData<S>);
+impl<S: ComputeShader> Default for ComputeReadbackCamera<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// The camera a [`ComputeReadbackCamera`] readback came from, on its receiver.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComputeReadbackCameraSource {
+    pub camera: Entity,
+}
+
+/// Region of a texture to read back into `buffer` instead of the whole texture, e.g. a window
+/// around the player. Extracted separately from `S`, so moving it doesn't restart the run.
+/// Rows come back padded, see [`ReadbackRegion::unpad`].
+#[derive(Resource, ExtractResource, Clone, Debug)]
+pub struct ReadbackRegion<S: ComputeShader> {
+    pub texture: Handle<Image>,
+    /// First texel of the region, with z the first array layer or depth slice.
+    pub origin: UVec3,
+    /// Size of the region in texels, with z the number of array layers or depth slices.
+    pub size: UVec3,
+    pub mip_level: u32,
+    /// Buffer the region is copied into. See [`ReadbackRegion::staging_buffer`].
+    pub buffer: Handle<ShaderBuffer>,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ReadbackRegion<S> {
+    /// Read back `size` texels of `texture` starting at `origin`, through `buffer`.
+    pub fn new(
+        texture: Handle<Image>,
+        origin: UVec3,
+        size: UVec3,
+        buffer: Handle<ShaderBuffer>,
+    ) -> Self {
+        Self {
+            texture,
+            origin,
+            size,
+            mip_level: 0,
+            buffer,
+            _marker: PhantomData,
+        }
+    }
+    /// Set [`ReadbackRegion::mip_level`].
+    pub fn with_mip_level(mut self, mip_level: u32) -> Self {
+        self.mip_level = mip_level;
+        self
+    }
+    /// Buffer large enough for regions of up to `max_size` texels of `format`.
+    pub fn staging_buffer(max_size: UVec3, format: TextureFormat) -> ShaderBuffer {
+        let size = TextureRowLayout::new(Self::extent_of(max_size), format)
+            .map_or(0, |layout| layout.len());
+        let mut buffer = ShaderBuffer::with_size(size, RenderAssetUsages::RENDER_WORLD);
+        buffer.buffer_description.usage |= BufferUsages::COPY_DST | BufferUsages::COPY_SRC;
+        buffer
+    }
+    /// Size of the region.
+    pub fn extent(&self) -> Extent3d {
+        Self::extent_of(self.size)
+    }
+    fn extent_of(size: UVec3) -> Extent3d {
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: size.z,
+        }
+    }
+    /// Remove the row padding of a readback of this region, see [`unpad_texture_data`].
+    /// The readback must come from the current region, which may have moved since.
+    pub fn unpad(
+        &self,
+        data: &[u8],
+        format: TextureFormat,
+    ) -> Result<Vec<u8>, ReadbackDecodeError> {
+        unpad_texture_data(data, self.extent(), format)
+    }
+    /// Copy the region into the buffer, after the dispatch.
+    pub(crate) fn encode(&self, ctx: &mut RenderContext, world: &World) {
+        let (Some(image), Some(buffer)) = (
+            world
+                .resource::<RenderAssets<GpuImage>>()
+                .get(&self.texture),
+            world
+                .resource::<RenderAssets<GpuShaderBuffer>>()
+                .get(&self.buffer),
+        ) else {
+            return;
+        };
+        let extent = self.extent();
+        let Ok(layout) = TextureRowLayout::new(extent, image.texture.format()) else {
+            error_once!(
+                "{}: {}",
+                S::shader_label(),
+                ReadbackDecodeError::TextureFormat(image.texture.format())
+            );
+            return;
+        };
+        if self.mip_level >= image.texture.mip_level_count() {
+            error_once!(
+                "{}: readback region mip {} is outside the {} mips of its texture",
+                S::shader_label(),
+                self.mip_level,
+                image.texture.mip_level_count(),
+            );
+            return;
+        }
+        let mip_size = image
+            .texture
+            .size()
+            .mip_level_size(self.mip_level, image.texture.dimension());
+        let mip_size = UVec3::new(
+            mip_size.width,
+            mip_size.height,
+            mip_size.depth_or_array_layers,
+        );
+        if (self.origin + self.size).cmpgt(mip_size).any() {
+            error_once!(
+                "{}: readback region at {} of size {} is outside mip {} of size {mip_size}",
+                S::shader_label(),
+                self.origin,
+                self.size,
+                self.mip_level,
+            );
+            return;
+        }
+        if (buffer.buffer.size() as usize) < layout.len() {
+            error_once!(
+                "{}: readback region buffer of {} bytes can't hold a region of {} bytes",
+                S::shader_label(),
+                buffer.buffer.size(),
+                layout.len(),
+            );
+            return;
+        }
+        if layout.rows == 0 {
+            return;
+        }
+        ctx.command_encoder().copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                texture: &image.texture,
+                mip_level: self.mip_level,
+                origin: Origin3d {
+                    x: self.origin.x,
+                    y: self.origin.y,
+                    z: self.origin.z,
+                },
+                aspect: TextureAspect::All,
+            },
+            TexelCopyBufferInfo {
+                buffer: &buffer.buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: (layout.rows > 1).then_some(layout.padded_row as u32),
+                    rows_per_image: (extent.depth_or_array_layers > 1)
+                        .then_some(layout.rows as u32 / extent.depth_or_array_layers),
+                },
+            },
+            extent,
+        );
+    }
+}
+
+/// Observers added by [`ComputeShaderAppExt::observe_compute_readback`], attached to each
+/// readback entity as it's spawned.
+///
+/// [`ComputeShaderAppExt::observe_compute_readback`]: crate::ComputeShaderAppExt::observe_compute_readback
+#[derive(Resource)]
+pub(crate) struct ReadbackObservers<S: ComputeShader> {
+    pub(crate) observers: Vec<ReadbackObserver>,
+    _marker: PhantomData<S>,
+}
+pub(crate) type ReadbackObserver = Arc<dyn Fn(Entity) -> Observer + Send + Sync>;
+impl<S: ComputeShader> Default for ReadbackObservers<S> {
+    fn default() -> Self {
+        Self {
+            observers: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// The main world entities receiving the readbacks of `S`, one per entry of
+/// [`ComputeShader::readbacks`], e.g. to attach observers or components to them.
+/// Filled in as the entities are spawned, and removed by [`RemoveComputeShader`].
+#[derive(Resource, Debug)]
+pub struct ComputeReadbackEntities<S: ComputeShader> {
+    entities: Vec<Option<Entity>>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeReadbackEntities<S> {
+    fn default() -> Self {
+        Self {
+            entities: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ComputeReadbackEntities<S> {
+    /// Entity receiving the readback at `index` in [`ComputeShader::readbacks`].
+    pub fn get(&self, index: usize) -> Option<Entity> {
+        self.entities.get(index).copied().flatten()
+    }
+    /// Readback indices and their entities.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Entity)> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entity)| entity.map(|entity| (index, entity)))
+    }
+    fn insert(&mut self, index: usize, entity: Entity) {
+        if self.entities.len() <= index {
+            self.entities.resize(index + 1, None);
+        }
+        self.entities[index] = Some(entity);
+    }
+}
+
+/// What to do with readbacks arriving while an earlier one is still being processed, see
+/// [`ComputeShaderAppExt::add_compute_readback_processor`].
+///
+/// [`ComputeShaderAppExt::add_compute_readback_processor`]: crate::ComputeShaderAppExt::add_compute_readback_processor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadbackProcessing {
+    /// Cancel the unfinished tasks, only the newest readback matters.
+    #[default]
+    LatestWins,
+    /// Process every readback, delivering the results in order.
+    ProcessAll,
+}
+
+/// Result of processing a readback of `S`, written once its task finishes.
+/// See [`ComputeShaderAppExt::add_compute_readback_processor`].
+///
+/// [`ComputeShaderAppExt::add_compute_readback_processor`]: crate::ComputeShaderAppExt::add_compute_readback_processor
+#[derive(Message, Debug)]
+pub struct ProcessedReadback<S: ComputeShader, T> {
+    pub value: T,
+    /// Index of the readback target in [`ComputeShader::readbacks`].
+    pub index: usize,
+    /// Dispatch that produced the readback.
+    pub info: ReadbackInfo,
+    _marker: PhantomData<S>,
+}
+
+/// Readbacks of `S` being processed into `T`, oldest first. Added by
+/// [`ComputeShaderAppExt::add_compute_readback_processor`].
+///
+/// [`ComputeShaderAppExt::add_compute_readback_processor`]: crate::ComputeShaderAppExt::add_compute_readback_processor
+#[derive(Resource)]
+pub struct ReadbackProcessor<S: ComputeShader, T> {
+    process: Arc<dyn Fn(ReadbackData, ReadbackInfo) -> T + Send + Sync>,
+    processing: ReadbackProcessing,
+    tasks: VecDeque<Task<ProcessedReadback<S, T>>>,
+    cancelled: u64,
+}
+impl<S: ComputeShader, T: Send + Sync + 'static> ReadbackProcessor<S, T> {
+    /// Number of readbacks being processed.
+    pub fn in_flight(&self) -> usize {
+        self.tasks.len()
+    }
+    /// Number of tasks cancelled by newer readbacks with [`ReadbackProcessing::LatestWins`].
+    pub fn cancelled(&self) -> u64 {
+        self.cancelled
+    }
+    pub(crate) fn new(
+        process: impl Fn(ReadbackData, ReadbackInfo) -> T + Send + Sync + 'static,
+        processing: ReadbackProcessing,
+    ) -> Self {
+        Self {
+            process: Arc::new(process),
+            processing,
+            tasks: VecDeque::new(),
+            cancelled: 0,
+        }
+    }
+    /// Start processing a readback.
+    pub(crate) fn spawn(
+        data: &ReadbackData,
+        index: usize,
+        info: ReadbackInfo,
+        world: &mut DeferredWorld,
+    ) {
+        let Some(mut processor) = world.get_resource_mut::<Self>() else {
+            return;
+        };
+        if processor.processing == ReadbackProcessing::LatestWins {
+            // Dropping a task cancels it.
+            processor.cancelled += processor.tasks.len() as u64;
+            processor.tasks.clear();
+        }
+        let (process, data) = (processor.process.clone(), data.clone());
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            ProcessedReadback {
+                value: process(data, info),
+                index,
+                info,
+                _marker: PhantomData,
+            }
+        });
+        processor.tasks.push_back(task);
+    }
+    /// Write the results of the finished tasks, in order.
+    pub(crate) fn poll(
+        mut processor: ResMut<Self>,
+        mut messages: ResMut<Messages<ProcessedReadback<S, T>>>,
+    ) {
+        while processor.tasks.front().is_some_and(Task::is_finished) {
+            let task = processor.tasks.pop_front().unwrap();
+            messages.write(block_on(task));
+        }
+    }
+}
+
+/// Decoders added by [`ComputeShaderAppExt::add_compute_readback_resource`], and processors
+/// added by [`ComputeShaderAppExt::add_compute_readback_processor`].
+///
+/// [`ComputeShaderAppExt::add_compute_readback_resource`]: crate::ComputeShaderAppExt::add_compute_readback_resource
+/// [`ComputeShaderAppExt::add_compute_readback_processor`]: crate::ComputeShaderAppExt::add_compute_readback_processor
+#[derive(Resource)]
+pub(crate) struct ReadbackResources<S: ComputeShader> {
+    pub(crate) decoders: Vec<ReadbackDecoder>,
+    pub(crate) processors: Vec<ReadbackProcessorSpawner>,
+    _marker: PhantomData<S>,
+}
+type ReadbackDecoder = Arc<dyn Fn(&[u8], &mut Commands) + Send + Sync>;
+type ReadbackProcessorSpawner =
+    Arc<dyn Fn(&ReadbackData, usize, ReadbackInfo, &mut DeferredWorld) + Send + Sync>;
+impl<S: ComputeShader> Default for ReadbackResources<S> {
+    fn default() -> Self {
+        Self {
+            decoders: Vec::new(),
+            processors: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Readback waiting for its [`ComputeShader::readback_image_target`] to be loaded.
+#[derive(Resource)]
+pub(crate) struct ImageReadback<S: ComputeShader> {
+    image: Handle<Image>,
+    data: ReadbackData,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ImageReadback<S> {
+    /// Copy the data into the image, returning false if it isn't loaded yet.
+    /// Data of the wrong size is logged and dropped.
+    fn apply(&self, images: &mut Assets<Image>) -> bool {
+        let Some(mut image) = images.get_mut(&self.image) else {
+            return false;
+        };
+        let (size, format) = (
+            image.texture_descriptor.size,
+            image.texture_descriptor.format,
+        );
+        match unpad_texture_data(&self.data, size, format) {
+            Ok(data) => image.data = Some(data),
+            Err(error) => error_once!("{}: image target: {error}", S::shader_label()),
+        }
+        true
+    }
+    /// Retry applying a readback that arrived before its image was loaded.
+    pub(crate) fn retry(world: &mut World) {
+        let applied = world.resource_scope(|world, pending: Mut<Self>| {
+            pending.apply(&mut world.resource_mut::<Assets<Image>>())
+        });
+        if applied {
+            world.remove_resource::<Self>();
+        }
+    }
+}
+
+/// Tracks readbacks requested by the render world and delivered to the main world,
+/// so [`ComputeShader::on_complete`] runs after the final readback.
+#[derive(Resource)]
+pub(crate) struct ReadbackTracker<S: ComputeShader> {
+    requested: usize,
+    delivered: usize,
+    /// Whether the render world requested no readbacks in its last frame.
+    idle: bool,
+    complete_pending: bool,
+    /// Number of [`CompleteComputeShader`] requests, sent to the render world.
+    pub(crate) completion_requests: usize,
+    /// Number of [`DispatchComputeShader`] requests, sent to the render world.
+    ///
+    /// [`DispatchComputeShader`]: crate::DispatchComputeShader
+    pub(crate) dispatch_requests: usize,
+    /// Number of [`RetryComputeShader`] requests, sent to the render world.
+    ///
+    /// [`RetryComputeShader`]: crate::RetryComputeShader
+    pub(crate) retry_requests: usize,
+    /// Remove the compute shader after completing, see [`ComputeShaderPlugin::remove_on_complete`].
+    ///
+    /// [`ComputeShaderPlugin::remove_on_complete`]: crate::ComputeShaderPlugin::remove_on_complete
+    remove_on_complete: bool,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ReadbackTracker<S> {
+    fn default() -> Self {
+        Self {
+            requested: 0,
+            delivered: 0,
+            idle: true,
+            complete_pending: false,
+            completion_requests: 0,
+            dispatch_requests: 0,
+            retry_requests: 0,
+            remove_on_complete: false,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ReadbackTracker<S> {
+    pub(crate) fn new(remove_on_complete: bool) -> Self {
+        Self {
+            remove_on_complete,
+            ..default()
+        }
+    }
+    /// Copy the render world's readback request count to the main world,
+    /// and completion and dispatch requests to the render world.
+    pub(crate) fn extract_requested(
+        mut node: ResMut<ComputeNode<S>>,
+        mut world: ResMut<MainWorld>,
+    ) {
+        let mut tracker = world.resource_mut::<Self>();
+        tracker.requested = node.requested;
+        tracker.idle = node.readbacks_idle;
+        node.delivered = tracker.delivered;
+        if node.completion_requests != tracker.completion_requests {
+            node.completion_requests = tracker.completion_requests;
+            node.complete_requested = true;
+        }
+        if node.dispatch_requests != tracker.dispatch_requests {
+            node.dispatch_requests = tracker.dispatch_requests;
+            node.dispatch_pending = true;
+        }
+        if node.retry_requests != tracker.retry_requests {
+            node.retry_requests = tracker.retry_requests;
+            node.retry_pending = true;
+        }
+    }
+    /// Count readbacks delivered to the main world.
+    fn on_delivered(_trigger: On<ReadbackComplete>, mut tracker: ResMut<Self>) {
+        tracker.delivered += 1;
+    }
+    /// Call the completion hook once all requested readbacks have been delivered.
+    pub(crate) fn complete_when_delivered(mut world: DeferredWorld) {
+        let mut tracker = world.resource_mut::<Self>();
+        if tracker.complete_pending && tracker.idle && tracker.delivered >= tracker.requested {
+            tracker.complete_pending = false;
+            let remove = tracker.remove_on_complete;
+            S::on_complete(world.reborrow());
+            if remove {
+                world.commands().queue(RemoveComputeShader::<S>::default());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec4;
+
+    use super::*;
+    use crate::{ReadbackBytes, shader::tests::TestComputeShader};
+
+    #[test]
+    fn readback_data_is_aligned() {
+        let bytes: Vec<u8> = (0..37).collect();
+        // Even copied from a misaligned slice.
+        let data = ReadbackData::new(&bytes[1..]);
+        assert_eq!(*data, bytes[1..]);
+        assert!((data.as_ptr() as usize).is_multiple_of(16));
+        assert!(ReadbackData::default().is_empty());
+    }
+
+    #[test]
+    fn delivered_readbacks_view_as_vec4() {
+        type Receiver = ComputeShaderReadback<TestComputeShader>;
+        let mut world = World::new();
+        world.init_resource::<LatestReadback<TestComputeShader>>();
+        let entity = world
+            .spawn(Receiver::new(0))
+            .observe(Receiver::on_readback)
+            .id();
+        let texels = [Vec4::ONE, Vec4::new(1.0, 2.0, 3.0, 4.0)];
+        world.trigger(ReadbackComplete {
+            entity,
+            data: bytemuck::cast_slice(&texels).to_vec(),
+        });
+        let latest = world.resource::<LatestReadback<TestComputeShader>>();
+        assert_eq!(latest.sequence, 1);
+        assert_eq!(latest.data.as_pod_slice::<Vec4>(), Ok(&texels[..]));
+    }
+}
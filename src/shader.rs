@@ -0,0 +1,950 @@
+//! The [`ComputeShader`] trait and dispatch helpers.
+
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    ops::{Deref, DerefMut, Range},
+};
+
+use bevy::{
+    asset::Handle,
+    ecs::{
+        change_detection::DetectChanges,
+        component::Mutable,
+        observer::On,
+        resource::Resource,
+        system::{Commands, Res, StaticSystemParam, SystemParamItem},
+        world::{DeferredWorld, FromWorld, World},
+    },
+    image::Image,
+    log::error,
+    math::UVec3,
+    render::{
+        extract_resource::ExtractResource,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{
+            AsBindGroup, AsBindGroupError, BindGroup, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry, ComputePipelineDescriptor, PipelineCache, PreparedBindGroup,
+            ShaderSize, ShaderType, UnpreparedBindGroup, encase::internal::CreateFrom,
+        },
+        renderer::{RenderContext, RenderDevice},
+        storage::ShaderBuffer,
+    },
+    shader::{Shader, ShaderDefVal, ShaderRef},
+};
+
+use crate::{
+    ComputeNodeStatus, ComputePipeline, ComputeShaderBindGroup, ComputeShaderError,
+    ComputeViewTexture, ReadbackInfo, decode_readback,
+};
+
+/// Trait to implement for a custom compute shader.
+pub trait ComputeShader:
+    AsBindGroup + Clone + FromWorld + ExtractResource + Resource<Mutability = Mutable>
+{
+    /// Asset path or handle to the shader.
+    fn compute_shader() -> ShaderRef;
+    /// Number of workgroups dispatched, evaluated from the extracted resource each dispatch.
+    /// This is not the `@workgroup_size` declared in the shader, see [`ComputeShader::local_workgroup_size`].
+    fn dispatch_workgroups(&self) -> UVec3 {
+        #[allow(deprecated)]
+        Self::workgroup_size()
+    }
+    /// Whether to dispatch this frame, based on render world data.
+    /// Skipped frames aren't read back or counted towards [`ReadbackLimit::Finite`].
+    ///
+    /// [`ReadbackLimit::Finite`]: crate::ReadbackLimit::Finite
+    fn should_dispatch(&self, _world: &World) -> bool {
+        true
+    }
+    /// Encode commands right before the compute pass, on the same command encoder.
+    /// Only called on frames that dispatch. The world is the render world, e.g. to look up
+    /// `RenderAssets<GpuImage>` for handle-based copies.
+    fn encode_pre_dispatch(&self, _ctx: &mut RenderContext, _world: &World) {}
+    /// Encode commands right after the compute pass, on the same command encoder,
+    /// e.g. to copy the output into a history texture. Only called on frames that dispatch.
+    fn encode_post_dispatch(&self, _ctx: &mut RenderContext, _world: &World) {}
+    /// Optional buffer of `DispatchIndirectArgs` to dispatch from instead of
+    /// [`ComputeShader::dispatch_workgroups`]. It needs [`BufferUsages::INDIRECT`].
+    ///
+    /// [`BufferUsages::INDIRECT`]: bevy::render::render_resource::BufferUsages::INDIRECT
+    fn indirect_dispatch(&self) -> Option<Handle<ShaderBuffer>> {
+        None
+    }
+    /// Byte offset of the arguments in [`ComputeShader::indirect_dispatch`].
+    fn indirect_dispatch_offset(&self) -> u64 {
+        0
+    }
+    /// Optional `@workgroup_size` declared in the shader, used to validate the dispatch.
+    fn local_workgroup_size() -> Option<UVec3> {
+        None
+    }
+    /// Optional extent of the output the dispatch is expected to cover, used to validate the dispatch.
+    fn output_extent(&self) -> Option<UVec3> {
+        None
+    }
+    /// Number of workgroups dispatched.
+    #[deprecated(note = "Use `dispatch_workgroups`, which is evaluated per dispatch.")]
+    fn workgroup_size() -> UVec3 {
+        UVec3::ONE
+    }
+    /// Label used for the pipeline and compute pass, to tell shaders apart when debugging.
+    /// Named to avoid conflicting with [`AsBindGroup::label`], which labels the bind group.
+    fn shader_label() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+    /// Name of the compute entry point in the shader.
+    fn entry_point() -> Cow<'static, str> {
+        "main".into()
+    }
+    /// Passes encoded in order each frame, sharing the same bind groups.
+    /// Defaults to a single pass running [`ComputeShader::entry_point`] every frame.
+    fn passes() -> Vec<ComputePassSpec> {
+        vec![ComputePassSpec::new(Self::entry_point())]
+    }
+    /// Number of times the passes are dispatched each frame, within a single compute pass.
+    /// [`ReadbackLimit::Finite`] still counts frames, and readbacks see the last iteration.
+    ///
+    /// [`ReadbackLimit::Finite`]: crate::ReadbackLimit::Finite
+    fn iterations_per_frame(&self) -> u32 {
+        1
+    }
+    /// Descriptor of the pipeline queued for each of [`ComputeShader::passes`].
+    /// The default combines the other pipeline methods; override it to take full control.
+    fn pipeline_descriptor(
+        layout: Vec<BindGroupLayoutDescriptor>,
+        shader: Handle<Shader>,
+        pass: &ComputePassSpec,
+    ) -> ComputePipelineDescriptor {
+        let mut shader_defs = Self::shader_defs();
+        shader_defs.extend(
+            Self::pipeline_constants()
+                .into_iter()
+                .map(|(key, value)| value.shader_def(key)),
+        );
+        let mut label = Self::shader_label().to_string();
+        if !shader_defs.is_empty() {
+            let defs = shader_defs
+                .iter()
+                .map(|def| match def {
+                    ShaderDefVal::Bool(key, _)
+                    | ShaderDefVal::Int(key, _)
+                    | ShaderDefVal::UInt(key, _) => {
+                        format!("{key}={}", def.value_as_string())
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            label = format!("{label} [{defs}]");
+        }
+        if Self::passes().len() > 1 {
+            label = format!("{label} ({})", pass.entry_point);
+        }
+        ComputePipelineDescriptor {
+            label: Some(label.into()),
+            layout,
+            shader,
+            shader_defs,
+            immediate_size: Self::immediate_size(),
+            entry_point: Some(pass.entry_point.clone()),
+            zero_initialize_workgroup_memory: Self::zero_initialize_workgroup_memory(),
+        }
+    }
+    /// Shader defs used when compiling the shader.
+    fn shader_defs() -> Vec<ShaderDefVal> {
+        Vec::new()
+    }
+    /// Values for WGSL `override` constants, evaluated once when the pipeline is created.
+    ///
+    /// Bevy's pipeline descriptor can't set override constants, so each value is passed as a
+    /// shader def instead and must be used as the override's initializer, e.g.
+    /// `override TILE: u32 = #{TILE};`. Use the [`PipelineConstant`] variant of the override's
+    /// declared type. Shader defs can't express floats, so `f32` overrides can't be set.
+    fn pipeline_constants() -> Vec<(String, PipelineConstant)> {
+        Vec::new()
+    }
+    /// Whether `var<workgroup>` memory is zeroed before each dispatch.
+    /// Enable this if the shader reads workgroup memory before writing it.
+    fn zero_initialize_workgroup_memory() -> bool {
+        false
+    }
+    /// Size in bytes of the `var<immediate>` data used by the shader.
+    fn immediate_size() -> u32 {
+        0
+    }
+    /// Optional immediate data, set before each dispatch.
+    fn immediates(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Optional bind group preparation.
+    /// If the bind group's resources aren't on the GPU yet, no bind group is inserted
+    /// and preparation is retried next frame. Other errors move the node to the error state
+    /// until the input changes.
+    /// Not called for changes that only touch struct-level `#[data(binding, Type)]` bindings,
+    /// which are written into the existing bind group instead. `#[uniform]` fields always
+    /// rebuild it, since [`AsBindGroup`] creates their buffers without `COPY_DST`.
+    fn prepare_bind_group(
+        mut commands: Commands,
+        pipeline: Res<ComputePipeline<Self>>,
+        pipeline_cache: Res<PipelineCache>,
+        render_device: Res<RenderDevice>,
+        input: Res<Self>,
+        param: StaticSystemParam<<Self as AsBindGroup>::Param>,
+    ) {
+        let bind_group_failed = matches!(pipeline.error, Some(ComputeShaderError::BindGroup(..)));
+        if bind_group_failed && !input.is_changed() {
+            return;
+        }
+        let layout = pipeline_cache.get_bind_group_layout(&pipeline.layout);
+        match input.unprepared_bind_group(&layout, &render_device, &mut param.into_inner(), false) {
+            Ok(unprepared) => {
+                commands.insert_resource(ComputeShaderBindGroup::<Self>::from_bindings(
+                    unprepared.bindings,
+                    &layout,
+                    &render_device,
+                ));
+                if bind_group_failed {
+                    commands.queue(|world: &mut World| {
+                        world.resource_mut::<ComputePipeline<Self>>().error = None;
+                    });
+                }
+            }
+            Err(AsBindGroupError::RetryNextUpdate) => {}
+            Err(error) => {
+                let error = ComputeShaderError::BindGroup(Self::shader_label(), error.to_string());
+                error!("{error}");
+                commands.queue(move |world: &mut World| {
+                    world.resource_mut::<ComputePipeline<Self>>().error = Some(error);
+                });
+            }
+        }
+    }
+    /// Layout of the bind group at `@group(0)`. Defaults to the [`AsBindGroup`] layout;
+    /// when overriding it, also create the bind group with [`ComputeShader::create_bind_group`].
+    fn shader_bind_group_layout(render_device: &RenderDevice) -> BindGroupLayoutDescriptor {
+        Self::bind_group_layout_descriptor(render_device)
+    }
+    /// Whether to create the bind group with [`ComputeShader::create_bind_group`]
+    /// instead of [`ComputeShader::prepare_bind_group`].
+    fn manual_bind_group() -> bool {
+        false
+    }
+    /// Create the bind group by hand from render world resources, for bindings that
+    /// [`AsBindGroup`] can't express. Only used when [`ComputeShader::manual_bind_group`]
+    /// returns true. Return `None` to retry next frame.
+    fn create_bind_group(
+        &self,
+        _render_device: &RenderDevice,
+        _layout: &BindGroupLayout,
+        _world: &World,
+    ) -> Option<BindGroup> {
+        None
+    }
+    /// Dynamic offsets for the bind group, evaluated from the extracted resource each dispatch.
+    /// Must provide one offset per binding declared with `has_dynamic_offset`.
+    fn dynamic_offsets(&self) -> Vec<u32> {
+        Vec::new()
+    }
+    /// Group index of the [`AsBindGroup`] bind group, e.g. 1 when `@group(0)` is reserved
+    /// for engine-wide globals. Lower groups are filled from
+    /// [`ComputeShader::extra_bind_group_layouts`].
+    fn bind_group_index() -> u32 {
+        0
+    }
+    /// Additional bind group layouts, bound in order at the group indices other than
+    /// [`ComputeShader::bind_group_index`].
+    fn extra_bind_group_layouts(_render_device: &RenderDevice) -> Vec<BindGroupLayoutDescriptor> {
+        Vec::new()
+    }
+    /// Bind groups matching [`ComputeShader::extra_bind_group_layouts`], in order.
+    /// Called in the render world after [`ComputeShader::prepare_bind_group`].
+    /// Return `None` to retry next frame, e.g. while another plugin's resources are not ready.
+    fn extra_bind_groups(_world: &World, _layouts: &[BindGroupLayout]) -> Option<Vec<BindGroup>> {
+        Some(Vec::new())
+    }
+    /// Textures of the camera marked with [`ComputeViewCamera`], as `(binding, texture)` pairs
+    /// in a bind group after all the others, e.g. `@group(1)` without extra bind groups.
+    /// They're rebound every frame, and the dispatch waits while the view has none, e.g. before
+    /// the camera first renders. Order the dispatch after the pass that writes them with
+    /// [`ComputeShaderPlugin::with_render_schedule`] and [`ComputeShaderPlugin::after_set`]:
+    /// the dispatch is then encoded after every system in that set, e.g. after
+    /// `Core3dSystems::MainPass` in `Core3d` for the depth of the main pass.
+    ///
+    /// [`ComputeViewCamera`]: crate::ComputeViewCamera
+    /// [`ComputeShaderPlugin::with_render_schedule`]: crate::ComputeShaderPlugin::with_render_schedule
+    /// [`ComputeShaderPlugin::after_set`]: crate::ComputeShaderPlugin::after_set
+    fn view_textures() -> Vec<(u32, ComputeViewTexture)> {
+        Vec::new()
+    }
+    /// Whether a change from `previous` should restart the run, resetting the readback limit.
+    /// Return false for changes to bookkeeping fields. The bind group is refreshed either way.
+    fn should_reset(&self, _previous: &Self) -> bool {
+        true
+    }
+    /// Optional readbacks.
+    fn readback(&self) -> Option<Readback> {
+        None
+    }
+    /// All readback targets. Defaults to [`ComputeShader::readback`].
+    /// Each target is delivered to its own entity, see [`ComputeShaderReadback::index_of`].
+    ///
+    /// [`ComputeShaderReadback::index_of`]: crate::ComputeShaderReadback::index_of
+    fn readbacks(&self) -> Vec<Readback> {
+        self.readback().into_iter().collect()
+    }
+    /// Bindings of the [`AsBindGroup`] bind group to read back, found from the prepared bind
+    /// group, delivered at the indices after [`ComputeShader::readbacks`].
+    fn readback_bindings() -> Vec<u32> {
+        Vec::new()
+    }
+    /// Storage buffers to copy into images after each dispatch, for display without a readback.
+    /// The images need `TextureUsages::COPY_DST`. See [`buffer_to_image`] for the CPU side.
+    ///
+    /// [`buffer_to_image`]: crate::buffer_to_image
+    fn buffer_images(&self) -> Vec<(Handle<ShaderBuffer>, Handle<Image>)> {
+        Vec::new()
+    }
+    /// Image asset to copy the first readback target into, for texture readbacks, with the row
+    /// padding removed, before [`ComputeShader::on_readback`] runs.
+    fn readback_image_target(&self) -> Option<Handle<Image>> {
+        None
+    }
+    /// Byte range of the buffer readback at `index` in [`ComputeShader::readbacks`] to read
+    /// instead of the whole buffer. Checked when the shader becomes ready and when the input changes.
+    fn readback_range(&self, _index: usize) -> Option<Range<u64>> {
+        None
+    }
+    /// Whether a readback shows the run has converged, completing it with
+    /// [`CompleteComputeShader`]. Defaults to never.
+    ///
+    /// [`CompleteComputeShader`]: crate::CompleteComputeShader
+    fn is_converged(&self, _data: &[u8]) -> bool {
+        false
+    }
+    /// Whether [`ComputeShader::on_readback`] reads the data from the trigger. Return false to
+    /// read it with [`ComputeShaderReadback::data_of`] instead, which is aligned and frees the
+    /// trigger's copy.
+    ///
+    /// [`ComputeShaderReadback::data_of`]: crate::ComputeShaderReadback::data_of
+    fn readback_data_in_trigger() -> bool {
+        true
+    }
+    /// Optional processing on readback. Could write back to the CPU buffer, etc.
+    fn on_readback(_trigger: On<ReadbackComplete>, mut _world: DeferredWorld) {}
+    /// Optional processing on readback with [`Commands`], called right before
+    /// [`ComputeShader::on_readback`].
+    fn on_readback_commands(
+        _data: &[u8],
+        _index: usize,
+        _info: ReadbackInfo,
+        _commands: &mut Commands,
+    ) {
+    }
+    /// Readback of the [`ComputeShaderInstance`] with id `instance`.
+    /// Defaults to [`ComputeShader::on_readback`].
+    fn on_instance_readback(_instance: usize, trigger: On<ReadbackComplete>, world: DeferredWorld) {
+        Self::on_readback(trigger, world);
+    }
+    /// Optional processing when a finite run completes, called in the main world
+    /// once per completion after the final readback has been delivered.
+    fn on_complete(mut _world: DeferredWorld) {}
+    /// Completion of the [`ComputeShaderInstance`] with id `instance`.
+    /// Defaults to [`ComputeShader::on_complete`].
+    fn on_instance_complete(_instance: usize, world: DeferredWorld) {
+        Self::on_complete(world);
+    }
+    /// Optional handling of every status transition, called in the main world in order.
+    fn on_status_change(
+        _old: ComputeNodeStatus,
+        _new: ComputeNodeStatus,
+        mut _world: DeferredWorld,
+    ) {
+    }
+    /// Optional handling of pipeline errors, called in the main world when the node enters
+    /// [`ComputeNodeStatus::Error`]. See [`RetryComputeShader`].
+    ///
+    /// [`RetryComputeShader`]: crate::RetryComputeShader
+    fn on_error(_error: &str, mut _world: DeferredWorld) {}
+}
+
+/// Compute shader whose buffer readbacks decode to a typed output.
+/// Forward [`ComputeShader::on_readback`] to [`TypedComputeShader::on_readback_decoded`] to use it,
+/// texture readbacks should keep using the raw bytes.
+pub trait TypedComputeShader: ComputeShader {
+    /// Element type of the read back buffer, decoded with the std430 layout.
+    type Output: ShaderType + ShaderSize + CreateFrom;
+    /// Optional processing of the decoded readback.
+    fn on_readback_typed(_data: Vec<Self::Output>, mut _world: DeferredWorld) {}
+    /// Decode a readback and pass it to [`TypedComputeShader::on_readback_typed`].
+    fn on_readback_decoded(trigger: On<ReadbackComplete>, world: DeferredWorld) {
+        match decode_readback::<Self::Output>(&trigger.event().data) {
+            Ok(data) => Self::on_readback_typed(data, world),
+            Err(error) => error!("{}: {error}", Self::shader_label()),
+        }
+    }
+    /// Optional processing of the decoded readback with [`Commands`].
+    fn on_readback_typed_commands(
+        _data: Vec<Self::Output>,
+        _info: ReadbackInfo,
+        _commands: &mut Commands,
+    ) {
+    }
+    /// Decode a readback and pass it to [`TypedComputeShader::on_readback_typed_commands`].
+    /// Forward [`ComputeShader::on_readback_commands`] to it to use it.
+    fn on_readback_decoded_commands(
+        data: &[u8],
+        _index: usize,
+        info: ReadbackInfo,
+        commands: &mut Commands,
+    ) {
+        match decode_readback::<Self::Output>(data) {
+            Ok(data) => Self::on_readback_typed_commands(data, info, commands),
+            Err(error) => error!("{}: {error}", Self::shader_label()),
+        }
+    }
+}
+
+/// Independent instance `ID` of the compute shader `S`, e.g. one per terrain chunk.
+/// Each instance is its own resource with its own plugin, pipeline, bind group, state
+/// and readback entities, added with `ComputeShaderPlugin::<ComputeShaderInstance<S, ID>>`.
+/// Everything else is forwarded to `S`, except that readbacks and completions go through
+/// [`ComputeShader::on_instance_readback`] and [`ComputeShader::on_instance_complete`],
+/// [`ComputeShader::shader_label`] names the instance and [`ComputeShader::prepare_bind_group`]
+/// is the default. Hooks of `S` that access the input should look up this resource, not `S`.
+#[derive(Resource, Clone)]
+pub struct ComputeShaderInstance<S: ComputeShader, const ID: usize>(pub S);
+
+impl<S: ComputeShader, const ID: usize> ComputeShaderInstance<S, ID> {
+    /// Id of this instance.
+    pub const ID: usize = ID;
+}
+
+impl<S: ComputeShader, const ID: usize> Deref for ComputeShaderInstance<S, ID> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S: ComputeShader, const ID: usize> DerefMut for ComputeShaderInstance<S, ID> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.0
+    }
+}
+
+impl<S: ComputeShader, const ID: usize> FromWorld for ComputeShaderInstance<S, ID> {
+    fn from_world(world: &mut World) -> Self {
+        Self(S::from_world(world))
+    }
+}
+
+impl<S: ComputeShader, const ID: usize> ExtractResource for ComputeShaderInstance<S, ID> {
+    type Source = Self;
+    fn extract_resource(source: &Self) -> Self {
+        source.clone()
+    }
+}
+
+impl<S: ComputeShader, const ID: usize> AsBindGroup for ComputeShaderInstance<S, ID> {
+    type Data = S::Data;
+    type Param = S::Param;
+
+    fn label() -> &'static str {
+        S::label()
+    }
+
+    fn as_bind_group(
+        &self,
+        layout_descriptor: &BindGroupLayoutDescriptor,
+        render_device: &RenderDevice,
+        pipeline_cache: &PipelineCache,
+        param: &mut SystemParamItem<'_, '_, Self::Param>,
+    ) -> Result<PreparedBindGroup, AsBindGroupError> {
+        self.0
+            .as_bind_group(layout_descriptor, render_device, pipeline_cache, param)
+    }
+
+    fn bind_group_data(&self) -> Self::Data {
+        self.0.bind_group_data()
+    }
+
+    fn unprepared_bind_group(
+        &self,
+        layout: &BindGroupLayout,
+        render_device: &RenderDevice,
+        param: &mut SystemParamItem<'_, '_, Self::Param>,
+        force_no_bindless: bool,
+    ) -> Result<UnpreparedBindGroup, AsBindGroupError> {
+        self.0
+            .unprepared_bind_group(layout, render_device, param, force_no_bindless)
+    }
+
+    fn bind_group_layout_entries(
+        render_device: &RenderDevice,
+        force_no_bindless: bool,
+    ) -> Vec<BindGroupLayoutEntry> {
+        S::bind_group_layout_entries(render_device, force_no_bindless)
+    }
+}
+
+/// Forward [`ComputeShader`] methods of [`ComputeShaderInstance`] to `S`.
+macro_rules! forward_to_shader {
+    () => {};
+    (fn $name:ident(&self $(, $arg:ident: $ty:ty)*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        fn $name(&self $(, $arg: $ty)*) $(-> $ret)? {
+            self.0.$name($($arg),*)
+        }
+        forward_to_shader!($($rest)*);
+    };
+    (fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?; $($rest:tt)*) => {
+        fn $name($($arg: $ty),*) $(-> $ret)? {
+            S::$name($($arg),*)
+        }
+        forward_to_shader!($($rest)*);
+    };
+}
+
+impl<S: ComputeShader, const ID: usize> ComputeShader for ComputeShaderInstance<S, ID> {
+    forward_to_shader! {
+        fn compute_shader() -> ShaderRef;
+        fn dispatch_workgroups(&self) -> UVec3;
+        fn should_dispatch(&self, world: &World) -> bool;
+        fn encode_pre_dispatch(&self, ctx: &mut RenderContext, world: &World);
+        fn encode_post_dispatch(&self, ctx: &mut RenderContext, world: &World);
+        fn indirect_dispatch(&self) -> Option<Handle<ShaderBuffer>>;
+        fn indirect_dispatch_offset(&self) -> u64;
+        fn local_workgroup_size() -> Option<UVec3>;
+        fn output_extent(&self) -> Option<UVec3>;
+        fn entry_point() -> Cow<'static, str>;
+        fn passes() -> Vec<ComputePassSpec>;
+        fn iterations_per_frame(&self) -> u32;
+        fn pipeline_descriptor(
+            layout: Vec<BindGroupLayoutDescriptor>,
+            shader: Handle<Shader>,
+            pass: &ComputePassSpec
+        ) -> ComputePipelineDescriptor;
+        fn shader_defs() -> Vec<ShaderDefVal>;
+        fn pipeline_constants() -> Vec<(String, PipelineConstant)>;
+        fn zero_initialize_workgroup_memory() -> bool;
+        fn immediate_size() -> u32;
+        fn immediates(&self) -> Option<Vec<u8>>;
+        fn shader_bind_group_layout(render_device: &RenderDevice) -> BindGroupLayoutDescriptor;
+        fn manual_bind_group() -> bool;
+        fn create_bind_group(
+            &self,
+            render_device: &RenderDevice,
+            layout: &BindGroupLayout,
+            world: &World
+        ) -> Option<BindGroup>;
+        fn dynamic_offsets(&self) -> Vec<u32>;
+        fn bind_group_index() -> u32;
+        fn extra_bind_group_layouts(render_device: &RenderDevice) -> Vec<BindGroupLayoutDescriptor>;
+        fn extra_bind_groups(world: &World, layouts: &[BindGroupLayout]) -> Option<Vec<BindGroup>>;
+        fn view_textures() -> Vec<(u32, ComputeViewTexture)>;
+        fn readbacks(&self) -> Vec<Readback>;
+        fn readback_bindings() -> Vec<u32>;
+        fn buffer_images(&self) -> Vec<(Handle<ShaderBuffer>, Handle<Image>)>;
+        fn readback_image_target(&self) -> Option<Handle<Image>>;
+        fn readback_range(&self, index: usize) -> Option<Range<u64>>;
+        fn is_converged(&self, data: &[u8]) -> bool;
+        fn readback_data_in_trigger() -> bool;
+        fn on_readback_commands(
+            data: &[u8],
+            index: usize,
+            info: ReadbackInfo,
+            commands: &mut Commands
+        );
+        fn on_status_change(old: ComputeNodeStatus, new: ComputeNodeStatus, world: DeferredWorld);
+        fn on_error(error: &str, world: DeferredWorld);
+    }
+    fn should_reset(&self, previous: &Self) -> bool {
+        self.0.should_reset(&previous.0)
+    }
+    fn on_readback(trigger: On<ReadbackComplete>, world: DeferredWorld) {
+        S::on_instance_readback(ID, trigger, world);
+    }
+    fn on_complete(world: DeferredWorld) {
+        S::on_instance_complete(ID, world);
+    }
+}
+
+/// Number of workgroups of `local_size` needed to cover `extent`.
+///
+/// Panics if a component of `local_size` is zero.
+pub fn workgroup_count(extent: UVec3, local_size: UVec3) -> UVec3 {
+    assert!(
+        local_size.cmpne(UVec3::ZERO).all(),
+        "workgroup size {local_size} has a zero component"
+    );
+    UVec3::new(
+        extent.x.div_ceil(local_size.x),
+        extent.y.div_ceil(local_size.y),
+        extent.z.div_ceil(local_size.z),
+    )
+}
+
+/// One entry point of a multi-pass compute shader. See [`ComputeShader::passes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputePassSpec {
+    /// Name of the compute entry point in the shader.
+    pub entry_point: Cow<'static, str>,
+    /// Number of workgroups dispatched. Defaults to [`ComputeShader::indirect_dispatch`],
+    /// or [`ComputeShader::dispatch_workgroups`].
+    pub workgroups: Option<UVec3>,
+    /// Only dispatch on the first frame of a run, e.g. to seed a simulation.
+    pub once: bool,
+}
+impl ComputePassSpec {
+    /// A pass dispatched every frame.
+    pub fn new(entry_point: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            entry_point: entry_point.into(),
+            workgroups: None,
+            once: false,
+        }
+    }
+    /// A pass dispatched once per run. Runs again after the shader is reset.
+    pub fn once(entry_point: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            once: true,
+            ..Self::new(entry_point)
+        }
+    }
+    /// Dispatch a fixed number of workgroups for this pass.
+    pub fn with_workgroups(mut self, workgroups: UVec3) -> Self {
+        self.workgroups = Some(workgroups);
+        self
+    }
+}
+
+/// Value of a WGSL `override` constant. See [`ComputeShader::pipeline_constants`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PipelineConstant {
+    Bool(bool),
+    I32(i32),
+    U32(u32),
+}
+impl PipelineConstant {
+    /// Shader def setting the constant `key`.
+    pub fn shader_def(self, key: impl Into<String>) -> ShaderDefVal {
+        let key = key.into();
+        match self {
+            Self::Bool(value) => ShaderDefVal::Bool(key, value),
+            Self::I32(value) => ShaderDefVal::Int(key, value),
+            Self::U32(value) => ShaderDefVal::UInt(key, value),
+        }
+    }
+}
+impl From<bool> for PipelineConstant {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+impl From<i32> for PipelineConstant {
+    fn from(value: i32) -> Self {
+        Self::I32(value)
+    }
+}
+impl From<u32> for PipelineConstant {
+    fn from(value: u32) -> Self {
+        Self::U32(value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use bevy::{ecs::world::CommandQueue, render::render_resource::AsBindGroup, utils::default};
+
+    use super::*;
+
+    /// Minimal shader for tests, which never reach the GPU.
+    #[derive(AsBindGroup, Resource, ExtractResource, Clone, Default)]
+    pub(crate) struct TestComputeShader {
+        #[uniform(0)]
+        pub(crate) value: u32,
+    }
+    impl ComputeShader for TestComputeShader {
+        fn compute_shader() -> ShaderRef {
+            "test.wgsl".into()
+        }
+    }
+
+    #[derive(AsBindGroup, Resource, ExtractResource, Clone, Default)]
+    struct ZeroedComputeShader {}
+    impl ComputeShader for ZeroedComputeShader {
+        fn compute_shader() -> ShaderRef {
+            "test.wgsl".into()
+        }
+        fn zero_initialize_workgroup_memory() -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn pipeline_descriptor_zero_initializes_workgroup_memory() {
+        let pass = ComputePassSpec::new("main");
+        let descriptor =
+            TestComputeShader::pipeline_descriptor(Vec::new(), Handle::default(), &pass);
+        assert!(!descriptor.zero_initialize_workgroup_memory);
+        let descriptor =
+            ZeroedComputeShader::pipeline_descriptor(Vec::new(), Handle::default(), &pass);
+        assert!(descriptor.zero_initialize_workgroup_memory);
+    }
+
+    /// Hooks called on [`ForwardedComputeShader`].
+    #[derive(Resource, Default)]
+    struct Calls(Vec<String>);
+
+    /// Overrides every method that runs without a render device.
+    #[derive(AsBindGroup, Resource, ExtractResource, Clone, Default)]
+    struct ForwardedComputeShader {
+        #[uniform(0)]
+        value: u32,
+    }
+    impl ComputeShader for ForwardedComputeShader {
+        fn compute_shader() -> ShaderRef {
+            "forwarded.wgsl".into()
+        }
+        fn dispatch_workgroups(&self) -> UVec3 {
+            UVec3::splat(self.value)
+        }
+        fn should_dispatch(&self, _world: &World) -> bool {
+            false
+        }
+        fn indirect_dispatch(&self) -> Option<Handle<ShaderBuffer>> {
+            Some(Handle::default())
+        }
+        fn indirect_dispatch_offset(&self) -> u64 {
+            12
+        }
+        fn local_workgroup_size() -> Option<UVec3> {
+            Some(UVec3::new(8, 8, 1))
+        }
+        fn output_extent(&self) -> Option<UVec3> {
+            Some(UVec3::ONE)
+        }
+        fn entry_point() -> Cow<'static, str> {
+            "forwarded".into()
+        }
+        fn passes() -> Vec<ComputePassSpec> {
+            vec![ComputePassSpec::once("seed"), ComputePassSpec::new("step")]
+        }
+        fn iterations_per_frame(&self) -> u32 {
+            3
+        }
+        fn pipeline_descriptor(
+            layout: Vec<BindGroupLayoutDescriptor>,
+            shader: Handle<Shader>,
+            pass: &ComputePassSpec,
+        ) -> ComputePipelineDescriptor {
+            ComputePipelineDescriptor {
+                label: Some("forwarded".into()),
+                layout,
+                shader,
+                entry_point: Some(pass.entry_point.clone()),
+                ..default()
+            }
+        }
+        fn shader_defs() -> Vec<ShaderDefVal> {
+            vec!["FORWARDED".into()]
+        }
+        fn pipeline_constants() -> Vec<(String, PipelineConstant)> {
+            vec![("TILE".into(), 4u32.into())]
+        }
+        fn zero_initialize_workgroup_memory() -> bool {
+            true
+        }
+        fn immediate_size() -> u32 {
+            4
+        }
+        fn immediates(&self) -> Option<Vec<u8>> {
+            Some(self.value.to_le_bytes().to_vec())
+        }
+        fn manual_bind_group() -> bool {
+            true
+        }
+        fn dynamic_offsets(&self) -> Vec<u32> {
+            vec![256]
+        }
+        fn bind_group_index() -> u32 {
+            1
+        }
+        fn extra_bind_groups(
+            _world: &World,
+            _layouts: &[BindGroupLayout],
+        ) -> Option<Vec<BindGroup>> {
+            Some(Vec::new())
+        }
+        fn view_textures() -> Vec<(u32, ComputeViewTexture)> {
+            vec![(0, ComputeViewTexture::Depth)]
+        }
+        fn should_reset(&self, previous: &Self) -> bool {
+            self.value > previous.value
+        }
+        fn readbacks(&self) -> Vec<Readback> {
+            vec![Readback::buffer(Handle::default()); 2]
+        }
+        fn readback_bindings() -> Vec<u32> {
+            vec![0]
+        }
+        fn buffer_images(&self) -> Vec<(Handle<ShaderBuffer>, Handle<Image>)> {
+            vec![(Handle::default(), Handle::default())]
+        }
+        fn readback_image_target(&self) -> Option<Handle<Image>> {
+            Some(Handle::default())
+        }
+        fn readback_range(&self, index: usize) -> Option<Range<u64>> {
+            Some(index as u64..16)
+        }
+        fn is_converged(&self, data: &[u8]) -> bool {
+            data.is_empty()
+        }
+        fn readback_data_in_trigger() -> bool {
+            false
+        }
+        fn on_readback_commands(
+            _data: &[u8],
+            index: usize,
+            _info: ReadbackInfo,
+            commands: &mut Commands,
+        ) {
+            commands.queue(move |world: &mut World| {
+                world
+                    .resource_mut::<Calls>()
+                    .0
+                    .push(format!("commands {index}"));
+            });
+        }
+        fn on_instance_readback(
+            instance: usize,
+            _trigger: On<ReadbackComplete>,
+            mut world: DeferredWorld,
+        ) {
+            world
+                .resource_mut::<Calls>()
+                .0
+                .push(format!("readback {instance}"));
+        }
+        fn on_instance_complete(instance: usize, mut world: DeferredWorld) {
+            world
+                .resource_mut::<Calls>()
+                .0
+                .push(format!("complete {instance}"));
+        }
+        fn on_status_change(
+            old: ComputeNodeStatus,
+            new: ComputeNodeStatus,
+            mut world: DeferredWorld,
+        ) {
+            world
+                .resource_mut::<Calls>()
+                .0
+                .push(format!("{old:?} -> {new:?}"));
+        }
+        fn on_error(error: &str, mut world: DeferredWorld) {
+            world.resource_mut::<Calls>().0.push(error.into());
+        }
+    }
+
+    #[test]
+    fn instance_forwards_to_shader() {
+        type Shader = ForwardedComputeShader;
+        type Instance = ComputeShaderInstance<Shader, 2>;
+        let shader = Shader { value: 2 };
+        let instance: Instance = ComputeShaderInstance(shader.clone());
+        let previous: Instance = ComputeShaderInstance(Shader { value: 1 });
+        let mut world = World::new();
+        world.init_resource::<Calls>();
+
+        assert!(
+            matches!(Instance::compute_shader(), ShaderRef::Path(path) if path.to_string() == "forwarded.wgsl")
+        );
+        assert_eq!(instance.dispatch_workgroups(), UVec3::splat(2));
+        assert!(!instance.should_dispatch(&world));
+        assert_eq!(instance.indirect_dispatch(), shader.indirect_dispatch());
+        assert_eq!(instance.indirect_dispatch_offset(), 12);
+        assert_eq!(Instance::local_workgroup_size(), Some(UVec3::new(8, 8, 1)));
+        assert_eq!(instance.output_extent(), Some(UVec3::ONE));
+        assert_eq!(Instance::entry_point(), "forwarded");
+        assert_eq!(
+            Instance::passes(),
+            [ComputePassSpec::once("seed"), ComputePassSpec::new("step")]
+        );
+        assert_eq!(instance.iterations_per_frame(), 3);
+        let pass = ComputePassSpec::new("step");
+        assert_eq!(
+            Instance::pipeline_descriptor(Vec::new(), Handle::default(), &pass).label,
+            Some("forwarded".into())
+        );
+        assert_eq!(Instance::shader_defs(), [ShaderDefVal::from("FORWARDED")]);
+        assert_eq!(
+            Instance::pipeline_constants(),
+            [("TILE".into(), PipelineConstant::U32(4))]
+        );
+        assert!(Instance::zero_initialize_workgroup_memory());
+        assert_eq!(Instance::immediate_size(), 4);
+        assert_eq!(instance.immediates(), Some(2u32.to_le_bytes().to_vec()));
+        assert!(Instance::manual_bind_group());
+        assert_eq!(instance.dynamic_offsets(), [256]);
+        assert_eq!(Instance::bind_group_index(), 1);
+        assert!(Instance::extra_bind_groups(&world, &[]).is_some());
+        assert_eq!(Instance::view_textures(), [(0, ComputeViewTexture::Depth)]);
+        assert!(instance.should_reset(&previous));
+        assert!(!previous.should_reset(&instance));
+        assert_eq!(instance.readbacks().len(), 2);
+        assert_eq!(Instance::readback_bindings(), [0]);
+        assert_eq!(instance.buffer_images(), shader.buffer_images());
+        assert_eq!(
+            instance.readback_image_target(),
+            shader.readback_image_target()
+        );
+        assert_eq!(instance.readback_range(1), Some(1..16));
+        assert!(instance.is_converged(&[]));
+        assert!(!Instance::readback_data_in_trigger());
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        Instance::on_readback_commands(&[], 1, ReadbackInfo::default(), &mut commands);
+        queue.apply(&mut world);
+        let entity = world.spawn_empty().observe(Instance::on_readback).id();
+        world.trigger(ReadbackComplete {
+            entity,
+            data: Vec::new(),
+        });
+        Instance::on_complete((&mut world).into());
+        Instance::on_status_change(
+            ComputeNodeStatus::Loading,
+            ComputeNodeStatus::Ready,
+            (&mut world).into(),
+        );
+        Instance::on_error("error", (&mut world).into());
+        assert_eq!(
+            world.resource::<Calls>().0,
+            [
+                "commands 1",
+                "readback 2",
+                "complete 2",
+                "Loading -> Ready",
+                "error"
+            ]
+        );
+    }
+
+    #[test]
+    fn workgroup_count_rounds_up_per_component() {
+        assert_eq!(
+            workgroup_count(UVec3::new(65, 8, 1), UVec3::new(8, 8, 1)),
+            UVec3::new(9, 1, 1)
+        );
+        assert_eq!(
+            workgroup_count(UVec3::new(u32::MAX, 1, 0), UVec3::new(64, 1, 1)),
+            UVec3::new(u32::MAX / 64 + 1, 1, 0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "zero component")]
+    fn workgroup_count_rejects_zero_local_size() {
+        workgroup_count(UVec3::new(8, 8, 1), UVec3::new(8, 0, 1));
+    }
+}
@@ -0,0 +1,29 @@
+// Box-filters a texture into a smaller one before it's read back, for
+// `ComputeShaderPlugin::with_readback_downsample`.
+@group(0) @binding(0) var source: texture_2d<f32>;
+#ifdef TARGET_RGBA8UNORM
+@group(0) @binding(1) var target: texture_storage_2d<rgba8unorm, write>;
+#else
+@group(0) @binding(1) var target: texture_storage_2d<rgba32float, write>;
+#endif
+
+// Averages the source texels each target texel covers. When the sizes don't divide evenly,
+// neighbors share the texels on their boundary, and targets larger than the source repeat texels.
+@compute @workgroup_size(8, 8, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let target_size = textureDimensions(target);
+    if any(global_id.xy >= target_size) {
+        return;
+    }
+    let source_size = textureDimensions(source);
+    let start = global_id.xy * source_size / target_size;
+    let end = max(((global_id.xy + 1u) * source_size + target_size - 1u) / target_size, start + 1u);
+    var sum = vec4(0.0);
+    for (var y = start.y; y < end.y; y++) {
+        for (var x = start.x; x < end.x; x++) {
+            sum += textureLoad(source, vec2(x, y), 0);
+        }
+    }
+    let count = f32((end.x - start.x) * (end.y - start.y));
+    textureStore(target, global_id.xy, sum / count);
+}
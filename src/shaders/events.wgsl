@@ -0,0 +1,40 @@
+#define_import_path bevy_compute_readback::events
+
+// Header of a buffer of events read back by `add_compute_event_output`, or of a `CountedBuffer`.
+// `count` is the number of events appended this dispatch, including the ones past
+// `capacity`, and is cleared before each dispatch. Events follow at byte 16:
+//
+// struct Collisions {
+//     header: EventHeader,
+//     events: array<Collision>,
+// }
+// @group(0) @binding(1) var<storage, read_write> collisions: Collisions;
+//
+// fn emit_collision(collision: Collision) {
+//     let index = atomicAdd(&collisions.header.count, 1u);
+//     if index < collisions.header.capacity {
+//         collisions.events[index] = collision;
+//     }
+// }
+struct EventHeader {
+    count: atomic<u32>,
+    capacity: u32,
+    _padding: vec2<u32>,
+}
+
+// Header of a buffer of events written by `add_compute_event_input`, bound read only.
+// `count` is the number of events sent since the last dispatch, up to `capacity`.
+// Events follow at byte 16:
+//
+// struct Paints {
+//     header: InputEventHeader,
+//     events: array<Paint>,
+// }
+// @group(0) @binding(1) var<storage, read> paints: Paints;
+//
+// for (var index = 0u; index < paints.header.count; index++) { ... }
+struct InputEventHeader {
+    count: u32,
+    capacity: u32,
+    _padding: vec2<u32>,
+}
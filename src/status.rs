@@ -0,0 +1,474 @@
+//! Status of each compute shader, mirrored to the main world.
+
+use std::{
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use bevy::{
+    ecs::{
+        change_detection::DetectChangesMut,
+        message::{Message, Messages},
+        resource::Resource,
+        system::{Res, ResMut},
+        world::{DeferredWorld, World},
+    },
+    render::MainWorld,
+    state::state::{NextState, States},
+};
+
+use crate::{ComputeNode, ComputeShader, ComputeShaderReadback, ReadbackLimit};
+
+/// Enum representing possible compute node states.
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ComputeNodeStatus {
+    #[default]
+    Loading,
+    Init,
+    Ready,
+    /// Dispatching is suspended by [`ComputePaused`] or [`ComputeShaderPlugin::run_if`].
+    /// Only entered from `Ready`, and left back to it on resume.
+    ///
+    /// [`ComputePaused`]: crate::ComputePaused
+    /// [`ComputeShaderPlugin::run_if`]: crate::ComputeShaderPlugin::run_if
+    Paused,
+    Completed,
+    Error,
+}
+/// Tracks compute node state.
+/// In render world, this is stored as a resource which is later extracted to main.
+/// In main world, this is a state so systems can react to state entry.
+#[derive(States, Resource, Clone)]
+pub struct ComputeNodeState<S: ComputeShader> {
+    pub(crate) status: ComputeNodeStatus,
+    /// Pipeline error message, set when `status` is [`ComputeNodeStatus::Error`].
+    pub(crate) error: Option<String>,
+    /// Transitions in the render world since the last extraction, see [`StatusTransitions`].
+    transitions: Vec<(ComputeNodeStatus, ComputeNodeStatus, Option<String>)>,
+    _marker: PhantomData<S>,
+}
+// Implemented by hand so that `S` doesn't need to implement `Debug`.
+impl<S: ComputeShader> Debug for ComputeNodeState<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComputeNodeState")
+            .field("shader", &S::shader_label())
+            .field("status", &self.status)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+impl<S: ComputeShader> Hash for ComputeNodeState<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.status.hash(state);
+    }
+}
+impl<S: ComputeShader> PartialEq for ComputeNodeState<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.status == other.status
+    }
+}
+impl<S: ComputeShader> Eq for ComputeNodeState<S> {}
+impl<S: ComputeShader> From<ComputeNodeStatus> for ComputeNodeState<S> {
+    fn from(value: ComputeNodeStatus) -> Self {
+        Self {
+            status: value,
+            error: None,
+            transitions: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> Default for ComputeNodeState<S> {
+    fn default() -> Self {
+        Self {
+            status: ComputeNodeStatus::default(),
+            error: None,
+            transitions: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> ComputeNodeState<S> {
+    /// Current status of the compute node.
+    pub fn status(&self) -> ComputeNodeStatus {
+        self.status
+    }
+    /// Pipeline error message, if the node is in [`ComputeNodeStatus::Error`].
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+    /// Set the status in the render world, queueing the transition for the main world.
+    pub(crate) fn set(&mut self, status: ComputeNodeStatus, error: Option<String>) {
+        if self.status != status {
+            self.transitions.push((self.status, status, error.clone()));
+        }
+        self.status = status;
+        self.error = error;
+    }
+    /// Extracts compute node state resource into a state
+    /// that systems can react to in the main world.
+    /// Each status transition is also queued so none are lost before the main world handles them,
+    /// even several in a frame.
+    pub(crate) fn extract_to_main(
+        mut compute_state: ResMut<ComputeNodeState<S>>,
+        mut world: ResMut<MainWorld>,
+    ) {
+        let transitions = std::mem::take(&mut compute_state.bypass_change_detection().transitions);
+        world
+            .resource_mut::<NextState<ComputeNodeState<S>>>()
+            .set(compute_state.clone());
+        world
+            .resource_mut::<StatusTransitions<S>>()
+            .transitions
+            .extend(transitions);
+    }
+}
+
+/// Progress of the current run of `S`, e.g. for a progress bar. Maintained by the render world
+/// and extracted to the main world like the [`ComputeNodeState`], so it lags a frame behind.
+/// Zeroed before the first dispatch and whenever the run restarts.
+#[derive(Resource, Clone, Debug)]
+pub struct ComputeProgress<S: ComputeShader> {
+    /// Dispatches in the current run, including warmup.
+    pub dispatched: usize,
+    /// Readbacks of the current run delivered to the main world.
+    pub delivered: usize,
+    /// Count that completes the run: dispatches including warmup with
+    /// [`ReadbackLimit::Finite`], or readbacks of all targets with
+    /// [`ReadbackLimit::FiniteReadbacks`]. `None` for other limits.
+    pub limit: Option<usize>,
+    /// Whether `limit` counts readbacks.
+    limits_readbacks: bool,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for ComputeProgress<S> {
+    fn default() -> Self {
+        Self {
+            dispatched: 0,
+            delivered: 0,
+            limit: None,
+            limits_readbacks: false,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> PartialEq for ComputeProgress<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dispatched == other.dispatched
+            && self.delivered == other.delivered
+            && self.limit == other.limit
+            && self.limits_readbacks == other.limits_readbacks
+    }
+}
+impl<S: ComputeShader> ComputeProgress<S> {
+    /// Fraction of the run done, from 0 to 1, or `None` without a limit.
+    pub fn fraction(&self) -> Option<f32> {
+        let done = if self.limits_readbacks {
+            self.delivered
+        } else {
+            self.dispatched
+        };
+        self.limit
+            .map(|limit| (done as f32 / limit.max(1) as f32).min(1.0))
+    }
+    /// Progress of the node's current run.
+    pub(crate) fn of(node: &ComputeNode<S>) -> Self {
+        let (limit, limits_readbacks) = match node.limit {
+            ReadbackLimit::Finite(limit) => (Some(node.warmup + limit), false),
+            ReadbackLimit::FiniteReadbacks(limit) => (Some(limit * node.readback_targets), true),
+            _ => (None, false),
+        };
+        Self {
+            dispatched: node.count,
+            delivered: node.run_readbacks().1,
+            limit,
+            limits_readbacks,
+            _marker: PhantomData,
+        }
+    }
+    /// Copy the progress to the main world.
+    pub(crate) fn extract_to_main(progress: Res<Self>, mut world: ResMut<MainWorld>) {
+        world.insert_resource(progress.clone());
+    }
+}
+
+/// Message written in the main world for each status transition of `S`, in order, even when
+/// the [`ComputeNodeState`] only shows the last one.
+#[derive(Message, Debug)]
+pub struct ComputeStatusChanged<S: ComputeShader> {
+    pub from: ComputeNodeStatus,
+    pub to: ComputeNodeStatus,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Clone for ComputeStatusChanged<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<S: ComputeShader> Copy for ComputeStatusChanged<S> {}
+
+/// Error message of `S` while it's in [`ComputeNodeStatus::Error`], including its causes such
+/// as the shader compiler's diagnostics.
+#[derive(Resource, Clone, Debug)]
+pub struct ComputeError<S: ComputeShader>(pub String, pub PhantomData<S>);
+impl<S: ComputeShader> ComputeError<S> {
+    /// Error with `message`, e.g. to insert it in tests.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into(), PhantomData)
+    }
+    /// Error message, with one cause per line.
+    pub fn message(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Display an error followed by its causes, one per line.
+pub(crate) fn error_report(error: &dyn std::error::Error) -> String {
+    let mut report = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+        // Transparent errors repeat their source's message.
+        if !report.ends_with(&cause_message) {
+            report.push_str("\ncaused by: ");
+            report.push_str(&cause_message);
+        }
+        source = cause.source();
+    }
+    report
+}
+
+/// Status transitions extracted from the render world, waiting to be handled in the main world.
+#[derive(Resource)]
+pub(crate) struct StatusTransitions<S: ComputeShader> {
+    /// Old and new status, with the pipeline error when entering [`ComputeNodeStatus::Error`].
+    transitions: Vec<(ComputeNodeStatus, ComputeNodeStatus, Option<String>)>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for StatusTransitions<S> {
+    fn default() -> Self {
+        Self {
+            transitions: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> StatusTransitions<S> {
+    /// Handle each queued transition in order: insert or remove the readbacks, write the
+    /// [`ComputeStatusChanged`] message, then call the shader's hooks.
+    pub(crate) fn on_status_change(world: &mut World) {
+        let transitions = std::mem::take(&mut world.resource_mut::<Self>().transitions);
+        for (old, new, error) in transitions {
+            if new == ComputeNodeStatus::Error {
+                world.insert_resource(ComputeError::<S>::new(error.clone().unwrap_or_default()));
+            } else if old == ComputeNodeStatus::Error {
+                world.remove_resource::<ComputeError<S>>();
+            }
+            if let Some(mut messages) =
+                world.get_resource_mut::<Messages<ComputeStatusChanged<S>>>()
+            {
+                messages.write(ComputeStatusChanged {
+                    from: old,
+                    to: new,
+                    _marker: PhantomData,
+                });
+            }
+            match new {
+                // Resuming keeps the readbacks inserted when the shader became ready.
+                ComputeNodeStatus::Ready
+                    if old != ComputeNodeStatus::Paused && world.contains_resource::<S>() =>
+                {
+                    let _ = world.run_system_cached(ComputeShaderReadback::<S>::on_shader_ready);
+                }
+                ComputeNodeStatus::Completed => {
+                    let _ = world.run_system_cached(ComputeShaderReadback::<S>::on_shader_complete);
+                }
+                ComputeNodeStatus::Error => {
+                    S::on_error(&error.unwrap_or_default(), DeferredWorld::from(&mut *world));
+                }
+                _ => {}
+            }
+            S::on_status_change(old, new, DeferredWorld::from(&mut *world));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{Handle, uuid_handle},
+        render::{
+            extract_resource::ExtractResource,
+            render_resource::{AsBindGroup, PipelineCache},
+        },
+        shader::{Shader, ShaderRef},
+        utils::default,
+    };
+
+    use super::*;
+    use crate::{
+        ComputePipeline, pipeline::tests::noop_render_world, shader::tests::TestComputeShader,
+    };
+
+    type Progress = ComputeProgress<TestComputeShader>;
+
+    #[test]
+    fn progress_counts_dispatches_or_readbacks() {
+        let mut node = ComputeNode::<TestComputeShader>::default();
+        (node.limit, node.warmup, node.count) = (ReadbackLimit::Finite(3), 1, 2);
+        let progress = Progress::of(&node);
+        assert_eq!((progress.dispatched, progress.limit), (2, Some(4)));
+        assert_eq!(progress.fraction(), Some(0.5));
+
+        (node.limit, node.readback_targets) = (ReadbackLimit::FiniteReadbacks(2), 2);
+        (node.requested, node.delivered) = (4, 1);
+        let progress = Progress::of(&node);
+        assert_eq!(progress.limit, Some(4));
+        assert_eq!(progress.fraction(), Some(0.25));
+
+        node.limit = ReadbackLimit::Infinite;
+        assert_eq!(Progress::of(&node).fraction(), None);
+        // Past the limit, e.g. while the completion is extracted.
+        let progress = Progress {
+            dispatched: 3,
+            limit: Some(2),
+            ..default()
+        };
+        assert_eq!(progress.fraction(), Some(1.0));
+        let progress = Progress {
+            limit: Some(0),
+            ..default()
+        };
+        assert_eq!(progress.fraction(), Some(0.0));
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    enum TestError {
+        #[error("failed to load")]
+        Load(#[source] TestCause),
+        #[error("failed to load: {0}")]
+        Repeated(#[source] TestCause),
+    }
+    #[derive(thiserror::Error, Debug)]
+    #[error("file not found")]
+    struct TestCause;
+
+    #[test]
+    fn error_report_lists_causes_once() {
+        assert_eq!(
+            error_report(&TestError::Load(TestCause)),
+            "failed to load\ncaused by: file not found"
+        );
+        assert_eq!(
+            error_report(&TestError::Repeated(TestCause)),
+            "failed to load: file not found"
+        );
+    }
+
+    #[test]
+    fn transitions_are_handled_in_order() {
+        use ComputeNodeStatus::*;
+        let mut world = World::new();
+        world.insert_resource(ComputeError::<TestComputeShader>::new("stale"));
+        world.init_resource::<StatusTransitions<TestComputeShader>>();
+        world.init_resource::<Messages<ComputeStatusChanged<TestComputeShader>>>();
+        world
+            .resource_mut::<StatusTransitions<TestComputeShader>>()
+            .transitions
+            .extend([
+                (Loading, Error, Some("no shader".into())),
+                (Error, Loading, None),
+                (Loading, Init, None),
+                (Init, Error, Some("no pipeline".into())),
+            ]);
+        StatusTransitions::<TestComputeShader>::on_status_change(&mut world);
+        let changes: Vec<_> = world
+            .resource_mut::<Messages<ComputeStatusChanged<TestComputeShader>>>()
+            .drain()
+            .map(|changed| (changed.from, changed.to))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Loading, Error),
+                (Error, Loading),
+                (Loading, Init),
+                (Init, Error)
+            ]
+        );
+        // The error of the last transition into `Error` is kept until it's left.
+        let error = world.resource::<ComputeError<TestComputeShader>>();
+        assert_eq!(error.message(), "no pipeline");
+        world
+            .resource_mut::<StatusTransitions<TestComputeShader>>()
+            .transitions
+            .push((Error, Loading, None));
+        StatusTransitions::<TestComputeShader>::on_status_change(&mut world);
+        assert!(!world.contains_resource::<ComputeError<TestComputeShader>>());
+    }
+
+    /// Shader without bindings, whose pipeline compiles on the noop device.
+    const EMPTY_SHADER: Handle<Shader> = uuid_handle!("8d5e2c17-4b3a-4f6e-9c81-2a7d0b5e3f94");
+
+    #[derive(AsBindGroup, Resource, ExtractResource, Clone, Default)]
+    struct EmptyComputeShader {}
+    impl ComputeShader for EmptyComputeShader {
+        fn compute_shader() -> ShaderRef {
+            EMPTY_SHADER.into()
+        }
+    }
+
+    #[test]
+    fn extract_to_main_keeps_transitions_of_one_frame() {
+        type Node = ComputeNode<EmptyComputeShader>;
+        let mut world = noop_render_world();
+        world.resource_mut::<PipelineCache>().set_shader(
+            EMPTY_SHADER.id(),
+            Shader::from_wgsl("@compute @workgroup_size(1) fn main() {}", "empty.wgsl"),
+        );
+        world.init_resource::<ComputePipeline<EmptyComputeShader>>();
+        world.resource_mut::<PipelineCache>().process_queue();
+        let mut node = Node::default();
+        node.limit = ReadbackLimit::Finite(1);
+        world.insert_resource(node);
+        world.init_resource::<EmptyComputeShader>();
+        world.init_resource::<ComputeNodeState<EmptyComputeShader>>();
+        world.init_resource::<MainWorld>();
+        let mut main_world = world.resource_mut::<MainWorld>();
+        main_world.init_resource::<NextState<ComputeNodeState<EmptyComputeShader>>>();
+        main_world.init_resource::<StatusTransitions<EmptyComputeShader>>();
+        main_world.init_resource::<Messages<ComputeStatusChanged<EmptyComputeShader>>>();
+        let reset = world.register_system(Node::reset_on_change);
+        let update = world.register_system(Node::update);
+        let extract =
+            world.register_system(ComputeNodeState::<EmptyComputeShader>::extract_to_main);
+        let changes = |world: &mut World, systems: &[_]| {
+            for system in systems {
+                world.run_system(*system).unwrap();
+            }
+            world.run_system(extract).unwrap();
+            let mut main_world = world.resource_mut::<MainWorld>();
+            StatusTransitions::<EmptyComputeShader>::on_status_change(&mut main_world);
+            main_world
+                .resource_mut::<Messages<ComputeStatusChanged<EmptyComputeShader>>>()
+                .drain()
+                .map(|changed| (changed.from, changed.to))
+                .collect::<Vec<_>>()
+        };
+        use ComputeNodeStatus::*;
+        assert_eq!(changes(&mut world, &[reset, update]), [(Loading, Ready)]);
+        assert_eq!(changes(&mut world, &[update]), [(Ready, Completed)]);
+        // A new input resets to `Loading` and the next update is `Ready` again, in one frame.
+        assert_eq!(
+            changes(&mut world, &[reset, update]),
+            [(Completed, Loading), (Loading, Ready)]
+        );
+        assert!(matches!(
+            world
+                .resource::<MainWorld>()
+                .resource::<NextState<ComputeNodeState<EmptyComputeShader>>>(),
+            NextState::Pending(state) if state.status() == Ready
+        ));
+    }
+}
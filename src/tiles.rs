@@ -0,0 +1,477 @@
+//! Progressive readback of a texture in tiles.
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::{
+    asset::{Handle, RenderAssetUsages},
+    ecs::{
+        message::{Message, Messages},
+        resource::Resource,
+        world::{DeferredWorld, World},
+    },
+    image::Image,
+    math::{UVec2, UVec3},
+    render::{
+        extract_resource::ExtractResource,
+        render_asset::RenderAssets,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        renderer::RenderContext,
+        storage::ShaderBuffer,
+        texture::GpuImage,
+    },
+};
+
+use crate::{
+    ComputeShader, ReadbackData, ReadbackDecodeError, ReadbackInfo, ReadbackRegion,
+    TextureRowLayout,
+};
+
+/// Progressive readback of a large texture one tile of a grid at a time, copied into `buffer`
+/// and delivered as a [`ReadbackTile`]. See [`TileAssembler`] to put the tiles back together.
+/// Insert and update it in the main world like [`ReadbackRegion`].
+#[derive(Resource, ExtractResource, Clone, Debug)]
+pub struct ReadbackTiles<S: ComputeShader> {
+    pub texture: Handle<Image>,
+    /// Number of tiles across and down, at most one per texel.
+    pub grid: UVec2,
+    pub mip_level: u32,
+    /// Buffer the tiles are copied into. See [`ReadbackTiles::staging_buffer`].
+    pub buffer: Handle<ShaderBuffer>,
+    /// Index of the readback of `buffer` in [`ComputeShader::readbacks`].
+    pub index: usize,
+    pub _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> ReadbackTiles<S> {
+    /// Read back `texture` one tile of `grid` at a time, through `buffer` at index 0.
+    pub fn new(texture: Handle<Image>, grid: UVec2, buffer: Handle<ShaderBuffer>) -> Self {
+        Self {
+            texture,
+            grid,
+            mip_level: 0,
+            buffer,
+            index: 0,
+            _marker: PhantomData,
+        }
+    }
+    /// Set [`ReadbackTiles::mip_level`].
+    pub fn with_mip_level(mut self, mip_level: u32) -> Self {
+        self.mip_level = mip_level;
+        self
+    }
+    /// Set [`ReadbackTiles::index`].
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = index;
+        self
+    }
+    /// Buffer large enough for the tiles of a `size` texture of `format` in `grid`.
+    pub fn staging_buffer(size: UVec2, grid: UVec2, format: TextureFormat) -> ShaderBuffer {
+        let grid = grid.max(UVec2::ONE);
+        let tile = UVec3::new(size.x.div_ceil(grid.x), size.y.div_ceil(grid.y), 1);
+        ReadbackRegion::<S>::staging_buffer(tile, format)
+    }
+    /// Grid and size of the texture at the mip level, once it's prepared.
+    pub(crate) fn layout(world: &World) -> Option<(UVec2, UVec2)> {
+        let tiles = world.get_resource::<Self>()?;
+        let image = world
+            .resource::<RenderAssets<GpuImage>>()
+            .get(&tiles.texture)?;
+        let size = UVec2::new(
+            (image.texture.size().width >> tiles.mip_level).max(1),
+            (image.texture.size().height >> tiles.mip_level).max(1),
+        );
+        Some((tiles.grid.clamp(UVec2::ONE, size), size))
+    }
+    /// Copy the tile into the buffer, after the dispatch.
+    pub(crate) fn encode(&self, tile: ReadbackTileInfo, ctx: &mut RenderContext, world: &World) {
+        ReadbackRegion::<S>::new(
+            self.texture.clone(),
+            tile.origin.extend(0),
+            tile.size.extend(1),
+            self.buffer.clone(),
+        )
+        .with_mip_level(self.mip_level)
+        .encode(ctx, world);
+    }
+    /// Write the tile read back at [`ReadbackTiles::index`], and the coverage once every tile
+    /// of the cycle arrived.
+    pub(crate) fn deliver(
+        world: &mut DeferredWorld,
+        index: usize,
+        info: ReadbackInfo,
+        tile: ReadbackTileInfo,
+        data: &ReadbackData,
+    ) {
+        if world
+            .get_resource::<Self>()
+            .is_none_or(|tiles| tiles.index != index)
+        {
+            return;
+        }
+        if let Some(mut messages) = world.get_resource_mut::<Messages<ReadbackTile<S>>>() {
+            messages.write(ReadbackTile {
+                data: data.clone(),
+                tile,
+                info,
+                _marker: PhantomData,
+            });
+        }
+        let Some(mut coverage) = world.get_resource_mut::<TileCoverage<S>>() else {
+            return;
+        };
+        let coverage = &mut *coverage;
+        let key = (info.run, tile.grid, tile.texture_size);
+        if coverage.key != Some(key) {
+            coverage.key = Some(key);
+            coverage.seen = vec![false; (tile.grid.x * tile.grid.y) as usize];
+            coverage.remaining = coverage.seen.len();
+        }
+        let seen = &mut coverage.seen[(tile.tile.y * tile.grid.x + tile.tile.x) as usize];
+        if !std::mem::replace(seen, true) {
+            coverage.remaining -= 1;
+        }
+        if coverage.remaining > 0 {
+            return;
+        }
+        coverage.seen.fill(false);
+        coverage.remaining = coverage.seen.len();
+        coverage.cycles += 1;
+        let cycle = coverage.cycles;
+        if let Some(mut messages) = world.get_resource_mut::<Messages<ReadbackTilesCovered<S>>>() {
+            messages.write(ReadbackTilesCovered {
+                run: info.run,
+                cycle,
+                _marker: PhantomData,
+            });
+        }
+    }
+}
+
+/// Tile of a [`ReadbackTiles`] readback, see [`ReadbackInfo::tile`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadbackTileInfo {
+    /// Column and row of the tile.
+    pub tile: UVec2,
+    /// First texel of the tile.
+    pub origin: UVec2,
+    /// Size of the tile in texels. Tiles differ by a texel when the texture isn't a multiple
+    /// of the grid.
+    pub size: UVec2,
+    /// Number of tiles across and down.
+    pub grid: UVec2,
+    /// Size of the whole texture at the mip level read.
+    pub texture_size: UVec2,
+}
+impl ReadbackTileInfo {
+    /// The `index`th tile of `grid`, in rows.
+    pub(crate) fn new(index: u32, grid: UVec2, texture_size: UVec2) -> Self {
+        let tile = UVec2::new(index % grid.x, index / grid.x);
+        let origin = tile * texture_size / grid;
+        let end = (tile + 1) * texture_size / grid;
+        Self {
+            tile,
+            origin,
+            size: end - origin,
+            grid,
+            texture_size,
+        }
+    }
+}
+
+/// Message written for each tile of [`ReadbackTiles`].
+#[derive(Message, Debug)]
+pub struct ReadbackTile<S: ComputeShader> {
+    /// Raw bytes of the tile, with rows padded like texture readbacks.
+    pub data: ReadbackData,
+    pub tile: ReadbackTileInfo,
+    /// Dispatch that produced the tile.
+    pub info: ReadbackInfo,
+    _marker: PhantomData<S>,
+}
+
+/// Message written each time every tile of [`ReadbackTiles`] was delivered since the last one,
+/// or since the run or grid changed.
+#[derive(Message, Debug)]
+pub struct ReadbackTilesCovered<S: ComputeShader> {
+    /// Run the tiles were delivered in, see [`ReadbackInfo::run`].
+    pub run: usize,
+    /// Number of full cycles so far, counting from 1.
+    pub cycle: usize,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Clone for ReadbackTilesCovered<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<S: ComputeShader> Copy for ReadbackTilesCovered<S> {}
+
+/// Tile of [`ReadbackTiles`] read back this frame, and the next one, in the render world.
+#[derive(Resource)]
+pub(crate) struct TileCursor<S: ComputeShader> {
+    next: u32,
+    pub(crate) tile: Option<ReadbackTileInfo>,
+    /// Run, grid and texture size the cycle is for.
+    key: Option<(usize, UVec2, UVec2)>,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for TileCursor<S> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            tile: None,
+            key: None,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<S: ComputeShader> TileCursor<S> {
+    /// Pick the tile to read back this frame of `run`, or none when `read` is false.
+    /// Restarts the cycle on a new run, or when the grid or texture size changed.
+    pub(crate) fn advance(world: &mut World, read: bool, run: usize) -> Option<ReadbackTileInfo> {
+        let layout = ReadbackTiles::<S>::layout(world).filter(|_| read);
+        let mut cursor = world.get_resource_mut::<Self>()?;
+        let Some((grid, texture_size)) = layout else {
+            cursor.tile = None;
+            return None;
+        };
+        let key = (run, grid, texture_size);
+        if cursor.key != Some(key) {
+            cursor.key = Some(key);
+            cursor.next = 0;
+        }
+        let index = cursor.next % (grid.x * grid.y);
+        cursor.next = index + 1;
+        cursor.tile = Some(ReadbackTileInfo::new(index, grid, texture_size));
+        cursor.tile
+    }
+}
+
+/// Tiles of [`ReadbackTiles`] delivered in the current cycle.
+#[derive(Resource)]
+pub(crate) struct TileCoverage<S: ComputeShader> {
+    /// Run, grid and texture size the cycle is for.
+    key: Option<(usize, UVec2, UVec2)>,
+    seen: Vec<bool>,
+    remaining: usize,
+    cycles: usize,
+    _marker: PhantomData<S>,
+}
+impl<S: ComputeShader> Default for TileCoverage<S> {
+    fn default() -> Self {
+        Self {
+            key: None,
+            seen: Vec::new(),
+            remaining: 0,
+            cycles: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Texture put back together on the CPU from [`ReadbackTile`]s, keeping the latest data of
+/// every tile, e.g. as a persistent copy of a texture that changes slowly.
+#[derive(Clone, Debug)]
+pub struct TileAssembler {
+    /// Size in texels, following the tiles inserted.
+    pub size: UVec2,
+    pub format: TextureFormat,
+    /// Tightly packed texels as in `Image::data`, zeroed until their tile arrives.
+    pub data: Vec<u8>,
+}
+impl TileAssembler {
+    /// Empty texture of `format`, sized by the first tile.
+    pub fn new(format: TextureFormat) -> Self {
+        Self {
+            size: UVec2::ZERO,
+            format,
+            data: Vec::new(),
+        }
+    }
+    /// Copy a tile into place, removing its row padding. Clears the texture first when the
+    /// tile is of a texture of another size.
+    pub fn insert(
+        &mut self,
+        data: &[u8],
+        tile: ReadbackTileInfo,
+    ) -> Result<(), ReadbackDecodeError> {
+        if self.format.block_dimensions() != (1, 1) {
+            return Err(ReadbackDecodeError::TextureFormat(self.format));
+        }
+        let layout = TextureRowLayout::new(
+            Extent3d {
+                width: tile.size.x,
+                height: tile.size.y,
+                depth_or_array_layers: 1,
+            },
+            self.format,
+        )?;
+        layout.check(data)?;
+        let texel = self.format.block_copy_size(None).unwrap_or(0) as usize;
+        if self.size != tile.texture_size {
+            self.size = tile.texture_size;
+            self.data = vec![0; self.size.x as usize * self.size.y as usize * texel];
+        }
+        for (y, row) in layout.rows(data).enumerate() {
+            let start = ((tile.origin.y as usize + y) * self.size.x as usize
+                + tile.origin.x as usize)
+                * texel;
+            self.data[start..][..row.len()].copy_from_slice(row);
+        }
+        Ok(())
+    }
+    /// Copy of the texture as an image, e.g. for display.
+    pub fn to_image(&self) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.data.clone(),
+            self.format,
+            RenderAssetUsages::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::default;
+
+    use super::*;
+    use crate::shader::tests::TestComputeShader;
+
+    type Tiles = ReadbackTiles<TestComputeShader>;
+
+    #[test]
+    fn tiles_cover_the_texture() {
+        for (grid, texture_size) in [
+            (UVec2::new(2, 2), UVec2::new(4, 4)),
+            (UVec2::new(3, 2), UVec2::new(7, 5)),
+            (UVec2::new(4, 1), UVec2::new(4, 3)),
+        ] {
+            let mut covered = vec![0; (texture_size.x * texture_size.y) as usize];
+            for index in 0..grid.x * grid.y {
+                let tile = ReadbackTileInfo::new(index, grid, texture_size);
+                assert_eq!(tile.tile.y * grid.x + tile.tile.x, index);
+                // Tiles differ by at most a texel.
+                let even = texture_size / grid;
+                assert!(tile.size.cmpge(even).all() && tile.size.cmple(even + 1).all());
+                for y in tile.origin.y..tile.origin.y + tile.size.y {
+                    for x in tile.origin.x..tile.origin.x + tile.size.x {
+                        covered[(y * texture_size.x + x) as usize] += 1;
+                    }
+                }
+            }
+            // Every texel is in exactly one tile.
+            assert!(covered.iter().all(|count| *count == 1));
+        }
+    }
+
+    #[test]
+    fn staging_buffer_fits_the_largest_tile() {
+        let buffer = Tiles::staging_buffer(
+            UVec2::new(5, 4),
+            UVec2::new(2, 2),
+            TextureFormat::Rgba8Unorm,
+        );
+        // Two rows of 3 texels, the first padded to 256 bytes.
+        assert_eq!(buffer.buffer_description.size, 256 + 12);
+        // An empty grid is read back whole.
+        let buffer = Tiles::staging_buffer(UVec2::new(5, 1), UVec2::ZERO, TextureFormat::R8Unorm);
+        assert_eq!(buffer.buffer_description.size, 5);
+    }
+
+    #[test]
+    fn coverage_completes_once_every_tile_arrived() {
+        let mut world = World::new();
+        world.insert_resource(Tiles::new(
+            Handle::default(),
+            UVec2::new(2, 1),
+            Handle::default(),
+        ));
+        world.init_resource::<TileCoverage<TestComputeShader>>();
+        world.init_resource::<Messages<ReadbackTile<TestComputeShader>>>();
+        world.init_resource::<Messages<ReadbackTilesCovered<TestComputeShader>>>();
+        let data = ReadbackData::default();
+        let deliver = |world: &mut World, run: usize, index: u32| {
+            let info = ReadbackInfo { run, ..default() };
+            let tile = ReadbackTileInfo::new(index, UVec2::new(2, 1), UVec2::new(4, 4));
+            Tiles::deliver(&mut DeferredWorld::from(world), 0, info, tile, &data);
+        };
+        let covered = |world: &mut World| {
+            world
+                .resource_mut::<Messages<ReadbackTilesCovered<TestComputeShader>>>()
+                .drain()
+                .map(|covered| (covered.run, covered.cycle))
+                .collect::<Vec<_>>()
+        };
+        deliver(&mut world, 0, 0);
+        deliver(&mut world, 0, 0);
+        assert!(covered(&mut world).is_empty());
+        deliver(&mut world, 0, 1);
+        assert_eq!(covered(&mut world), [(0, 1)]);
+        deliver(&mut world, 0, 1);
+        deliver(&mut world, 0, 0);
+        assert_eq!(covered(&mut world), [(0, 2)]);
+        // A new run starts the cycle over.
+        deliver(&mut world, 0, 1);
+        deliver(&mut world, 1, 0);
+        deliver(&mut world, 1, 1);
+        assert_eq!(covered(&mut world), [(1, 3)]);
+        // Readbacks at other indices aren't tiles.
+        let tile = ReadbackTileInfo::new(0, UVec2::new(2, 1), UVec2::new(4, 4));
+        Tiles::deliver(
+            &mut DeferredWorld::from(&mut world),
+            1,
+            default(),
+            tile,
+            &data,
+        );
+        assert_eq!(
+            world
+                .resource::<Messages<ReadbackTile<TestComputeShader>>>()
+                .len(),
+            8
+        );
+    }
+
+    #[test]
+    fn tile_assembler_puts_tiles_back_together() {
+        let (grid, texture_size) = (UVec2::new(2, 2), UVec2::new(5, 3));
+        let texels: Vec<u8> = (0..texture_size.x * texture_size.y)
+            .map(|index| index as u8)
+            .collect();
+        let mut assembler = TileAssembler::new(TextureFormat::R8Unorm);
+        for index in 0..grid.x * grid.y {
+            let tile = ReadbackTileInfo::new(index, grid, texture_size);
+            // Rows of the tile, padded as read back.
+            let mut data = Vec::new();
+            for y in tile.origin.y..tile.origin.y + tile.size.y {
+                let start = (y * texture_size.x + tile.origin.x) as usize;
+                data.resize(data.len().next_multiple_of(256), 0xff);
+                data.extend_from_slice(&texels[start..][..tile.size.x as usize]);
+            }
+            assembler.insert(&data, tile).unwrap();
+        }
+        assert_eq!(assembler.size, texture_size);
+        assert_eq!(assembler.data, texels);
+
+        // A tile of another texture size clears the texture.
+        let tile = ReadbackTileInfo::new(0, UVec2::ONE, UVec2::new(2, 1));
+        assembler.insert(&[1, 2], tile).unwrap();
+        assert_eq!(
+            (assembler.size, assembler.data.as_slice()),
+            (tile.size, &[1, 2][..])
+        );
+        assert!(assembler.insert(&[1], tile).is_err());
+        let mut compressed = TileAssembler::new(TextureFormat::Bc1RgbaUnorm);
+        assert_eq!(
+            compressed.insert(&[], tile),
+            Err(ReadbackDecodeError::TextureFormat(
+                TextureFormat::Bc1RgbaUnorm
+            ))
+        );
+    }
+}